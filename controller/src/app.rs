@@ -0,0 +1,314 @@
+use crate::middleware::{auth::authenticate, metrics::track_metrics, request_id::request_id};
+use crate::routes::{admin, audit, auth, health, memo, user};
+use crate::state::AppState;
+use crate::{metrics, openapi};
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware, Router,
+};
+use shared::settings::Settings;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tracing::{Level, Span};
+
+pub fn app(state: AppState, settings: &Settings) -> Router {
+    let mut router = Router::new()
+        .nest("/users", user::sub_router())
+        .nest("/memos", memo::sub_router())
+        .nest("/auth", auth::sub_router())
+        .nest("/admin", admin::sub_router())
+        .nest("/audit", audit::sub_router())
+        .merge(health::sub_router());
+    if settings.features.openapi {
+        router = router.merge(openapi::sub_router());
+    }
+    if settings.features.metrics {
+        router = router.merge(metrics::sub_router());
+    }
+    router = router.layer(middleware::from_fn_with_state(state.clone(), authenticate));
+    // outside authentication, so requests it rejects are counted too
+    if settings.features.metrics {
+        router = router.layer(middleware::from_fn(track_metrics));
+    }
+    router
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .layer(middleware::from_fn(request_id))
+        .with_state(state)
+}
+
+/// The span every log line of a request is recorded in; runs inside the
+/// request id middleware so the id is already known.
+fn request_span(request: &Request) -> Span {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(MatchedPath::as_str);
+    let request_id = shared::request_id::current();
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        route,
+        request_id,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::mock_state;
+    use axum::{body::Body, http::Request, http::StatusCode};
+    use http_body_util::BodyExt;
+    use repository::repository::{
+        credential::MockCredentialRepository, refresh_token::MockRefreshTokenRepository,
+        role::MockRoleRepository, unit_of_work::MockTransactionManager,
+    };
+    use serde_json::Value;
+    use service::dto::auth::Actor;
+    use service::service::auth::{AuthServiceImpl, MockAuthService};
+    use service::service::token::TokenIssuer;
+    use service::service::user::MockUserService;
+    use shared::{settings::AuthSettings, AppError};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_error_body_carries_request_id() {
+        // given
+        let mut mock_user_service = MockUserService::new();
+        mock_user_service
+            .expect_find_by_id()
+            .returning(|_| Ok(None));
+        let app = app(
+            AppState {
+                user_service: Arc::new(mock_user_service),
+                ..mock_state()
+            },
+            &Settings::default(),
+        );
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/users/99")
+                    .header("x-request-id", "abc-123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()["x-request-id"], "abc-123");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["requestId"], "abc-123");
+    }
+
+    #[tokio::test]
+    async fn test_revoked_permission_is_refused_at_once() {
+        // given
+        let mut mock_role_repository = MockRoleRepository::new();
+        mock_role_repository
+            .expect_find_roles_for_user()
+            .returning(|_| Ok(vec![]));
+        mock_role_repository
+            .expect_find_permissions_for_user()
+            .returning(|_| Ok(vec![]));
+        let token_issuer = TokenIssuer::new(&AuthSettings {
+            keys: HashMap::from([(
+                "test".to_string(),
+                "a-test-secret-that-is-long-enough-0000".to_string(),
+            )]),
+            active_kid: "test".to_string(),
+            ..AuthSettings::default()
+        });
+        // issued while the user was still an auditor
+        let access_token = token_issuer
+            .issue_access_token(&Actor {
+                user_id: 2,
+                roles: vec!["auditor".to_string()],
+                permissions: vec!["audit:read".to_string()],
+            })
+            .unwrap();
+        let auth_service = AuthServiceImpl::new(
+            Arc::new(MockCredentialRepository::new()),
+            Arc::new(MockRefreshTokenRepository::new()),
+            Arc::new(mock_role_repository),
+            Arc::new(MockTransactionManager::new()),
+            token_issuer,
+        );
+        let app = app(
+            AppState {
+                auth_service: Arc::new(auth_service),
+                ..mock_state()
+            },
+            &Settings::default(),
+        );
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/audit")
+                    .header("authorization", format!("Bearer {access_token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_openapi_json_is_served() {
+        // given
+        let app = app(mock_state(), &Settings::default());
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/openapi.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert!(body["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(body["paths"]["/users/{id}"]["get"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_openapi_can_be_disabled() {
+        // given
+        let mut settings = Settings::default();
+        settings.features.openapi = false;
+        let app = app(mock_state(), &settings);
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/openapi.json")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_generated() {
+        // given
+        let app = app(mock_state(), &Settings::default());
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/unknown")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert!(response.headers().contains_key("x-request-id"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_token_is_counted() {
+        // given
+        let mut mock_auth_service = MockAuthService::new();
+        mock_auth_service
+            .expect_authenticate()
+            .returning(|_| Err(AppError::Unauthorized));
+        let app = app(
+            AppState {
+                auth_service: Arc::new(mock_auth_service),
+                ..mock_state()
+            },
+            &Settings::default(),
+        );
+        let rejected = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/memos/7")
+                    .header("authorization", "Bearer expired")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(rejected.status(), StatusCode::UNAUTHORIZED);
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(
+            body.contains(r#"http_requests_total{method="GET",path="/memos/{id}",status="401"}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metrics_are_labelled_by_route() {
+        // given
+        let mut mock_user_service = MockUserService::new();
+        mock_user_service
+            .expect_find_by_id()
+            .returning(|_| Ok(None));
+        let app = app(
+            AppState {
+                user_service: Arc::new(mock_user_service),
+                ..mock_state()
+            },
+            &Settings::default(),
+        );
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/users/42")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(
+            body.contains(r#"http_requests_total{method="GET",path="/users/{id}",status="404"}"#)
+        );
+        assert!(body.contains("http_request_duration_seconds_bucket{"));
+        assert!(!body.contains("/users/42"));
+    }
+}
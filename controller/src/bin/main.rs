@@ -1,24 +1,152 @@
 //! Run with
 //!
 //! ```not_rust
-//! cargo run -p example-hello-world
+//! cargo run -p controller
+//! cargo run -p controller -- migrate status
 //! ```
 
-use axum::Router;
-use controller::routes::user::sub_router;
+use clap::Parser;
+use controller::app::app;
+use controller::cli::{Cli, Command, MigrateCommand};
+use controller::shutdown;
 use controller::state::state;
+use controller::telemetry;
+use controller::worker::{events, purge};
+use repository::infra::backend::Backend;
+use repository::infra::migration::{self, Migrations};
+use shared::settings::Settings;
+use sqlx::migrate::{Migrate, MigrateError};
+use sqlx::Pool;
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+    let settings = match Settings::load() {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(settings).await,
+        Command::Migrate { command } => migrate(settings, command).await,
+    }
+}
+
+async fn serve(settings: Settings) {
+    let telemetry = match telemetry::init(&settings.log) {
+        Ok(telemetry) => telemetry,
+        Err(err) => {
+            eprintln!("failed to set up tracing: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let state = state(&settings).await;
+    let backend = state.backend.clone();
+    let readiness = state.readiness.clone();
+    let workers = CancellationToken::new();
+    let mut jobs = Vec::new();
+    if settings.purge.enabled {
+        jobs.push(purge::spawn(
+            state.user_service.clone(),
+            settings.purge.retention(),
+            settings.purge.interval(),
+            workers.clone(),
+        ));
+    }
+    if settings.events.enabled {
+        jobs.push(events::spawn(
+            state.event_service.clone(),
+            settings.events.poll_interval(),
+            workers.clone(),
+        ));
+    }
+
     // build our application with a route
-    let app = Router::new()
-        .nest("/users", sub_router())
-        .with_state(state().await);
-
-    // run it
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000")
-        .await
-        .unwrap();
-    println!("listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await.unwrap();
+    let app = app(state, &settings);
+
+    // run it until SIGINT/SIGTERM
+    let address = settings.server.address().unwrap();
+    let listener = tokio::net::TcpListener::bind(address).await.unwrap();
+    tracing::info!("listening on {}", listener.local_addr().unwrap());
+    let drain_timeout = settings.server.shutdown_timeout();
+    let drained = shutdown::serve(
+        listener,
+        app,
+        readiness,
+        shutdown::signal(),
+        settings.server.shutdown_drain_delay(),
+        drain_timeout,
+    )
+    .await;
+    if !drained {
+        tracing::warn!(
+            ?drain_timeout,
+            "dropping requests still running after the drain timeout"
+        );
+    }
+
+    workers.cancel();
+    for job in jobs {
+        if tokio::time::timeout(drain_timeout, job).await.is_err() {
+            tracing::warn!("a background job did not stop in time");
+        }
+    }
+    if let Some(backend) = backend {
+        if tokio::time::timeout(drain_timeout, backend.close())
+            .await
+            .is_err()
+        {
+            tracing::warn!("database connections did not close in time");
+        }
+    }
+    tracing::info!("shut down");
+    telemetry.shutdown();
+}
+
+async fn migrate(settings: Settings, command: MigrateCommand) {
+    let result = match Backend::connect(&settings.database).await {
+        Backend::Postgres(pool, _) => run_migrations(&pool, command).await,
+        Backend::Sqlite(pool) => run_migrations(&pool, command).await,
+        Backend::Memory(_) => {
+            eprintln!("{} databases have no migrations", settings.database.url);
+            std::process::exit(1);
+        }
+    };
+    if let Err(err) = result {
+        eprintln!("migration failed: {}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run_migrations<DB: Migrations>(
+    pool: &Pool<DB>,
+    command: MigrateCommand,
+) -> Result<(), MigrateError>
+where
+    DB::Connection: Migrate,
+{
+    match command {
+        MigrateCommand::Up => migration::up(pool).await.map(|_| {
+            println!("migrations are up to date");
+        }),
+        MigrateCommand::Down { steps } => migration::down(pool, steps).await.map(|versions| {
+            for version in versions {
+                println!("reverted {}", version);
+            }
+        }),
+        MigrateCommand::Status => migration::status(pool).await.map(|migrations| {
+            for migration in migrations {
+                let state = if migration.applied {
+                    "applied"
+                } else {
+                    "pending"
+                };
+                println!("{} {} {}", migration.version, state, migration.description);
+            }
+        }),
+    }
 }
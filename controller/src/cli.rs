@@ -0,0 +1,58 @@
+use clap::{Parser, Subcommand};
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Memo app server")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum Command {
+    /// Run the HTTP server (default)
+    Serve,
+    /// Manage the database schema
+    Migrate {
+        #[command(subcommand)]
+        command: MigrateCommand,
+    },
+}
+
+#[derive(Subcommand, Debug, PartialEq)]
+pub enum MigrateCommand {
+    /// Apply all pending migrations
+    Up,
+    /// Revert the last N applied migrations
+    Down {
+        #[arg(default_value_t = 1)]
+        steps: usize,
+    },
+    /// List migrations and whether they are applied
+    Status,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Cli::parse_from(["controller"]).command, None);
+        assert_eq!(
+            Cli::parse_from(["controller", "serve"]).command,
+            Some(Command::Serve)
+        );
+        assert_eq!(
+            Cli::parse_from(["controller", "migrate", "down", "2"]).command,
+            Some(Command::Migrate {
+                command: MigrateCommand::Down { steps: 2 }
+            })
+        );
+        assert_eq!(
+            Cli::parse_from(["controller", "migrate", "down"]).command,
+            Some(Command::Migrate {
+                command: MigrateCommand::Down { steps: 1 }
+            })
+        );
+    }
+}
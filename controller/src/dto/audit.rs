@@ -0,0 +1,61 @@
+use repository::repository::audit::AuditFilter;
+use serde::{Deserialize, Serialize};
+use service::dto::audit::AuditEvent;
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEventResponse {
+    pub id: i64,
+    /// Absent when the system made the change, e.g. the purge job.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor_id: Option<i32>,
+    /// `create`, `update`, `delete`, `restore` or `purge`.
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: i32,
+    /// The fields that changed, as they were before; `null` on creation.
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<serde_json::Value>,
+    /// The fields that changed, as they are after; `null` on deletion.
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub created_at: String,
+}
+
+impl From<AuditEvent> for AuditEventResponse {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            id: event.id,
+            actor_id: event.actor_id,
+            action: event.action,
+            entity_type: event.entity_type,
+            entity_id: event.entity_id,
+            before: event.before,
+            after: event.after,
+            request_id: event.request_id,
+            created_at: event.created_at.to_string(),
+        }
+    }
+}
+
+/// Filters of `GET /audit`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditFilterParams {
+    /// Type of the entity: `users`, `memos` or `user_roles`.
+    pub entity: Option<String>,
+    /// Id of the entity; needs `entity`.
+    pub id: Option<i32>,
+}
+
+impl From<AuditFilterParams> for AuditFilter {
+    fn from(params: AuditFilterParams) -> Self {
+        Self {
+            entity_type: params.entity,
+            entity_id: params.id,
+        }
+    }
+}
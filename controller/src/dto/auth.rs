@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use service::dto::auth::{Login, Registration, TokenPair, EMAIL_MAX_LENGTH, PASSWORD_MIN_LENGTH};
+use service::dto::user::NAME_MAX_LENGTH;
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Deserialize, ToSchema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterRequest {
+    #[schema(min_length = 1, max_length = 255)]
+    #[validate(length(min = 1, max = NAME_MAX_LENGTH))]
+    pub name: String,
+    #[schema(format = Email, max_length = 255)]
+    #[validate(email, length(max = EMAIL_MAX_LENGTH))]
+    pub email: String,
+    #[schema(min_length = 8)]
+    #[validate(length(min = PASSWORD_MIN_LENGTH))]
+    pub password: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginRequest {
+    #[validate(length(min = 1))]
+    pub email: String,
+    #[validate(length(min = 1))]
+    pub password: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshTokenRequest {
+    #[validate(length(min = 1))]
+    pub refresh_token: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Always `Bearer`.
+    pub token_type: String,
+    /// Seconds until the access token expires.
+    pub expires_in: u64,
+}
+
+impl From<RegisterRequest> for Registration {
+    fn from(request: RegisterRequest) -> Self {
+        Self {
+            name: request.name,
+            email: request.email,
+            password: request.password,
+        }
+    }
+}
+
+impl From<LoginRequest> for Login {
+    fn from(request: LoginRequest) -> Self {
+        Self {
+            email: request.email,
+            password: request.password,
+        }
+    }
+}
+
+impl From<TokenPair> for TokenResponse {
+    fn from(tokens: TokenPair) -> Self {
+        Self {
+            access_token: tokens.access_token,
+            refresh_token: tokens.refresh_token,
+            token_type: "Bearer".to_string(),
+            expires_in: tokens.expires_in,
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthResponse {
+    /// `ok`, or `unavailable` when any check is down.
+    pub status: String,
+    /// Result of each dependency check by name, e.g. `database`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<String, CheckResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckResponse {
+    /// `up` or `down`.
+    pub status: String,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl HealthResponse {
+    pub fn new(checks: BTreeMap<String, CheckResponse>) -> Self {
+        let status = if checks.values().all(CheckResponse::is_up) {
+            "ok"
+        } else {
+            "unavailable"
+        };
+        Self {
+            status: status.to_string(),
+            checks,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
+
+impl CheckResponse {
+    pub fn up(latency_ms: u64) -> Self {
+        Self {
+            status: "up".to_string(),
+            latency_ms,
+            error: None,
+        }
+    }
+
+    pub fn down(latency_ms: u64, error: impl ToString) -> Self {
+        Self {
+            status: "down".to_string(),
+            latency_ms,
+            error: Some(error.to_string()),
+        }
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.status == "up"
+    }
+}
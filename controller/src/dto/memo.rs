@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use service::dto::memo::{Memo, TITLE_MAX_LENGTH};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoResponse {
+    pub id: i32,
+    pub user_id: i32,
+    pub title: String,
+    pub content: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoRequest {
+    #[schema(min_length = 1, max_length = 255)]
+    #[validate(length(min = 1, max = TITLE_MAX_LENGTH))]
+    pub title: String,
+    pub content: String,
+}
+
+impl From<Memo> for MemoResponse {
+    fn from(memo: Memo) -> Self {
+        Self {
+            id: memo.id,
+            user_id: memo.user_id,
+            title: memo.title,
+            content: memo.content,
+            created_at: memo.created_at.to_string(),
+            updated_at: memo.updated_at.to_string(),
+        }
+    }
+}
+
+/// The memo, without an owner: that is the user who sends the request.
+impl From<MemoRequest> for Memo {
+    fn from(request: MemoRequest) -> Self {
+        Self {
+            id: 0,
+            user_id: 0,
+            title: request.title,
+            content: request.content,
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
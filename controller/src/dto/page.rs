@@ -0,0 +1,109 @@
+use axum::{
+    http::HeaderName,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use shared::query::{ListQuery, Page, SortField};
+use shared::AppError;
+use utoipa::{IntoParams, ToSchema};
+
+pub const X_TOTAL_COUNT: HeaderName = HeaderName::from_static("x-total-count");
+
+/// Pagination and sorting parameters accepted by every list endpoint.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+    /// Page size, 1 to 100 (default 20).
+    pub limit: Option<u32>,
+    /// `nextCursor` of the previous page.
+    pub cursor: Option<String>,
+    /// Comma separated fields, `-` for descending, e.g. `name,-created_at`.
+    pub sort: Option<String>,
+}
+
+impl PageParams {
+    pub fn into_query<F: SortField, Q>(self, filter: Q) -> Result<ListQuery<F, Q>, AppError> {
+        ListQuery::new(
+            filter,
+            self.sort.as_deref(),
+            self.limit,
+            self.cursor.as_deref(),
+        )
+    }
+}
+
+/// One page of a list. The number of matching items across all pages is
+/// sent in the `X-Total-Count` header.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PageResponse<T> {
+    pub items: Vec<T>,
+    /// Pass as `cursor` to fetch the next page; absent on the last page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(skip)]
+    #[schema(ignore)]
+    pub total: i64,
+}
+
+impl<T, U: From<T>> From<Page<T>> for PageResponse<U> {
+    fn from(page: Page<T>) -> Self {
+        Self {
+            items: page.items.into_iter().map(U::from).collect(),
+            next_cursor: page.next_cursor.map(|cursor| cursor.encode()),
+            total: page.total,
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for PageResponse<T> {
+    fn into_response(self) -> Response {
+        let total = self.total;
+        ([(X_TOTAL_COUNT, total.to_string())], Json(self)).into_response()
+    }
+}
+
+/// Parses a query parameter timestamp given as `2025-02-10T12:00:00`,
+/// `2025-02-10 12:00:00` or `2025-02-10` (midnight).
+pub fn parse_timestamp(name: &str, value: &str) -> Result<chrono::NaiveDateTime, AppError> {
+    value
+        .parse::<chrono::NaiveDateTime>()
+        .or_else(|_| chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+        .or_else(|_| {
+            value
+                .parse::<chrono::NaiveDate>()
+                .map(|date| date.and_time(chrono::NaiveTime::MIN))
+        })
+        .map_err(|_| AppError::BadRequest(format!("{name} is not a valid timestamp")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        // given
+        let expected =
+            chrono::NaiveDateTime::parse_from_str("2025-02-10 12:00:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap();
+        // then
+        assert_eq!(
+            parse_timestamp("t", "2025-02-10T12:00:00").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_timestamp("t", "2025-02-10 12:00:00").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_timestamp("t", "2025-02-10").unwrap(),
+            expected - chrono::Duration::hours(12)
+        );
+        assert!(matches!(
+            parse_timestamp("t", "yesterday"),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+}
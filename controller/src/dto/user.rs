@@ -1,6 +1,10 @@
+use crate::dto::page::parse_timestamp;
+use repository::repository::user::UserFilter;
 use serde::{Deserialize, Serialize};
-use service::dto::user::User;
-use utoipa::ToSchema;
+use service::dto::user::{User, NAME_MAX_LENGTH};
+use shared::AppError;
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
 
 #[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -9,14 +13,27 @@ pub struct UserResponse {
     pub name: String,
     pub created_at: String,
     pub updated_at: String,
+    /// Set while the user is in the trash.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<String>,
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct UserRequest {
+    #[schema(min_length = 1, max_length = 255)]
+    #[validate(length(min = 1, max = NAME_MAX_LENGTH))]
     pub name: String,
 }
 
+/// Body of a JSON Merge Patch on a user; omitted fields stay unchanged.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserMergePatch {
+    #[schema(min_length = 1, max_length = 255)]
+    pub name: Option<String>,
+}
+
 impl From<User> for UserResponse {
     fn from(user: User) -> Self {
         Self {
@@ -24,17 +41,47 @@ impl From<User> for UserResponse {
             name: user.name,
             created_at: user.created_at.to_string(),
             updated_at: user.updated_at.to_string(),
+            deleted_at: user.deleted_at.map(|deleted_at| deleted_at.to_string()),
         }
     }
 }
 
-impl Into<User> for UserRequest {
-    fn into(self) -> User {
-        User {
+impl From<UserRequest> for User {
+    fn from(request: UserRequest) -> Self {
+        Self {
             id: 0,
-            name: self.name,
+            name: request.name,
             created_at: chrono::Utc::now().naive_utc(),
             updated_at: chrono::Utc::now().naive_utc(),
+            version: 0,
+            deleted_at: None,
         }
     }
 }
+
+/// Filters of `GET /users`.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserFilterParams {
+    /// Case-insensitive substring of the name.
+    pub name_contains: Option<String>,
+    /// Only users created after this time, e.g. `2025-02-10T00:00:00`.
+    pub created_after: Option<String>,
+    /// Also list deleted users (admins only).
+    pub include_deleted: Option<bool>,
+}
+
+impl TryFrom<UserFilterParams> for UserFilter {
+    type Error = AppError;
+
+    fn try_from(params: UserFilterParams) -> Result<Self, AppError> {
+        Ok(Self {
+            name_contains: params.name_contains,
+            created_after: params
+                .created_after
+                .map(|value| parse_timestamp("created_after", &value))
+                .transpose()?,
+            include_deleted: params.include_deleted.unwrap_or(false),
+        })
+    }
+}
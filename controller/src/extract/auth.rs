@@ -0,0 +1,87 @@
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::request::Parts,
+};
+use service::dto::auth::Actor;
+use shared::AppError;
+use std::convert::Infallible;
+
+/// The caller identified by the `Authorization: Bearer <access token>` header.
+///
+/// [`crate::middleware::auth::authenticate`] resolves the token; handlers that
+/// take an `AuthUser` reject unauthenticated requests with 401.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthUser(pub Actor);
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, AppError> {
+        parts
+            .extensions
+            .get::<AuthUser>()
+            .cloned()
+            .ok_or(AppError::Unauthorized)
+    }
+}
+
+/// `Option<AuthUser>` is `None` for anonymous requests instead of rejecting them.
+impl<S: Send + Sync> OptionalFromRequestParts<S> for AuthUser {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Option<Self>, Infallible> {
+        Ok(parts.extensions.get::<AuthUser>().cloned())
+    }
+}
+
+/// A member holding `permissions`, for requests built in tests.
+#[cfg(test)]
+pub fn auth_user(user_id: i32, permissions: &[&str]) -> AuthUser {
+    AuthUser(Actor {
+        user_id,
+        roles: vec!["member".to_string()],
+        permissions: permissions.iter().map(|p| p.to_string()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, http::StatusCode, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new().route(
+            "/",
+            get(|AuthUser(actor): AuthUser| async move { actor.user_id.to_string() }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_request_is_accepted() {
+        // when
+        let response = app()
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .extension(auth_user(7, &[]))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_request_is_rejected() {
+        // when
+        let response = app()
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,264 @@
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, Request},
+    http::{header, HeaderMap},
+};
+use serde::de::DeserializeOwned;
+use serde_json::error::Category;
+use service::dto::patch::Patch;
+use shared::{validation::FieldViolation, AppError};
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+/// A JSON request body that deserialized and passed its `validator` rules.
+///
+/// Unlike `axum::Json`, every rejection uses the JSON error format: 415 without
+/// an `application/json` content type, 400 for malformed JSON and 422 listing
+/// each missing, mistyped or invalid field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<S, T> FromRequest<S> for ValidatedJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, AppError> {
+        if media_type(req.headers()).as_deref() != Some("application/json") {
+            return Err(AppError::UnsupportedMediaType);
+        }
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|rejection| AppError::BadRequest(rejection.body_text()))?;
+        let value: T = deserialize(&body)?;
+        value
+            .validate()
+            .map_err(|errors| AppError::Validation(violations(&errors)))?;
+        Ok(Self(value))
+    }
+}
+
+/// Media type of an RFC 7396 JSON Merge Patch body.
+pub const MERGE_PATCH_JSON: &str = "application/merge-patch+json";
+/// Media type of an RFC 6902 JSON Patch body.
+pub const JSON_PATCH_JSON: &str = "application/json-patch+json";
+
+/// A `PATCH` body, read as JSON Merge Patch or JSON Patch by its content type.
+///
+/// Any other content type is rejected with 415.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchJson(pub Patch);
+
+impl<S: Send + Sync> FromRequest<S> for PatchJson {
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, AppError> {
+        let media_type = media_type(req.headers());
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|rejection| AppError::BadRequest(rejection.body_text()))?;
+        match media_type.as_deref() {
+            Some(MERGE_PATCH_JSON) => Ok(Self(Patch::Merge(deserialize(&body)?))),
+            Some(JSON_PATCH_JSON) => Ok(Self(Patch::Json(deserialize(&body)?))),
+            _ => Err(AppError::UnsupportedMediaType),
+        }
+    }
+}
+
+/// The lower-cased `Content-Type` without parameters such as `charset`.
+pub fn media_type(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    let media_type = content_type.split(';').next()?.trim();
+    Some(media_type.to_ascii_lowercase())
+}
+
+/// Deserializes `body`, reporting type mismatches as field violations.
+pub fn deserialize<T: DeserializeOwned>(body: &[u8]) -> Result<T, AppError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|err| {
+        let path = err.path().to_string();
+        let err = err.into_inner();
+        match err.classify() {
+            Category::Data => AppError::Validation(vec![FieldViolation::from_json(&path, &err)]),
+            _ => malformed(&err),
+        }
+    })?;
+    deserializer.end().map_err(|err| malformed(&err))?;
+    Ok(value)
+}
+
+fn malformed(err: &serde_json::Error) -> AppError {
+    AppError::BadRequest(format!("Malformed JSON: {err}"))
+}
+
+/// Flattens `errors` into violations named like the camelCase JSON fields.
+pub fn violations(errors: &ValidationErrors) -> Vec<FieldViolation> {
+    let mut violations = vec![];
+    collect(errors, "", &mut violations);
+    violations.sort_by(|a, b| a.field.cmp(&b.field));
+    violations
+}
+
+fn collect(errors: &ValidationErrors, prefix: &str, violations: &mut Vec<FieldViolation>) {
+    for (field, kind) in errors.errors() {
+        let path = match prefix {
+            "" => camel_case(field),
+            prefix => format!("{prefix}.{}", camel_case(field)),
+        };
+        match kind {
+            ValidationErrorsKind::Field(errors) => {
+                violations.extend(
+                    errors.iter().map(|error| {
+                        FieldViolation::new(&path, error.code.as_ref(), message(error))
+                    }),
+                );
+            }
+            ValidationErrorsKind::Struct(errors) => collect(errors, &path, violations),
+            ValidationErrorsKind::List(items) => {
+                for (index, errors) in items {
+                    collect(errors, &format!("{path}[{index}]"), violations);
+                }
+            }
+        }
+    }
+}
+
+fn camel_case(field: &str) -> String {
+    let mut words = field.split('_');
+    let mut camel = words.next().unwrap_or_default().to_string();
+    for word in words {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel.extend(first.to_uppercase());
+            camel.push_str(chars.as_str());
+        }
+    }
+    camel
+}
+
+fn message(error: &ValidationError) -> String {
+    if let Some(message) = &error.message {
+        return message.to_string();
+    }
+    let min = error.params.get("min");
+    let max = error.params.get("max");
+    let unit = if error.code == "length" {
+        " characters"
+    } else {
+        ""
+    };
+    match (error.code.as_ref(), min, max) {
+        ("length" | "range", Some(min), Some(max)) => {
+            format!("must be between {min} and {max}{unit}")
+        }
+        ("length" | "range", Some(min), None) => format!("must be at least {min}{unit}"),
+        ("length" | "range", None, Some(max)) => format!("must be at most {max}{unit}"),
+        ("email", _, _) => "must be an email address".to_string(),
+        ("required", _, _) => "is required".to_string(),
+        _ => "is invalid".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::post, Router};
+    use http_body_util::BodyExt;
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    #[derive(Deserialize, Validate)]
+    #[serde(rename_all = "camelCase")]
+    struct Payload {
+        #[validate(length(min = 1, max = 5))]
+        display_name: String,
+        #[validate(range(min = 1))]
+        count: i32,
+    }
+
+    async fn send(content_type: &str, body: &str) -> (StatusCode, Value) {
+        let app = Router::new().route(
+            "/",
+            post(|ValidatedJson(payload): ValidatedJson<Payload>| async move {
+                payload.display_name
+            }),
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/")
+                    .header(header::CONTENT_TYPE, content_type)
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+
+    #[tokio::test]
+    async fn test_valid_payload() {
+        // when
+        let (status, _) = send(
+            "application/json; charset=utf-8",
+            r#"{"displayName":"Ann","count":1}"#,
+        )
+        .await;
+        // then
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_rule_violations() {
+        // when
+        let (status, body) = send("application/json", r#"{"displayName":"","count":0}"#).await;
+        // then
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "VALIDATION_FAILED");
+        assert_eq!(
+            body["violations"],
+            json!([
+                {"field": "count", "code": "range", "message": "must be at least 1"},
+                {"field": "displayName", "code": "length", "message": "must be between 1 and 5 characters"},
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_and_mistyped_fields() {
+        // when
+        let (_, missing) = send("application/json", r#"{"displayName":"Ann"}"#).await;
+        let (status, mistyped) = send("application/json", r#"{"displayName":1,"count":1}"#).await;
+        // then
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            missing["violations"],
+            json!([{"field": "count", "code": "required", "message": "is required"}])
+        );
+        assert_eq!(mistyped["violations"][0]["field"], "displayName");
+        assert_eq!(mistyped["violations"][0]["code"], "type");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_json() {
+        // when
+        let (status, body) = send("application/json", r#"{"displayName":"#).await;
+        // then
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "BAD_REQUEST");
+    }
+
+    #[tokio::test]
+    async fn test_wrong_content_type() {
+        // when
+        let (status, body) = send("text/plain", r#"{"displayName":"Ann","count":1}"#).await;
+        // then
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(body["code"], "UNSUPPORTED_MEDIA_TYPE");
+    }
+}
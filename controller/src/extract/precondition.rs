@@ -0,0 +1,120 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderValue},
+};
+use shared::AppError;
+use std::convert::Infallible;
+
+/// The strong entity tag of a resource at `version`, e.g. `"3"`.
+pub fn etag(version: i32) -> HeaderValue {
+    HeaderValue::from_str(&format!("\"{version}\"")).expect("a quoted number is a valid header")
+}
+
+/// The versions an `If-Match` header accepts, if it restricts them.
+///
+/// No header or `*` impose no version; a list of tags lets the write through
+/// at any of them. Tags that are not ours can never match, so a header with
+/// nothing else fails with 412 straight away.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IfMatch(pub Option<Vec<i32>>);
+
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, AppError> {
+        let Some(value) = parts.headers.get(header::IF_MATCH) else {
+            return Ok(Self(None));
+        };
+        let tags = entity_tags(value).ok_or(AppError::PreconditionFailed)?;
+        if tags.contains(&"*") {
+            return Ok(Self(None));
+        }
+        // If-Match compares strongly, so weak tags never match
+        let versions: Vec<i32> = tags.iter().filter_map(|tag| version(tag)).collect();
+        if versions.is_empty() {
+            return Err(AppError::PreconditionFailed);
+        }
+        Ok(Self(Some(versions)))
+    }
+}
+
+/// The entity tags of an `If-None-Match` header.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IfNoneMatch(Option<HeaderValue>);
+
+impl IfNoneMatch {
+    /// Whether the client already holds the representation at `version`.
+    pub fn matches(&self, version: i32) -> bool {
+        let Some(tags) = self.0.as_ref().and_then(entity_tags) else {
+            return false;
+        };
+        // If-None-Match compares weakly
+        tags.iter()
+            .any(|tag| *tag == "*" || self::version(tag.trim_start_matches("W/")) == Some(version))
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for IfNoneMatch {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Infallible> {
+        Ok(Self(parts.headers.get(header::IF_NONE_MATCH).cloned()))
+    }
+}
+
+fn entity_tags(value: &HeaderValue) -> Option<Vec<&str>> {
+    let value = value.to_str().ok()?;
+    Some(value.split(',').map(str::trim).collect())
+}
+
+fn version(tag: &str) -> Option<i32> {
+    tag.strip_prefix('"')?.strip_suffix('"')?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    async fn if_match(value: &str) -> Result<IfMatch, AppError> {
+        let (mut parts, _) = Request::builder()
+            .header(header::IF_MATCH, value)
+            .body(())
+            .unwrap()
+            .into_parts();
+        IfMatch::from_request_parts(&mut parts, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_if_match() {
+        assert_eq!(if_match("\"3\"").await.unwrap(), IfMatch(Some(vec![3])));
+        assert_eq!(if_match("*").await.unwrap(), IfMatch(None));
+        assert!(matches!(
+            if_match("W/\"3\"").await,
+            Err(AppError::PreconditionFailed)
+        ));
+        assert!(matches!(
+            if_match("\"abc\"").await,
+            Err(AppError::PreconditionFailed)
+        ));
+        assert_eq!(
+            if_match("\"1\", \"2\"").await.unwrap(),
+            IfMatch(Some(vec![1, 2]))
+        );
+        assert_eq!(
+            if_match("\"abc\", W/\"1\", \"2\"").await.unwrap(),
+            IfMatch(Some(vec![2]))
+        );
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let header = |value: &str| IfNoneMatch(Some(HeaderValue::from_str(value).unwrap()));
+        assert!(header("\"3\"").matches(3));
+        assert!(header("W/\"3\"").matches(3));
+        assert!(header("\"1\", \"3\"").matches(3));
+        assert!(header("*").matches(3));
+        assert!(!header("\"2\"").matches(3));
+        assert!(!IfNoneMatch::default().matches(3));
+    }
+}
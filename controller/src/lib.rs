@@ -1,7 +1,38 @@
+pub mod app;
+pub mod cli;
 pub mod dto {
+    pub mod audit;
+    pub mod auth;
+    pub mod health;
+    pub mod memo;
+    pub mod page;
     pub mod user;
 }
+pub mod extract {
+    pub mod auth;
+    pub mod json;
+    pub mod precondition;
+}
+pub mod metrics;
+pub mod middleware {
+    pub mod auth;
+    pub mod metrics;
+    pub mod permission;
+    pub mod request_id;
+}
+pub mod openapi;
 pub mod routes {
+    pub mod admin;
+    pub mod audit;
+    pub mod auth;
+    pub mod health;
+    pub mod memo;
     pub mod user;
 }
+pub mod shutdown;
 pub mod state;
+pub mod telemetry;
+pub mod worker {
+    pub mod events;
+    pub mod purge;
+}
@@ -0,0 +1,41 @@
+use crate::state::AppState;
+use axum::{extract::State, http::header, response::IntoResponse, routing::get, Router};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use std::sync::OnceLock;
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The process-wide Prometheus recorder, installed on first use.
+pub fn handle() -> PrometheusHandle {
+    static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+    HANDLE
+        .get_or_init(|| {
+            PrometheusBuilder::new()
+                .set_buckets(LATENCY_BUCKETS)
+                .expect("Invalid histogram buckets")
+                .install_recorder()
+                .expect("Failed to install the metrics recorder")
+        })
+        .clone()
+}
+
+/// Serves the metrics in Prometheus text format at `/metrics`.
+pub fn sub_router() -> Router<AppState> {
+    let handle = handle();
+    Router::new().route(
+        "/metrics",
+        get(|State(state): State<AppState>| async move {
+            if let Some(backend) = &state.backend {
+                backend.record_metrics().await;
+            }
+            (
+                [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+                handle.render(),
+            )
+                .into_response()
+        }),
+    )
+}
@@ -0,0 +1,122 @@
+use crate::extract::auth::AuthUser;
+use crate::state::AppState;
+use axum::{
+    extract::{Request, State},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    response::Response,
+};
+use shared::AppError;
+
+/// Resolves a bearer token into an [`AuthUser`] request extension and
+/// serves the request on behalf of that user (see [`shared::caller`]).
+///
+/// Requests without an `Authorization` header pass through anonymously;
+/// a header that is not a valid bearer token is rejected with 401.
+pub async fn authenticate(
+    State(AppState { auth_service, .. }): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if let Some(value) = request.headers().get(AUTHORIZATION) {
+        let token = value
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(AppError::Unauthorized)?;
+        let actor = auth_service.authenticate(token.trim()).await?;
+        let caller = actor.user_id.to_string();
+        request.extensions_mut().insert(AuthUser(actor));
+        return Ok(shared::caller::scope(caller, next.run(request)).await);
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::mock_state;
+    use axum::{
+        body::Body, http::StatusCode, middleware::from_fn_with_state, routing::get, Router,
+    };
+    use service::{dto::auth::Actor, service::auth::MockAuthService};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        let mut mock_auth_service = MockAuthService::new();
+        mock_auth_service
+            .expect_authenticate()
+            .returning(|token| match token {
+                "valid" => Ok(Actor {
+                    user_id: 7,
+                    roles: vec![],
+                    permissions: vec![],
+                }),
+                _ => Err(AppError::Unauthorized),
+            });
+        let state = AppState {
+            auth_service: Arc::new(mock_auth_service),
+            ..mock_state()
+        };
+        Router::new()
+            .route(
+                "/",
+                get(|user: Option<AuthUser>| async move {
+                    // the request is served on behalf of the authenticated user
+                    let caller = shared::caller::current();
+                    assert_eq!(
+                        caller,
+                        user.map(|AuthUser(actor)| actor.user_id.to_string())
+                    );
+                    caller.unwrap_or_else(|| "anonymous".to_string())
+                }),
+            )
+            .layer(from_fn_with_state(state.clone(), authenticate))
+            .with_state(state)
+    }
+
+    async fn call(authorization: Option<&str>) -> (StatusCode, String) {
+        let mut request = Request::builder().uri("/");
+        if let Some(authorization) = authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+        let response = app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token_is_resolved() {
+        // when
+        let (status, body) = call(Some("Bearer valid")).await;
+        // then
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "7");
+    }
+
+    #[tokio::test]
+    async fn test_request_without_token_is_anonymous() {
+        // when
+        let (status, body) = call(None).await;
+        // then
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, "anonymous");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_token_is_rejected() {
+        for authorization in ["Bearer wrong", "Basic dXNlcjpwdw=="] {
+            // when
+            let (status, _) = call(Some(authorization)).await;
+            // then
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+        }
+    }
+}
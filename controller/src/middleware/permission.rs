@@ -0,0 +1,111 @@
+use crate::extract::auth::AuthUser;
+use axum::{extract::Request, response::IntoResponse, response::Response};
+use shared::AppError;
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Rejects requests whose [`AuthUser`] lacks `permission`: 401 when the
+/// caller is anonymous, 403 when the permission is missing.
+///
+/// ```text
+/// Router::new()
+///     .route("/", post(create_user))
+///     .route_layer(require_permission("users:write"))
+/// ```
+pub fn require_permission(permission: &'static str) -> RequirePermissionLayer {
+    RequirePermissionLayer { permission }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequirePermissionLayer {
+    permission: &'static str,
+}
+
+impl<S> Layer<S> for RequirePermissionLayer {
+    type Service = RequirePermission<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequirePermission {
+            inner,
+            permission: self.permission,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequirePermission<S> {
+    inner: S,
+    permission: &'static str,
+}
+
+impl<S> Service<Request> for RequirePermission<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let denied = match request.extensions().get::<AuthUser>() {
+            None => Some(AppError::Unauthorized),
+            Some(AuthUser(actor)) if !actor.has_permission(self.permission) => {
+                Some(AppError::Forbidden)
+            }
+            Some(_) => None,
+        };
+        match denied {
+            Some(err) => Box::pin(async move { Ok(err.into_response()) }),
+            None => Box::pin(self.inner.call(request)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::auth::auth_user;
+    use axum::{body::Body, http::StatusCode, routing::post, Router};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", post(|| async { StatusCode::NO_CONTENT }))
+            .route_layer(require_permission("users:write"))
+    }
+
+    async fn call(user: Option<AuthUser>) -> StatusCode {
+        let mut request = Request::builder().uri("/").method("POST");
+        if let Some(user) = user {
+            request = request.extension(user);
+        }
+        app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_require_permission() {
+        assert_eq!(
+            call(Some(auth_user(1, &["users:write"]))).await,
+            StatusCode::NO_CONTENT
+        );
+        assert_eq!(
+            call(Some(auth_user(1, &["memos:write"]))).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(call(None).await, StatusCode::UNAUTHORIZED);
+    }
+}
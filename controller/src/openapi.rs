@@ -0,0 +1,93 @@
+use crate::routes::{
+    admin::AdminApi, audit::AuditApi, auth::AuthApi, health::HealthApi, memo::MemoApi,
+    user::UserApi,
+};
+use crate::state::AppState;
+use axum::Router;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "memo-app", description = "Memo app API"),
+    modifiers(&BearerAuth)
+)]
+pub struct ApiDoc;
+
+/// Registers the `bearer_auth` scheme referenced by routes that take an `AuthUser`.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
+
+impl ApiDoc {
+    /// Builds the document for every router mounted by [`crate::app::app`].
+    pub fn build() -> utoipa::openapi::OpenApi {
+        let mut api = ApiDoc::openapi();
+        api.merge(UserApi::openapi());
+        api.merge(MemoApi::openapi());
+        api.merge(AuthApi::openapi());
+        api.merge(AdminApi::openapi());
+        api.merge(AuditApi::openapi());
+        api.merge(HealthApi::openapi());
+        api
+    }
+}
+
+/// Serves the OpenAPI document at `/openapi.json` and Swagger UI at `/swagger-ui`.
+pub fn sub_router() -> Router<AppState> {
+    SwaggerUi::new("/swagger-ui")
+        .url("/openapi.json", ApiDoc::build())
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_doc_contains_all_routes() {
+        // when
+        let api = ApiDoc::build();
+        // then
+        let paths: Vec<&String> = api.paths.paths.keys().collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/admin/users/{id}/roles",
+                "/admin/users/{id}/roles/{role}",
+                "/audit",
+                "/auth/login",
+                "/auth/logout",
+                "/auth/refresh",
+                "/auth/register",
+                "/healthz",
+                "/memos",
+                "/memos/{id}",
+                "/readyz",
+                "/users",
+                "/users/{id}",
+                "/users/{id}/restore"
+            ]
+        );
+        let components = api.components.unwrap();
+        assert!(components.security_schemes.contains_key("bearer_auth"));
+        let schemas = components.schemas;
+        assert!(schemas.contains_key("UserResponse"));
+        assert!(schemas.contains_key("MemoRequest"));
+        assert!(schemas.contains_key("ErrorResponse"));
+    }
+}
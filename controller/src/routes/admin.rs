@@ -0,0 +1,230 @@
+use crate::extract::auth::AuthUser;
+use crate::middleware::permission::require_permission;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, put},
+    Json, Router,
+};
+use shared::{AppError, ErrorResponse};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(get_user_roles, grant_role, revoke_role),
+    components(schemas(ErrorResponse)),
+    tags((name = "admin", description = "Administration"))
+)]
+pub struct AdminApi;
+
+pub fn sub_router() -> Router<AppState> {
+    Router::new()
+        .route("/users/{id}/roles", get(get_user_roles))
+        .route(
+            "/users/{id}/roles/{role}",
+            put(grant_role).delete(revoke_role),
+        )
+        .route_layer(require_permission("roles:manage"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}/roles",
+    tag = "admin",
+    params(("id" = i32, Path, description = "User id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Roles held by the user", body = [String]),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing roles:manage permission", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn get_user_roles(
+    State(AppState { role_service, .. }): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<String>>, AppError> {
+    let roles = role_service.get_user_roles(id).await?;
+    Ok(Json(roles))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/users/{id}/roles/{role}",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "User id"),
+        ("role" = String, Path, description = "Role name, e.g. admin, member or read-only"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Role granted"),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing roles:manage permission", body = ErrorResponse),
+        (status = 404, description = "User or role not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn grant_role(
+    State(AppState { role_service, .. }): State<AppState>,
+    AuthUser(actor): AuthUser,
+    Path((id, role)): Path<(i32, String)>,
+) -> Result<StatusCode, AppError> {
+    role_service.grant_role(&actor, id, role).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{id}/roles/{role}",
+    tag = "admin",
+    params(
+        ("id" = i32, Path, description = "User id"),
+        ("role" = String, Path, description = "Role name"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Role revoked"),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing roles:manage permission", body = ErrorResponse),
+        (status = 404, description = "User does not hold the role", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn revoke_role(
+    State(AppState { role_service, .. }): State<AppState>,
+    AuthUser(actor): AuthUser,
+    Path((id, role)): Path<(i32, String)>,
+) -> Result<StatusCode, AppError> {
+    role_service.revoke_role(&actor, id, role).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::auth::auth_user;
+    use crate::state::mock_state;
+    use axum::{
+        body::Body,
+        http::{self, Request, StatusCode},
+    };
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use service::service::role::MockRoleService;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn admin() -> AuthUser {
+        auth_user(1, &["roles:manage"])
+    }
+
+    #[tokio::test]
+    async fn test_get_user_roles() {
+        // given
+        let mut mock_role_service = MockRoleService::new();
+        mock_role_service
+            .expect_get_user_roles()
+            .withf(|user_id| *user_id == 2)
+            .returning(|_| Ok(vec!["member".to_string()]));
+        let app = sub_router().with_state(AppState {
+            role_service: Arc::new(mock_role_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/users/2/roles")
+                    .extension(admin())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, json!(["member"]));
+    }
+
+    #[tokio::test]
+    async fn test_grant_role() {
+        // given
+        let mut mock_role_service = MockRoleService::new();
+        mock_role_service
+            .expect_grant_role()
+            .withf(|actor, user_id, role| {
+                actor.user_id == 1 && *user_id == 2 && role == "read-only"
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let app = sub_router().with_state(AppState {
+            role_service: Arc::new(mock_role_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/users/2/roles/read-only")
+                    .method(http::Method::PUT)
+                    .extension(admin())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_role_not_held() {
+        // given
+        let mut mock_role_service = MockRoleService::new();
+        mock_role_service
+            .expect_revoke_role()
+            .returning(|_, _, _| Err(AppError::NotFound));
+        let app = sub_router().with_state(AppState {
+            role_service: Arc::new(mock_role_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/users/2/roles/admin")
+                    .method(http::Method::DELETE)
+                    .extension(admin())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_grant_role_requires_permission() {
+        // given
+        let app = sub_router().with_state(mock_state());
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/users/2/roles/admin")
+                    .method(http::Method::PUT)
+                    .extension(auth_user(2, &["users:write"]))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::dto::audit::{AuditEventResponse, AuditFilterParams};
+use crate::dto::page::{PageParams, PageResponse};
+use crate::middleware::permission::require_permission;
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Router,
+};
+use shared::{AppError, ErrorResponse};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(get_events),
+    components(schemas(AuditEventResponse, PageResponse<AuditEventResponse>, ErrorResponse)),
+    tags((name = "audit", description = "Audit log"))
+)]
+pub struct AuditApi;
+
+pub fn sub_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_events))
+        .route_layer(require_permission("audit:read"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "audit",
+    params(PageParams, AuditFilterParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (
+            status = 200,
+            description = "A page of recorded changes, oldest first by default",
+            body = PageResponse<AuditEventResponse>,
+            headers(("X-Total-Count" = i64, description = "Events matching the filter"))
+        ),
+        (status = 400, description = "Invalid pagination, sort or filter", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing audit:read permission", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn get_events(
+    State(AppState { audit_service, .. }): State<AppState>,
+    Query(page): Query<PageParams>,
+    Query(filter): Query<AuditFilterParams>,
+) -> Result<PageResponse<AuditEventResponse>, AppError> {
+    let query = page.into_query(filter.into())?;
+    let events = audit_service.get_events(query).await?;
+    Ok(events.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::auth::auth_user;
+    use crate::state::mock_state;
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode},
+    };
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use service::dto::audit::AuditEvent;
+    use service::service::audit::MockAuditService;
+    use shared::query::Page;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_get_events() {
+        // given
+        let mut mock_audit_service = MockAuditService::new();
+        mock_audit_service
+            .expect_get_events()
+            .withf(|query| {
+                query.filter.entity_type.as_deref() == Some("users")
+                    && query.filter.entity_id == Some(1)
+                    && query.limit == 10
+            })
+            .returning(|_| {
+                Ok(Page {
+                    items: vec![AuditEvent {
+                        id: 7,
+                        actor_id: Some(1),
+                        action: "update".to_string(),
+                        entity_type: "users".to_string(),
+                        entity_id: 1,
+                        before: Some(json!({"name": "Alice"})),
+                        after: Some(json!({"name": "Alicia"})),
+                        request_id: Some("abc-123".to_string()),
+                        created_at: chrono::NaiveDateTime::parse_from_str(
+                            "2025-02-27 12:00:00",
+                            "%Y-%m-%d %H:%M:%S",
+                        )
+                        .unwrap(),
+                    }],
+                    next_cursor: None,
+                    total: 1,
+                })
+            });
+        let app = sub_router().with_state(AppState {
+            audit_service: Arc::new(mock_audit_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/?entity=users&id=1&limit=10")
+                    .extension(auth_user(1, &["audit:read"]))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-total-count"], "1");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"items": [{
+                "id": 7,
+                "actorId": 1,
+                "action": "update",
+                "entityType": "users",
+                "entityId": 1,
+                "before": {"name": "Alice"},
+                "after": {"name": "Alicia"},
+                "requestId": "abc-123",
+                "createdAt": "2025-02-27 12:00:00"
+            }]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_memo_events() {
+        // given
+        let mut mock_audit_service = MockAuditService::new();
+        mock_audit_service
+            .expect_get_events()
+            .withf(|query| {
+                query.filter.entity_type.as_deref() == Some("memos")
+                    && query.filter.entity_id == Some(3)
+            })
+            .times(1)
+            .returning(|_| {
+                Ok(Page {
+                    items: vec![],
+                    next_cursor: None,
+                    total: 0,
+                })
+            });
+        let app = sub_router().with_state(AppState {
+            audit_service: Arc::new(mock_audit_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/?entity=memos&id=3")
+                    .extension(auth_user(1, &["audit:read"]))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_get_events_requires_permission() {
+        // given
+        let app = sub_router().with_state(mock_state());
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/?entity=users&id=1")
+                    .extension(auth_user(2, &["users:write", "roles:manage"]))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
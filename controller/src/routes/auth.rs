@@ -0,0 +1,358 @@
+use crate::dto::auth::{LoginRequest, RefreshTokenRequest, RegisterRequest, TokenResponse};
+use crate::dto::user::UserResponse;
+use crate::extract::json::ValidatedJson;
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use shared::{AppError, ErrorResponse};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(register, login, refresh, logout),
+    components(schemas(
+        RegisterRequest,
+        LoginRequest,
+        RefreshTokenRequest,
+        TokenResponse,
+        UserResponse,
+        ErrorResponse
+    )),
+    tags((name = "auth", description = "Authentication"))
+)]
+pub struct AuthApi;
+
+pub fn sub_router() -> Router<AppState> {
+    Router::new()
+        .route("/register", post(register))
+        .route("/login", post(login))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/register",
+    tag = "auth",
+    request_body = RegisterRequest,
+    responses(
+        (status = 201, description = "User registered", body = UserResponse),
+        (status = 409, description = "Email already registered", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn register(
+    State(AppState { auth_service, .. }): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<RegisterRequest>,
+) -> Result<(StatusCode, Json<UserResponse>), AppError> {
+    let user = auth_service.register(payload.into()).await?;
+
+    Ok((StatusCode::CREATED, Json(user.into())))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Tokens issued", body = TokenResponse),
+        (status = 401, description = "Invalid email or password", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn login(
+    State(AppState { auth_service, .. }): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<LoginRequest>,
+) -> Result<Json<TokenResponse>, AppError> {
+    let tokens = auth_service.login(payload.into()).await?;
+    Ok(Json(tokens.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    tag = "auth",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 200, description = "Tokens rotated", body = TokenResponse),
+        (status = 401, description = "Refresh token unknown, expired or revoked", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn refresh(
+    State(AppState { auth_service, .. }): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<RefreshTokenRequest>,
+) -> Result<Json<TokenResponse>, AppError> {
+    let tokens = auth_service.refresh(payload.refresh_token).await?;
+    Ok(Json(tokens.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    tag = "auth",
+    request_body = RefreshTokenRequest,
+    responses(
+        (status = 204, description = "Refresh token revoked"),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn logout(
+    State(AppState { auth_service, .. }): State<AppState>,
+    ValidatedJson(payload): ValidatedJson<RefreshTokenRequest>,
+) -> Result<StatusCode, AppError> {
+    auth_service.logout(payload.refresh_token).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::mock_state;
+    use axum::{
+        body::Body,
+        http::{self, Request, StatusCode},
+    };
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use service::{
+        dto::{auth::TokenPair, user::User},
+        service::auth::MockAuthService,
+    };
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn timestamp() -> chrono::NaiveDateTime {
+        chrono::NaiveDateTime::parse_from_str("2021-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn token_pair() -> TokenPair {
+        TokenPair {
+            access_token: "access".to_string(),
+            refresh_token: "refresh".to_string(),
+            expires_in: 900,
+        }
+    }
+
+    fn post(uri: &str, body: Value) -> Request<Body> {
+        Request::builder()
+            .uri(uri)
+            .method(http::Method::POST)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_register() {
+        // given
+        let mut mock_auth_service = MockAuthService::new();
+        mock_auth_service
+            .expect_register()
+            .withf(|registration| {
+                registration.email == "alice@example.com" && registration.password == "password"
+            })
+            .returning(|registration| {
+                Ok(User {
+                    id: 3,
+                    name: registration.name,
+                    version: 1,
+                    deleted_at: None,
+                    created_at: timestamp(),
+                    updated_at: timestamp(),
+                })
+            });
+        let app = sub_router().with_state(AppState {
+            auth_service: Arc::new(mock_auth_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(post(
+                "/register",
+                json!({"name": "Alice", "email": "alice@example.com", "password": "password"}),
+            ))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["id"], 3);
+        assert_eq!(body["name"], "Alice");
+    }
+
+    #[tokio::test]
+    async fn test_register_invalid_payload() {
+        // given
+        let app = sub_router().with_state(mock_state());
+        // when
+        let response = app
+            .oneshot(post(
+                "/register",
+                json!({"name": "Alice", "email": "alice", "password": "short"}),
+            ))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "VALIDATION_FAILED");
+        assert_eq!(body["violations"][0]["field"], "email");
+        assert_eq!(body["violations"][1]["field"], "password");
+    }
+
+    #[tokio::test]
+    async fn test_register_duplicate_email() {
+        // given
+        let mut mock_auth_service = MockAuthService::new();
+        mock_auth_service
+            .expect_register()
+            .returning(|_| Err(AppError::Conflict));
+        let app = sub_router().with_state(AppState {
+            auth_service: Arc::new(mock_auth_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(post(
+                "/register",
+                json!({"name": "Alice", "email": "alice@example.com", "password": "password"}),
+            ))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_login() {
+        // given
+        let mut mock_auth_service = MockAuthService::new();
+        mock_auth_service
+            .expect_login()
+            .returning(|_| Ok(token_pair()));
+        let app = sub_router().with_state(AppState {
+            auth_service: Arc::new(mock_auth_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(post(
+                "/login",
+                json!({"email": "alice@example.com", "password": "password"}),
+            ))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "accessToken": "access",
+                "refreshToken": "refresh",
+                "tokenType": "Bearer",
+                "expiresIn": 900
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_login_invalid_credentials() {
+        // given
+        let mut mock_auth_service = MockAuthService::new();
+        mock_auth_service
+            .expect_login()
+            .returning(|_| Err(AppError::Unauthorized));
+        let app = sub_router().with_state(AppState {
+            auth_service: Arc::new(mock_auth_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(post(
+                "/login",
+                json!({"email": "alice@example.com", "password": "wrong"}),
+            ))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "UNAUTHORIZED");
+    }
+
+    #[tokio::test]
+    async fn test_refresh() {
+        // given
+        let mut mock_auth_service = MockAuthService::new();
+        mock_auth_service
+            .expect_refresh()
+            .withf(|refresh_token| refresh_token == "old")
+            .returning(|_| Ok(token_pair()));
+        let app = sub_router().with_state(AppState {
+            auth_service: Arc::new(mock_auth_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(post("/refresh", json!({"refreshToken": "old"})))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["refreshToken"], "refresh");
+    }
+
+    #[tokio::test]
+    async fn test_refresh_rejected() {
+        // given
+        let mut mock_auth_service = MockAuthService::new();
+        mock_auth_service
+            .expect_refresh()
+            .returning(|_| Err(AppError::Unauthorized));
+        let app = sub_router().with_state(AppState {
+            auth_service: Arc::new(mock_auth_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(post("/refresh", json!({"refreshToken": "revoked"})))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_logout() {
+        // given
+        let mut mock_auth_service = MockAuthService::new();
+        mock_auth_service
+            .expect_logout()
+            .withf(|refresh_token| refresh_token == "refresh")
+            .times(1)
+            .returning(|_| Ok(()));
+        let app = sub_router().with_state(AppState {
+            auth_service: Arc::new(mock_auth_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(post("/logout", json!({"refreshToken": "refresh"})))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+}
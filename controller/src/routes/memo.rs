@@ -0,0 +1,452 @@
+use crate::dto::memo::{MemoRequest, MemoResponse};
+use crate::extract::auth::AuthUser;
+use crate::extract::json::ValidatedJson;
+use crate::middleware::permission::require_permission;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use service::dto::memo::Memo;
+use shared::{AppError, ErrorResponse};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(get_memos, find_by_id, create_memo, update_memo, delete_memo),
+    components(schemas(MemoRequest, MemoResponse, ErrorResponse)),
+    tags((name = "memos", description = "Memo management"))
+)]
+pub struct MemoApi;
+
+pub fn sub_router() -> Router<AppState> {
+    let read = Router::new()
+        .route("/", get(get_memos))
+        .route("/{id}", get(find_by_id));
+    let write = Router::new()
+        .route("/", post(create_memo))
+        .route("/{id}", put(update_memo))
+        .route_layer(require_permission("memos:write"));
+    let remove = Router::new()
+        .route("/{id}", delete(delete_memo))
+        .route_layer(require_permission("memos:delete"));
+    read.merge(write).merge(remove)
+}
+
+#[utoipa::path(
+    get,
+    path = "/memos",
+    tag = "memos",
+    responses(
+        (status = 200, description = "List memos", body = [MemoResponse]),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn get_memos(
+    State(AppState { memo_service, .. }): State<AppState>,
+) -> Result<Json<Vec<MemoResponse>>, AppError> {
+    let memos = memo_service.get_memos().await?;
+    let body = memos.into_iter().map(|memo| memo.into()).collect();
+    Ok(Json(body))
+}
+
+#[utoipa::path(
+    get,
+    path = "/memos/{id}",
+    tag = "memos",
+    params(("id" = i32, Path, description = "Memo id")),
+    responses(
+        (status = 200, description = "Memo found", body = MemoResponse),
+        (status = 404, description = "Memo not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn find_by_id(
+    State(AppState { memo_service, .. }): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<Json<MemoResponse>, AppError> {
+    let memo = memo_service
+        .find_by_id(id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    Ok(Json(memo.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/memos",
+    tag = "memos",
+    request_body = MemoRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Memo created", body = MemoResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing memos:write permission", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn create_memo(
+    State(AppState { memo_service, .. }): State<AppState>,
+    AuthUser(actor): AuthUser,
+    ValidatedJson(payload): ValidatedJson<MemoRequest>,
+) -> Result<(StatusCode, Json<MemoResponse>), AppError> {
+    let memo = memo_service.create_memo(&actor, payload.into()).await?;
+
+    Ok((StatusCode::CREATED, Json(memo.into())))
+}
+
+#[utoipa::path(
+    put,
+    path = "/memos/{id}",
+    tag = "memos",
+    params(("id" = i32, Path, description = "Memo id")),
+    request_body = MemoRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Memo updated", body = MemoResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing memos:write permission, or not the memo's owner", body = ErrorResponse),
+        (status = 404, description = "Memo not found", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn update_memo(
+    State(AppState { memo_service, .. }): State<AppState>,
+    AuthUser(actor): AuthUser,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<MemoRequest>,
+) -> Result<Json<MemoResponse>, AppError> {
+    let mut memo: Memo = payload.into();
+    memo.id = id;
+    let memo = memo_service.update_memo(&actor, memo).await?;
+    Ok(Json(memo.into()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/memos/{id}",
+    tag = "memos",
+    params(("id" = i32, Path, description = "Memo id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Memo deleted"),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing memos:delete permission, or not the memo's owner", body = ErrorResponse),
+        (status = 404, description = "Memo not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn delete_memo(
+    State(AppState { memo_service, .. }): State<AppState>,
+    AuthUser(actor): AuthUser,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    memo_service.delete_memo(&actor, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::auth::auth_user;
+    use crate::state::mock_state;
+    use axum::{
+        body::Body,
+        http::{self, Request, StatusCode},
+    };
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use service::{dto::memo::Memo, service::memo::MockMemoService};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn timestamp() -> chrono::NaiveDateTime {
+        chrono::NaiveDateTime::parse_from_str("2021-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn app(mock_memo_service: MockMemoService) -> Router {
+        sub_router().with_state(AppState {
+            memo_service: Arc::new(mock_memo_service),
+            ..mock_state()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_memos() {
+        // given
+        let mut mock_memo_service = MockMemoService::new();
+        mock_memo_service.expect_get_memos().returning(|| {
+            Ok(vec![
+                Memo {
+                    id: 1,
+                    user_id: 1,
+                    title: "Groceries".to_string(),
+                    content: "Milk, eggs and bread".to_string(),
+                    created_at: timestamp(),
+                    updated_at: timestamp(),
+                },
+                Memo {
+                    id: 2,
+                    user_id: 2,
+                    title: "Meeting".to_string(),
+                    content: "Prepare slides for Monday".to_string(),
+                    created_at: timestamp(),
+                    updated_at: timestamp(),
+                },
+            ])
+        });
+        let app = app(mock_memo_service);
+        // when
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!([
+                {
+                    "id": 1,
+                    "userId": 1,
+                    "title": "Groceries",
+                    "content": "Milk, eggs and bread",
+                    "createdAt": "2021-01-01 00:00:00",
+                    "updatedAt": "2021-01-01 00:00:00"
+                },
+                {
+                    "id": 2,
+                    "userId": 2,
+                    "title": "Meeting",
+                    "content": "Prepare slides for Monday",
+                    "createdAt": "2021-01-01 00:00:00",
+                    "updatedAt": "2021-01-01 00:00:00"
+                }
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_by_id() {
+        // given
+        let mut mock_memo_service = MockMemoService::new();
+        mock_memo_service.expect_find_by_id().returning(|id| {
+            Ok(Some(Memo {
+                id,
+                user_id: 1,
+                title: "Groceries".to_string(),
+                content: "Milk, eggs and bread".to_string(),
+                created_at: timestamp(),
+                updated_at: timestamp(),
+            }))
+        });
+        let app = app(mock_memo_service);
+        // when
+        let response = app
+            .oneshot(Request::builder().uri("/1").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "id": 1,
+                "userId": 1,
+                "title": "Groceries",
+                "content": "Milk, eggs and bread",
+                "createdAt": "2021-01-01 00:00:00",
+                "updatedAt": "2021-01-01 00:00:00"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_by_id_not_found() {
+        // given
+        let mut mock_memo_service = MockMemoService::new();
+        mock_memo_service
+            .expect_find_by_id()
+            .returning(|_| Ok(None));
+        let app = app(mock_memo_service);
+        // when
+        let response = app
+            .oneshot(Request::builder().uri("/99").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_create_memo() {
+        // given
+        let mut mock_memo_service = MockMemoService::new();
+        mock_memo_service
+            .expect_create_memo()
+            .withf(|actor, _| actor.user_id == 1)
+            .returning(|actor, memo| {
+                Ok(Memo {
+                    id: 3,
+                    user_id: actor.user_id,
+                    title: memo.title.clone(),
+                    content: memo.content.clone(),
+                    created_at: timestamp(),
+                    updated_at: timestamp(),
+                })
+            });
+        let app = app(mock_memo_service);
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .method(http::Method::POST)
+                    .extension(auth_user(1, &["memos:write", "memos:delete"]))
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        json!({"title": "Todo", "content": "Water the plants"}).to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["id"], 3);
+        assert_eq!(body["userId"], 1);
+        assert_eq!(body["title"], "Todo");
+        assert_eq!(body["content"], "Water the plants");
+    }
+
+    #[tokio::test]
+    async fn test_update_memo() {
+        // given
+        let mut mock_memo_service = MockMemoService::new();
+        mock_memo_service
+            .expect_update_memo()
+            .withf(|actor, memo| actor.user_id == 1 && memo.id == 2)
+            .returning(|_, memo| {
+                Ok(Memo {
+                    id: memo.id,
+                    user_id: memo.user_id,
+                    title: memo.title.clone(),
+                    content: memo.content.clone(),
+                    created_at: timestamp(),
+                    updated_at: timestamp(),
+                })
+            });
+        let app = app(mock_memo_service);
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/2")
+                    .method(http::Method::PUT)
+                    .extension(auth_user(1, &["memos:write", "memos:delete"]))
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        json!({"title": "Shopping", "content": "Milk and cheese"}).to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["id"], 2);
+        assert_eq!(body["title"], "Shopping");
+        assert_eq!(body["content"], "Milk and cheese");
+    }
+
+    #[tokio::test]
+    async fn test_delete_memo() {
+        // given
+        let mut mock_memo_service = MockMemoService::new();
+        mock_memo_service
+            .expect_delete_memo()
+            .withf(|actor, id| actor.user_id == 1 && *id == 1)
+            .returning(|_, _| Ok(()));
+        let app = app(mock_memo_service);
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/1")
+                    .method(http::Method::DELETE)
+                    .extension(auth_user(1, &["memos:write", "memos:delete"]))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_update_memo_of_another_user_is_forbidden() {
+        // given
+        let mut mock_memo_service = MockMemoService::new();
+        mock_memo_service
+            .expect_update_memo()
+            .returning(|_, _| Err(AppError::Forbidden));
+        let app = app(mock_memo_service);
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/2")
+                    .method(http::Method::PUT)
+                    .extension(auth_user(1, &["memos:write", "memos:delete"]))
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        json!({"title": "Shopping", "content": "Milk and cheese"}).to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_delete_memo_of_another_user_is_forbidden() {
+        // given
+        let mut mock_memo_service = MockMemoService::new();
+        mock_memo_service
+            .expect_delete_memo()
+            .returning(|_, _| Err(AppError::Forbidden));
+        let app = app(mock_memo_service);
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/1")
+                    .method(http::Method::DELETE)
+                    .extension(auth_user(1, &["memos:write", "memos:delete"]))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
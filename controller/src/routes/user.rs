@@ -1,74 +1,290 @@
-use crate::dto::user::{UserRequest, UserResponse};
+use crate::dto::page::{PageParams, PageResponse};
+use crate::dto::user::{UserFilterParams, UserMergePatch, UserRequest, UserResponse};
+use crate::extract::auth::AuthUser;
+use crate::extract::json::{PatchJson, ValidatedJson};
+use crate::extract::precondition::{etag, IfMatch, IfNoneMatch};
+use crate::middleware::permission::require_permission;
 use crate::state::AppState;
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    routing::get,
+    extract::{Path, Query, State},
+    http::{header, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use service::dto::user::User;
+use shared::{AppError, ErrorResponse};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(get_users, find_by_id, create_user, update_user, patch_user, delete_user, restore_user),
+    components(schemas(
+        UserRequest,
+        UserMergePatch,
+        UserResponse,
+        PageResponse<UserResponse>,
+        ErrorResponse
+    )),
+    tags((name = "users", description = "User management"))
+)]
+pub struct UserApi;
 
 pub fn sub_router() -> Router<AppState> {
-    Router::new()
-        .route("/", get(get_users).post(create_user))
-        .route(
-            "/{id}",
-            get(find_by_id).put(update_user).delete(delete_user),
-        )
+    let read = Router::new()
+        .route("/", get(get_users))
+        .route("/{id}", get(find_by_id));
+    let write = Router::new()
+        .route("/", post(create_user))
+        .route("/{id}", put(update_user).patch(patch_user))
+        .route_layer(require_permission("users:write"));
+    let remove = Router::new()
+        .route("/{id}", delete(delete_user))
+        .route("/{id}/restore", post(restore_user))
+        .route_layer(require_permission("users:delete"));
+    read.merge(write).merge(remove)
 }
 
-async fn get_users(State(AppState { user_service }): State<AppState>) -> Json<Vec<UserResponse>> {
-    let users = user_service.get_users().await.unwrap();
-    let body = users.into_iter().map(|user| user.into()).collect();
-    Json(body)
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    params(PageParams, UserFilterParams),
+    responses(
+        (
+            status = 200,
+            description = "A page of users",
+            body = PageResponse<UserResponse>,
+            headers(("X-Total-Count" = i64, description = "Users matching the filter"))
+        ),
+        (status = 400, description = "Invalid pagination, sort or filter", body = ErrorResponse),
+        (status = 401, description = "include_deleted without an access token", body = ErrorResponse),
+        (status = 403, description = "include_deleted by a non-admin", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn get_users(
+    State(AppState { user_service, .. }): State<AppState>,
+    auth: Option<AuthUser>,
+    Query(page): Query<PageParams>,
+    Query(filter): Query<UserFilterParams>,
+) -> Result<PageResponse<UserResponse>, AppError> {
+    let query = page.into_query(filter.try_into()?)?;
+    let actor = auth.map(|AuthUser(actor)| actor);
+    let users = user_service.get_users(actor, query).await?;
+    Ok(users.into())
 }
 
+#[utoipa::path(
+    get,
+    path = "/users/{id}",
+    tag = "users",
+    params(
+        ("id" = i32, Path, description = "User id"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a cached copy"),
+    ),
+    responses(
+        (status = 200, description = "User found", body = UserResponse,
+            headers(("ETag" = String, description = "Current version of the user"))),
+        (status = 304, description = "The cached copy is current"),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
 async fn find_by_id(
-    State(AppState { user_service }): State<AppState>,
+    State(AppState { user_service, .. }): State<AppState>,
+    if_none_match: IfNoneMatch,
     Path(id): Path<i32>,
-) -> Json<UserResponse> {
-    let user = user_service.find_by_id(id).await.unwrap();
-    Json(user.unwrap().into())
+) -> Result<Response, AppError> {
+    let user = user_service
+        .find_by_id(id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if if_none_match.matches(user.version) {
+        let etag = [(header::ETAG, etag(user.version))];
+        return Ok((StatusCode::NOT_MODIFIED, etag).into_response());
+    }
+    Ok(tagged(user).into_response())
+}
+
+/// The user as the body, with its version as the `ETag`.
+fn tagged(user: User) -> ([(HeaderName, HeaderValue); 1], Json<UserResponse>) {
+    ([(header::ETAG, etag(user.version))], Json(user.into()))
 }
 
+#[utoipa::path(
+    post,
+    path = "/users",
+    tag = "users",
+    request_body = UserRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "User created", body = UserResponse),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing users:write permission", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
 async fn create_user(
-    State(AppState { user_service }): State<AppState>,
-    Json(payload): Json<UserRequest>,
-) -> (StatusCode, Json<UserResponse>) {
-    let user = user_service.create_user(payload.into()).await.unwrap();
+    State(AppState { user_service, .. }): State<AppState>,
+    AuthUser(actor): AuthUser,
+    ValidatedJson(payload): ValidatedJson<UserRequest>,
+) -> Result<(StatusCode, Json<UserResponse>), AppError> {
+    let user = user_service.create_user(&actor, payload.into()).await?;
 
-    (StatusCode::CREATED, Json(user.into()))
+    Ok((StatusCode::CREATED, Json(user.into())))
 }
 
+#[utoipa::path(
+    put,
+    path = "/users/{id}",
+    tag = "users",
+    params(
+        ("id" = i32, Path, description = "User id"),
+        ("If-Match" = Option<String>, Header, description = "ETags the user must still have one of"),
+    ),
+    request_body = UserRequest,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "User updated", body = UserResponse,
+            headers(("ETag" = String, description = "New version of the user"))),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing permission or not the owner", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 412, description = "The user has changed since the If-Match ETag", body = ErrorResponse),
+        (status = 422, description = "Invalid request body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
 async fn update_user(
-    State(AppState { user_service }): State<AppState>,
+    State(AppState { user_service, .. }): State<AppState>,
+    AuthUser(actor): AuthUser,
+    IfMatch(expected_versions): IfMatch,
     Path(id): Path<i32>,
-    Json(payload): Json<UserRequest>,
-) -> Json<UserResponse> {
+    ValidatedJson(payload): ValidatedJson<UserRequest>,
+) -> Result<([(HeaderName, HeaderValue); 1], Json<UserResponse>), AppError> {
     let mut user: User = payload.into();
     user.id = id;
-    let user = user_service.update_user(user).await.unwrap();
-    Json(user.into())
+    let user = user_service
+        .update_user(&actor, user, expected_versions)
+        .await?;
+    Ok(tagged(user))
 }
 
+#[utoipa::path(
+    patch,
+    path = "/users/{id}",
+    tag = "users",
+    params(
+        ("id" = i32, Path, description = "User id"),
+        ("If-Match" = Option<String>, Header, description = "ETags the user must still have one of"),
+    ),
+    request_body(
+        description = "JSON Merge Patch (RFC 7396) or JSON Patch (RFC 6902)",
+        content(
+            (UserMergePatch = "application/merge-patch+json"),
+            (json_patch::Patch = "application/json-patch+json"),
+        ),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "User patched", body = UserResponse,
+            headers(("ETag" = String, description = "New version of the user"))),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing permission or not the owner", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 412, description = "The user has changed since the If-Match ETag", body = ErrorResponse),
+        (status = 415, description = "Neither a merge patch nor a JSON patch", body = ErrorResponse),
+        (status = 422, description = "Unknown field, failed operation or invalid result", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn patch_user(
+    State(AppState { user_service, .. }): State<AppState>,
+    AuthUser(actor): AuthUser,
+    Path(id): Path<i32>,
+    IfMatch(expected_versions): IfMatch,
+    PatchJson(patch): PatchJson,
+) -> Result<([(HeaderName, HeaderValue); 1], Json<UserResponse>), AppError> {
+    let user = user_service
+        .patch_user(&actor, id, patch, expected_versions)
+        .await?;
+    Ok(tagged(user))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{id}",
+    tag = "users",
+    params(
+        ("id" = i32, Path, description = "User id"),
+        ("If-Match" = Option<String>, Header, description = "ETags the user must still have one of"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "User deleted"),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing permission or not the owner", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 412, description = "The user has changed since the If-Match ETag", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
 async fn delete_user(
-    State(AppState { user_service }): State<AppState>,
+    State(AppState { user_service, .. }): State<AppState>,
+    AuthUser(actor): AuthUser,
+    IfMatch(expected_versions): IfMatch,
     Path(id): Path<i32>,
-) -> StatusCode {
-    user_service.delete_user(id).await.unwrap();
-    StatusCode::NO_CONTENT
+) -> Result<StatusCode, AppError> {
+    user_service
+        .delete_user(&actor, id, expected_versions)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/restore",
+    tag = "users",
+    params(("id" = i32, Path, description = "User id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "User restored from the trash", body = UserResponse,
+            headers(("ETag" = String, description = "New version of the user"))),
+        (status = 401, description = "Missing or invalid access token", body = ErrorResponse),
+        (status = 403, description = "Missing permission or not the owner", body = ErrorResponse),
+        (status = 404, description = "No deleted user with this id", body = ErrorResponse),
+        (status = 409, description = "The user's email has been registered again", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    )
+)]
+async fn restore_user(
+    State(AppState { user_service, .. }): State<AppState>,
+    AuthUser(actor): AuthUser,
+    Path(id): Path<i32>,
+) -> Result<([(HeaderName, HeaderValue); 1], Json<UserResponse>), AppError> {
+    let user = user_service.restore_user(&actor, id).await?;
+    Ok(tagged(user))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::extract::auth::auth_user;
+    use crate::state::mock_state;
     use axum::{
         body::Body,
         http::{self, Request, StatusCode},
     };
     use http_body_util::BodyExt;
+    use repository::repository::user::UserSortField;
     use serde_json::{json, Value};
-    use service::{dto::user::User, service::user::MockUserService};
+    use service::{
+        dto::{patch::Patch, user::User},
+        service::user::MockUserService,
+    };
+    use shared::query::{Direction, Page};
     use std::sync::Arc;
     use tower::ServiceExt;
 
@@ -76,51 +292,103 @@ mod tests {
     async fn test_get_users() {
         // given
         let mut mock_user_service = MockUserService::new();
-        mock_user_service.expect_get_users().returning(|| {
-            Ok(vec![
-                User {
-                    id: 1,
-                    name: "Alice".to_string(),
-                    created_at: chrono::NaiveDateTime::parse_from_str(
-                        "2021-01-01 00:00:00",
-                        "%Y-%m-%d %H:%M:%S",
-                    )
-                    .unwrap(),
-                    updated_at: chrono::NaiveDateTime::parse_from_str(
-                        "2021-01-01 00:00:00",
-                        "%Y-%m-%d %H:%M:%S",
-                    )
-                    .unwrap(),
-                },
-                User {
-                    id: 2,
-                    name: "Bob".to_string(),
-                    created_at: chrono::NaiveDateTime::parse_from_str(
-                        "2021-01-01 00:00:00",
-                        "%Y-%m-%d %H:%M:%S",
-                    )
-                    .unwrap(),
-                    updated_at: chrono::NaiveDateTime::parse_from_str(
-                        "2021-01-01 00:00:00",
-                        "%Y-%m-%d %H:%M:%S",
-                    )
-                    .unwrap(),
-                },
-            ])
-        });
+        mock_user_service
+            .expect_get_users()
+            .withf(|actor, query| {
+                actor.is_none()
+                    && query.limit == 2
+                    && query.sort[0].field == UserSortField::Name
+                    && query.sort[0].direction == Direction::Desc
+                    && query.filter.name_contains.as_deref() == Some("o")
+            })
+            .returning(|_, _| {
+                Ok(Page {
+                    items: vec![
+                        User {
+                            id: 1,
+                            name: "Alice".to_string(),
+                            version: 1,
+                            deleted_at: None,
+                            created_at: chrono::NaiveDateTime::parse_from_str(
+                                "2021-01-01 00:00:00",
+                                "%Y-%m-%d %H:%M:%S",
+                            )
+                            .unwrap(),
+                            updated_at: chrono::NaiveDateTime::parse_from_str(
+                                "2021-01-01 00:00:00",
+                                "%Y-%m-%d %H:%M:%S",
+                            )
+                            .unwrap(),
+                        },
+                        User {
+                            id: 2,
+                            name: "Bob".to_string(),
+                            version: 1,
+                            deleted_at: None,
+                            created_at: chrono::NaiveDateTime::parse_from_str(
+                                "2021-01-01 00:00:00",
+                                "%Y-%m-%d %H:%M:%S",
+                            )
+                            .unwrap(),
+                            updated_at: chrono::NaiveDateTime::parse_from_str(
+                                "2021-01-01 00:00:00",
+                                "%Y-%m-%d %H:%M:%S",
+                            )
+                            .unwrap(),
+                        },
+                    ],
+                    next_cursor: None,
+                    total: 2,
+                })
+            });
         let app = sub_router().with_state(AppState {
             user_service: Arc::new(mock_user_service),
+            ..mock_state()
         });
         // when
         let response = app
-            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .oneshot(
+                Request::builder()
+                    .uri("/?limit=2&sort=-name&name_contains=o")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
             .await
             .unwrap();
         // then
         assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-total-count"], "2");
         let body = response.into_body().collect().await.unwrap().to_bytes();
         let body: Value = serde_json::from_slice(&body).unwrap();
-        assert_eq!(body, json!([{"id":1,"name":"Alice"},{"id":2,"name":"Bob"}]));
+        assert_eq!(
+            body,
+            json!({"items": [
+                {"id":1,"name":"Alice","createdAt":"2021-01-01 00:00:00","updatedAt":"2021-01-01 00:00:00"},
+                {"id":2,"name":"Bob","createdAt":"2021-01-01 00:00:00","updatedAt":"2021-01-01 00:00:00"}
+            ]})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_users_invalid_query() {
+        for uri in [
+            "/?sort=password",
+            "/?limit=1000",
+            "/?created_after=yesterday",
+        ] {
+            // given
+            let app = sub_router().with_state(mock_state());
+            // when
+            let response = app
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            // then
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["code"], "BAD_REQUEST");
+        }
     }
 
     #[tokio::test]
@@ -131,6 +399,8 @@ mod tests {
             Ok(Some(User {
                 id,
                 name: "Alice".to_string(),
+                version: 1,
+                deleted_at: None,
                 created_at: chrono::NaiveDateTime::parse_from_str(
                     "2021-01-01 00:00:00",
                     "%Y-%m-%d %H:%M:%S",
@@ -145,6 +415,7 @@ mod tests {
         });
         let app = sub_router().with_state(AppState {
             user_service: Arc::new(mock_user_service),
+            ..mock_state()
         });
         // when
         let response = app
@@ -153,39 +424,162 @@ mod tests {
             .unwrap();
         // then
         assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[http::header::ETAG], "\"1\"");
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        assert_eq!(body, r#"{"id":1,"name":"Alice"}"#);
+        assert_eq!(
+            body,
+            r#"{"id":1,"name":"Alice","createdAt":"2021-01-01 00:00:00","updatedAt":"2021-01-01 00:00:00"}"#
+        );
     }
 
     #[tokio::test]
-    async fn test_create_user() {
+    async fn test_find_by_id_not_modified() {
         // given
         let mut mock_user_service = MockUserService::new();
-        mock_user_service.expect_create_user().returning(|user| {
-            Ok(User {
-                id: 2,
-                name: user.name.clone(),
-                created_at: chrono::NaiveDateTime::parse_from_str(
-                    "2021-01-01 00:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-                updated_at: chrono::NaiveDateTime::parse_from_str(
-                    "2021-01-01 00:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-            })
+        mock_user_service.expect_find_by_id().returning(|id| {
+            Ok(Some(User {
+                id,
+                name: "Alice".to_string(),
+                version: 4,
+                deleted_at: None,
+                created_at: chrono::Utc::now().naive_utc(),
+                updated_at: chrono::Utc::now().naive_utc(),
+            }))
         });
         let app = sub_router().with_state(AppState {
             user_service: Arc::new(mock_user_service),
+            ..mock_state()
         });
         // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/1")
+                    .header(http::header::IF_NONE_MATCH, "\"4\"")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[http::header::ETAG], "\"4\"");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_id_not_found() {
+        // given
+        let mut mock_user_service = MockUserService::new();
+        mock_user_service
+            .expect_find_by_id()
+            .returning(|_| Ok(None));
+        let app = sub_router().with_state(AppState {
+            user_service: Arc::new(mock_user_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(Request::builder().uri("/99").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({"code": "NOT_FOUND", "message": "Resource not found"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_users_internal_server_error() {
+        // given
+        let mut mock_user_service = MockUserService::new();
+        mock_user_service
+            .expect_get_users()
+            .returning(|_, _| Err(AppError::InternalServerError));
+        let app = sub_router().with_state(AppState {
+            user_service: Arc::new(mock_user_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "INTERNAL_SERVER_ERROR");
+    }
+
+    #[tokio::test]
+    async fn test_create_user_invalid_name() {
+        // given
+        let app = sub_router().with_state(mock_state());
+        // when
         let response = app
             .oneshot(
                 Request::builder()
                     .uri("/")
                     .method(http::Method::POST)
+                    .extension(auth_user(1, &["users:write"]))
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(json!({"name": "x".repeat(256)}).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["violations"],
+            json!([{"field": "name", "code": "length", "message": "must be between 1 and 255 characters"}])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_user() {
+        // given
+        let mut mock_user_service = MockUserService::new();
+        mock_user_service
+            .expect_create_user()
+            .withf(|actor, _| actor.user_id == 1)
+            .returning(|_, user| {
+                Ok(User {
+                    id: 2,
+                    name: user.name.clone(),
+                    version: 1,
+                    deleted_at: None,
+                    created_at: chrono::NaiveDateTime::parse_from_str(
+                        "2021-01-01 00:00:00",
+                        "%Y-%m-%d %H:%M:%S",
+                    )
+                    .unwrap(),
+                    updated_at: chrono::NaiveDateTime::parse_from_str(
+                        "2021-01-01 00:00:00",
+                        "%Y-%m-%d %H:%M:%S",
+                    )
+                    .unwrap(),
+                })
+            });
+        let app = sub_router().with_state(AppState {
+            user_service: Arc::new(mock_user_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .method(http::Method::POST)
+                    .extension(auth_user(1, &["users:write", "users:delete"]))
                     .header(http::header::CONTENT_TYPE, "application/json")
                     .body(Body::from(json!({"name": "Alice"}).to_string()))
                     .unwrap(),
@@ -195,31 +589,39 @@ mod tests {
         // then
         assert_eq!(response.status(), StatusCode::CREATED);
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        assert_eq!(body, r#"{"id":2,"name":"Alice"}"#);
+        assert_eq!(
+            body,
+            r#"{"id":2,"name":"Alice","createdAt":"2021-01-01 00:00:00","updatedAt":"2021-01-01 00:00:00"}"#
+        );
     }
 
     #[tokio::test]
     async fn test_update_user() {
         // given
         let mut mock_user_service = MockUserService::new();
-        mock_user_service.expect_update_user().returning(|user| {
-            Ok(User {
-                id: user.id,
-                name: user.name.clone(),
-                created_at: chrono::NaiveDateTime::parse_from_str(
-                    "2021-01-01 00:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-                updated_at: chrono::NaiveDateTime::parse_from_str(
-                    "2021-01-01 00:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-            })
-        });
+        mock_user_service
+            .expect_update_user()
+            .returning(|_, user, _| {
+                Ok(User {
+                    id: user.id,
+                    name: user.name.clone(),
+                    version: 1,
+                    deleted_at: None,
+                    created_at: chrono::NaiveDateTime::parse_from_str(
+                        "2021-01-01 00:00:00",
+                        "%Y-%m-%d %H:%M:%S",
+                    )
+                    .unwrap(),
+                    updated_at: chrono::NaiveDateTime::parse_from_str(
+                        "2021-01-01 00:00:00",
+                        "%Y-%m-%d %H:%M:%S",
+                    )
+                    .unwrap(),
+                })
+            });
         let app = sub_router().with_state(AppState {
             user_service: Arc::new(mock_user_service),
+            ..mock_state()
         });
         // when
         let response = app
@@ -227,6 +629,7 @@ mod tests {
                 Request::builder()
                     .uri("/3")
                     .method(http::Method::PUT)
+                    .extension(auth_user(1, &["users:write", "users:delete"]))
                     .header(http::header::CONTENT_TYPE, "application/json")
                     .body(Body::from(json!({"name": "Alice"}).to_string()))
                     .unwrap(),
@@ -236,16 +639,148 @@ mod tests {
         // then
         assert_eq!(response.status(), StatusCode::OK);
         let body = response.into_body().collect().await.unwrap().to_bytes();
-        assert_eq!(body, r#"{"id":3,"name":"Alice"}"#);
+        assert_eq!(
+            body,
+            r#"{"id":3,"name":"Alice","createdAt":"2021-01-01 00:00:00","updatedAt":"2021-01-01 00:00:00"}"#
+        );
+    }
+
+    fn patch(content_type: &str, body: Value) -> Request<Body> {
+        Request::builder()
+            .uri("/1")
+            .method(http::Method::PATCH)
+            .extension(auth_user(1, &["users:write"]))
+            .header(http::header::CONTENT_TYPE, content_type)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_patch_user() {
+        for (content_type, body) in [
+            ("application/merge-patch+json", json!({"name": "Alicia"})),
+            (
+                "application/json-patch+json",
+                json!([{"op": "replace", "path": "/name", "value": "Alicia"}]),
+            ),
+        ] {
+            // given
+            let mut mock_user_service = MockUserService::new();
+            mock_user_service
+                .expect_patch_user()
+                .withf(move |actor, id, patch, _| {
+                    let expected = match content_type {
+                        "application/merge-patch+json" => matches!(patch, Patch::Merge(_)),
+                        _ => matches!(patch, Patch::Json(_)),
+                    };
+                    actor.user_id == 1 && *id == 1 && expected
+                })
+                .returning(|_, id, _, _| {
+                    Ok(User {
+                        id,
+                        name: "Alicia".to_string(),
+                        version: 1,
+                        deleted_at: None,
+                        created_at: chrono::NaiveDateTime::parse_from_str(
+                            "2021-01-01 00:00:00",
+                            "%Y-%m-%d %H:%M:%S",
+                        )
+                        .unwrap(),
+                        updated_at: chrono::NaiveDateTime::parse_from_str(
+                            "2021-01-01 00:00:00",
+                            "%Y-%m-%d %H:%M:%S",
+                        )
+                        .unwrap(),
+                    })
+                });
+            let app = sub_router().with_state(AppState {
+                user_service: Arc::new(mock_user_service),
+                ..mock_state()
+            });
+            // when
+            let response = app.oneshot(patch(content_type, body)).await.unwrap();
+            // then
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()[http::header::ETAG], "\"1\"");
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["name"], "Alicia");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_patch_user_unsupported_media_type() {
+        // given
+        let app = sub_router().with_state(mock_state());
+        // when
+        let response = app
+            .oneshot(patch("application/json", json!({"name": "Alicia"})))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn test_patch_user_malformed_operations() {
+        // given
+        let app = sub_router().with_state(mock_state());
+        // when
+        let response = app
+            .oneshot(patch(
+                "application/json-patch+json",
+                json!([{"op": "rename", "path": "/name"}]),
+            ))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_if_match() {
+        // given
+        let mut mock_user_service = MockUserService::new();
+        mock_user_service
+            .expect_update_user()
+            .withf(|_, _, versions| *versions == Some(vec![2, 3]))
+            .returning(|_, _, _| Err(AppError::PreconditionFailed));
+        let app = sub_router().with_state(AppState {
+            user_service: Arc::new(mock_user_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/1")
+                    .method(http::Method::PUT)
+                    .extension(auth_user(1, &["users:write"]))
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .header(http::header::IF_MATCH, "\"2\", \"3\"")
+                    .body(Body::from(json!({"name": "Alice"}).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "PRECONDITION_FAILED");
     }
 
     #[tokio::test]
     async fn test_delete_user() {
         // given
         let mut mock_user_service = MockUserService::new();
-        mock_user_service.expect_delete_user().returning(|_| Ok(()));
+        mock_user_service
+            .expect_delete_user()
+            .withf(|actor, id, version| actor.user_id == 1 && *id == 1 && version.is_none())
+            .returning(|_, _, _| Ok(()));
         let app = sub_router().with_state(AppState {
             user_service: Arc::new(mock_user_service),
+            ..mock_state()
         });
         // when
         let response = app
@@ -253,6 +788,7 @@ mod tests {
                 Request::builder()
                     .uri("/1")
                     .method(http::Method::DELETE)
+                    .extension(auth_user(1, &["users:write", "users:delete"]))
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -261,4 +797,176 @@ mod tests {
         // then
         assert_eq!(response.status(), StatusCode::NO_CONTENT);
     }
+
+    #[tokio::test]
+    async fn test_delete_user_requires_authentication() {
+        // given
+        let app = sub_router().with_state(mock_state());
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/1")
+                    .method(http::Method::DELETE)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_requires_permission() {
+        // given
+        let app = sub_router().with_state(mock_state());
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/1")
+                    .method(http::Method::PUT)
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .extension(auth_user(1, &[]))
+                    .body(Body::from(json!({"name": "Alice"}).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_delete_other_user_is_forbidden() {
+        // given
+        let mut mock_user_service = MockUserService::new();
+        mock_user_service
+            .expect_delete_user()
+            .returning(|_, _, _| Err(AppError::Forbidden));
+        let app = sub_router().with_state(AppState {
+            user_service: Arc::new(mock_user_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/2")
+                    .method(http::Method::DELETE)
+                    .extension(auth_user(1, &["users:write", "users:delete"]))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "FORBIDDEN");
+    }
+
+    #[tokio::test]
+    async fn test_get_users_include_deleted() {
+        // given
+        let mut mock_user_service = MockUserService::new();
+        mock_user_service
+            .expect_get_users()
+            .withf(|actor, query| {
+                actor.as_ref().map(|actor| actor.user_id) == Some(1) && query.filter.include_deleted
+            })
+            .returning(|_, _| {
+                Ok(Page {
+                    items: vec![],
+                    next_cursor: None,
+                    total: 0,
+                })
+            });
+        let app = sub_router().with_state(AppState {
+            user_service: Arc::new(mock_user_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/?include_deleted=true")
+                    .extension(auth_user(1, &[]))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_restore_user() {
+        // given
+        let mut mock_user_service = MockUserService::new();
+        mock_user_service
+            .expect_restore_user()
+            .withf(|actor, id| actor.user_id == 1 && *id == 1)
+            .returning(|_, id| {
+                let timestamp = chrono::NaiveDateTime::parse_from_str(
+                    "2021-01-01 00:00:00",
+                    "%Y-%m-%d %H:%M:%S",
+                )
+                .unwrap();
+                Ok(User {
+                    id,
+                    name: "Alice".to_string(),
+                    version: 3,
+                    deleted_at: None,
+                    created_at: timestamp,
+                    updated_at: timestamp,
+                })
+            });
+        let app = sub_router().with_state(AppState {
+            user_service: Arc::new(mock_user_service),
+            ..mock_state()
+        });
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/1/restore")
+                    .method(http::Method::POST)
+                    .extension(auth_user(1, &["users:delete"]))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[http::header::ETAG], "\"3\"");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["id"], 1);
+        assert!(body.get("deletedAt").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_restore_user_requires_permission() {
+        // given
+        let app = sub_router().with_state(mock_state());
+        // when
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/1/restore")
+                    .method(http::Method::POST)
+                    .extension(auth_user(1, &["users:write"]))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
 }
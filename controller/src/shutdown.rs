@@ -0,0 +1,182 @@
+use crate::routes::health::Readiness;
+use axum::Router;
+use std::future::Future;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+/// Resolves on the first SIGINT (Ctrl+C) or SIGTERM.
+pub async fn signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Serves `app` until `shutdown` resolves, then fails readiness and keeps
+/// serving for `drain_delay`, so that load balancers see the failure and
+/// stop routing here. It then stops accepting connections and waits up to
+/// `drain_timeout` for in-flight requests. Returns `false` if requests were
+/// still running at the deadline.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    readiness: Readiness,
+    shutdown: impl Future<Output = ()>,
+    drain_delay: Duration,
+    drain_timeout: Duration,
+) -> bool {
+    let (stop, stopped) = oneshot::channel::<()>();
+    let mut server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = stopped.await;
+            })
+            .await
+    });
+    tokio::select! {
+        result = &mut server => {
+            // the server only stops by itself when accepting fails
+            tracing::error!(?result, "server stopped unexpectedly");
+            return true;
+        }
+        _ = shutdown => {}
+    }
+    tracing::info!(?drain_delay, "shutting down, failing readiness");
+    readiness.drain();
+    tokio::time::sleep(drain_delay).await;
+    tracing::info!("draining connections");
+    let _ = stop.send(());
+    match tokio::time::timeout(drain_timeout, &mut server).await {
+        Ok(_) => true,
+        Err(_) => {
+            server.abort();
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::health;
+    use crate::state::{mock_state, AppState};
+    use axum::routing::get;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    fn slow_app(readiness: Readiness) -> Router {
+        Router::new()
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                    "done"
+                }),
+            )
+            .merge(health::sub_router())
+            .with_state(AppState {
+                readiness,
+                ..mock_state()
+            })
+    }
+
+    /// Sends `GET path` on a new connection, which is left open for the response.
+    async fn request(address: SocketAddr, path: &str) -> TcpStream {
+        let mut client = TcpStream::connect(address).await.unwrap();
+        let request =
+            format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n");
+        client.write_all(request.as_bytes()).await.unwrap();
+        client
+    }
+
+    async fn start(
+        drain_delay: Duration,
+        drain_timeout: Duration,
+    ) -> (
+        SocketAddr,
+        TcpStream,
+        Readiness,
+        tokio::task::JoinHandle<bool>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let readiness = Readiness::new(Duration::from_secs(1));
+        let (trigger, triggered) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve(
+            listener,
+            slow_app(readiness.clone()),
+            readiness.clone(),
+            async {
+                let _ = triggered.await;
+            },
+            drain_delay,
+            drain_timeout,
+        ));
+        let client = request(address, "/slow").await;
+        // let the request reach the handler before shutting down
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        trigger.send(()).unwrap();
+        (address, client, readiness, server)
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_requests_are_drained() {
+        // given
+        let (_, mut client, readiness, server) =
+            start(Duration::ZERO, Duration::from_secs(5)).await;
+        // when
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        // then
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("done"));
+        assert!(server.await.unwrap());
+        assert!(readiness.is_draining());
+    }
+
+    #[tokio::test]
+    async fn test_drain_timeout() {
+        // given
+        let (_, _client, _, server) = start(Duration::ZERO, Duration::from_millis(50)).await;
+        // when
+        let drained = server.await.unwrap();
+        // then
+        assert!(!drained);
+    }
+
+    #[tokio::test]
+    async fn test_readyz_fails_while_requests_are_still_served() {
+        // given
+        let (address, mut slow, _, server) =
+            start(Duration::from_millis(500), Duration::from_secs(5)).await;
+        // when
+        let mut ready = String::new();
+        request(address, "/readyz")
+            .await
+            .read_to_string(&mut ready)
+            .await
+            .unwrap();
+        // then
+        assert!(ready.starts_with("HTTP/1.1 503"));
+        let mut response = String::new();
+        slow.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(server.await.unwrap());
+    }
+}
@@ -1,16 +1,96 @@
-use repository::infra::postgres::pool;
-use repository::repository::user::UserRepositoryImpl;
+use crate::routes::health::Readiness;
+use repository::infra::backend::Backend;
+use repository::infra::cache;
+use service::service::audit::{AuditService, AuditServiceImpl};
+use service::service::auth::{AuthService, AuthServiceImpl};
+use service::service::event::{EventService, EventServiceImpl};
+use service::service::memo::{MemoService, MemoServiceImpl};
+use service::service::policy::OwnershipPolicy;
+use service::service::role::{RoleService, RoleServiceImpl};
+use service::service::sink;
+use service::service::token::TokenIssuer;
 use service::service::user::{UserService, UserServiceImpl};
+use shared::settings::Settings;
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_service: Arc<dyn UserService>,
+    pub memo_service: Arc<dyn MemoService>,
+    pub auth_service: Arc<dyn AuthService>,
+    pub role_service: Arc<dyn RoleService>,
+    pub audit_service: Arc<dyn AuditService>,
+    /// Delivers domain events; run by the dispatcher, not by routes.
+    pub event_service: Arc<dyn EventService>,
+    /// The storage behind the services; `None` when they are mocks.
+    pub backend: Option<Backend>,
+    pub readiness: Readiness,
 }
 
-pub async fn state() -> AppState {
-    let pool = pool().await;
-    let user_repository = Arc::new(UserRepositoryImpl::new(pool));
-    let user_service = Arc::new(UserServiceImpl::new(user_repository));
-    AppState { user_service }
+pub async fn state(settings: &Settings) -> AppState {
+    let backend = Backend::connect(&settings.database).await;
+    if settings.database.migrate_on_startup {
+        backend.migrate().await.expect("Failed to run migrations");
+    }
+    let mut repositories = backend.repositories();
+    if let Some(cache) = cache::connect(&settings.cache) {
+        repositories = repositories.with_user_cache(cache);
+    }
+    let user_service = Arc::new(UserServiceImpl::new(
+        repositories.users,
+        repositories.transactions.clone(),
+        Arc::new(OwnershipPolicy),
+    ));
+    let memo_service = Arc::new(MemoServiceImpl::new(
+        repositories.memos,
+        repositories.transactions.clone(),
+        Arc::new(OwnershipPolicy),
+    ));
+    let auth_service = Arc::new(AuthServiceImpl::new(
+        repositories.credentials,
+        repositories.refresh_tokens,
+        repositories.roles.clone(),
+        repositories.transactions.clone(),
+        TokenIssuer::new(&settings.auth),
+    ));
+    let role_service = Arc::new(RoleServiceImpl::new(
+        repositories.roles,
+        repositories.transactions,
+    ));
+    let audit_service = Arc::new(AuditServiceImpl::new(repositories.audit));
+    let event_service = Arc::new(EventServiceImpl::new(
+        repositories.outbox,
+        sink::from_settings(&settings.events),
+        &settings.events,
+    ));
+    AppState {
+        user_service,
+        memo_service,
+        auth_service,
+        role_service,
+        audit_service,
+        event_service,
+        backend: Some(backend),
+        readiness: Readiness::new(settings.health.check_timeout()),
+    }
+}
+
+/// State whose services are all mocks without expectations; override the
+/// ones a test exercises with struct update syntax.
+#[cfg(test)]
+pub fn mock_state() -> AppState {
+    use service::service::{
+        audit::MockAuditService, auth::MockAuthService, event::MockEventService,
+        memo::MockMemoService, role::MockRoleService, user::MockUserService,
+    };
+    AppState {
+        user_service: Arc::new(MockUserService::new()),
+        memo_service: Arc::new(MockMemoService::new()),
+        auth_service: Arc::new(MockAuthService::new()),
+        role_service: Arc::new(MockRoleService::new()),
+        audit_service: Arc::new(MockAuditService::new()),
+        event_service: Arc::new(MockEventService::new()),
+        backend: None,
+        readiness: Readiness::new(std::time::Duration::from_secs(1)),
+    }
 }
@@ -0,0 +1,157 @@
+use opentelemetry::trace::{TraceError, TracerProvider};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+use shared::settings::LogSettings;
+use tracing::Subscriber;
+use tracing_subscriber::{
+    filter::EnvFilter, fmt::MakeWriter, layer::SubscriberExt, registry::LookupSpan,
+    util::SubscriberInitExt, Layer,
+};
+
+/// Handle on the tracing pipeline installed by [`init`].
+pub struct Telemetry {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Telemetry {
+    /// Exports the spans still buffered for the OTLP collector.
+    pub fn shutdown(self) {
+        if let Some(provider) = self.provider {
+            if let Err(err) = provider.shutdown() {
+                eprintln!("failed to flush spans: {}", err);
+            }
+        }
+    }
+}
+
+/// Installs the global subscriber: logs at `settings.level` in
+/// `settings.format` on stdout and, when `settings.otlp_endpoint` is set,
+/// spans exported to that OTLP collector.
+pub fn init(settings: &LogSettings) -> Result<Telemetry, TraceError> {
+    let provider = settings
+        .otlp_endpoint
+        .as_deref()
+        .map(|endpoint| tracer_provider(endpoint, &settings.service_name))
+        .transpose()?;
+    let otel = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer(settings.service_name.clone()))
+    });
+    tracing_subscriber::registry()
+        .with(EnvFilter::new(&settings.level))
+        .with(fmt_layer(&settings.format, std::io::stdout))
+        .with(otel)
+        .init();
+    Ok(Telemetry { provider })
+}
+
+fn fmt_layer<S, W>(format: &str, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    if format == "json" {
+        layer.json().with_current_span(true).boxed()
+    } else {
+        layer.boxed()
+    }
+}
+
+fn tracer_provider(endpoint: &str, service_name: &str) -> Result<SdkTracerProvider, TraceError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Bytes, http::StatusCode, routing::post, Router};
+    use serde_json::Value;
+    use shared::AppError;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::mpsc;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'w> MakeWriter<'w> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'w self) -> Buffer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_json_logs_carry_sql_errors() {
+        // given
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(fmt_layer("json", buffer.clone()));
+        // when
+        let err = tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("request", request_id = "abc-123")
+                .in_scope(|| AppError::from(sqlx::Error::PoolTimedOut))
+        });
+        // then
+        assert!(matches!(err, AppError::InternalServerError));
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+        assert_eq!(line["level"], "ERROR");
+        assert_eq!(line["span"]["request_id"], "abc-123");
+        assert!(line["fields"]["error"]
+            .as_str()
+            .unwrap()
+            .contains("pool timed out"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spans_are_exported_over_otlp() {
+        // given a collector stand-in
+        let (sender, mut received) = mpsc::unbounded_channel();
+        let collector = Router::new().route(
+            "/v1/traces",
+            post(move |body: Bytes| async move {
+                sender.send(body).unwrap();
+                StatusCode::OK
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/v1/traces", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, collector).await.unwrap() });
+        let provider = tracer_provider(&endpoint, "memo-app-test").unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        // when
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("find_by_id", id = 1).in_scope(|| {});
+        });
+        tokio::task::spawn_blocking(move || provider.shutdown().unwrap())
+            .await
+            .unwrap();
+        // then
+        let body = received.recv().await.unwrap();
+        let body = String::from_utf8_lossy(&body);
+        assert!(body.contains("find_by_id"));
+        assert!(body.contains("memo-app-test"));
+    }
+}
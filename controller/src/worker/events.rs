@@ -0,0 +1,68 @@
+use service::service::event::EventService;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+/// Every `interval`, delivers the domain events waiting in the outbox until
+/// none are left that can be delivered. Stops between two batches once
+/// `shutdown` is cancelled.
+pub fn spawn(
+    event_service: Arc<dyn EventService>,
+    interval: Duration,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = ticker.tick() => {}
+            }
+            while !shutdown.is_cancelled() {
+                match event_service.dispatch().await {
+                    Ok(0) => break,
+                    Ok(delivered) => tracing::debug!(delivered, "delivered domain events"),
+                    Err(err) => {
+                        tracing::error!(error = %err, "dispatching domain events failed");
+                        break;
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use service::service::event::MockEventService;
+    use shared::AppError;
+
+    #[tokio::test]
+    async fn test_drains_the_outbox_on_every_tick() {
+        // given
+        let mut mock_event_service = MockEventService::new();
+        let mut batches = vec![Ok(0), Ok(100), Ok(100)];
+        mock_event_service
+            .expect_dispatch()
+            .times(4..)
+            .returning(move || batches.pop().unwrap_or(Err(AppError::InternalServerError)));
+        let shutdown = CancellationToken::new();
+        // when
+        let job = spawn(
+            Arc::new(mock_event_service),
+            Duration::from_millis(10),
+            shutdown.clone(),
+        );
+        tokio::time::sleep(Duration::from_millis(35)).await;
+        shutdown.cancel();
+        // then the job stops by itself
+        tokio::time::timeout(Duration::from_secs(1), job)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+use service::service::user::UserService;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+/// Every `interval`, permanently removes users deleted more than `retention`
+/// ago. Stops between two runs once `shutdown` is cancelled.
+pub fn spawn(
+    user_service: Arc<dyn UserService>,
+    retention: Duration,
+    interval: Duration,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+                _ = ticker.tick() => {}
+            }
+            match user_service.purge_deleted(retention).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!(purged, "purged deleted users"),
+                Err(err) => tracing::error!(error = %err, "purging deleted users failed"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use service::service::user::MockUserService;
+    use shared::AppError;
+
+    #[tokio::test]
+    async fn test_purges_periodically() {
+        // given
+        let mut mock_user_service = MockUserService::new();
+        let retention = Duration::from_secs(86400);
+        mock_user_service
+            .expect_purge_deleted()
+            .withf(move |r| *r == retention)
+            .times(2..)
+            .returning(|_| Err(AppError::InternalServerError));
+        let shutdown = CancellationToken::new();
+        // when
+        let job = spawn(
+            Arc::new(mock_user_service),
+            retention,
+            Duration::from_millis(10),
+            shutdown.clone(),
+        );
+        tokio::time::sleep(Duration::from_millis(35)).await;
+        shutdown.cancel();
+        // then the job stops by itself
+        tokio::time::timeout(Duration::from_secs(1), job)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
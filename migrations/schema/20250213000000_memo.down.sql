@@ -0,0 +1 @@
+DROP TABLE memos;
\ No newline at end of file
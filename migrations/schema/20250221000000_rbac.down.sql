@@ -0,0 +1,15 @@
+ALTER TABLE users
+    ADD COLUMN role VARCHAR(32) NOT NULL DEFAULT 'user';
+
+UPDATE users SET role = 'admin'
+WHERE id IN (
+    SELECT user_roles.user_id
+    FROM user_roles
+    JOIN roles ON roles.id = user_roles.role_id
+    WHERE roles.name = 'admin'
+);
+
+DROP TABLE user_roles;
+DROP TABLE role_permissions;
+DROP TABLE permissions;
+DROP TABLE roles;
@@ -0,0 +1,4 @@
+DROP INDEX users_deleted_at_idx;
+
+ALTER TABLE users
+    DROP COLUMN deleted_at;
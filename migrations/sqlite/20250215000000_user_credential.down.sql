@@ -0,0 +1,6 @@
+DROP INDEX users_email_key;
+
+ALTER TABLE users
+    DROP COLUMN password_hash;
+ALTER TABLE users
+    DROP COLUMN email;
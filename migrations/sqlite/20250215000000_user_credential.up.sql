@@ -0,0 +1,7 @@
+-- SQLite cannot add a UNIQUE column, so the constraint is an index
+ALTER TABLE users
+    ADD COLUMN email VARCHAR(255);
+ALTER TABLE users
+    ADD COLUMN password_hash VARCHAR(255);
+
+CREATE UNIQUE INDEX users_email_key ON users (email);
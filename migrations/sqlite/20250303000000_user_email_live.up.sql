@@ -0,0 +1,4 @@
+-- a user in the trash gives up their email, so it can be registered again
+DROP INDEX users_email_key;
+
+CREATE UNIQUE INDEX users_email_key ON users (email) WHERE deleted_at IS NULL;
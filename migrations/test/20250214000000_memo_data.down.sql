@@ -0,0 +1,2 @@
+DELETE FROM memos WHERE id = 1;
+DELETE FROM memos WHERE id = 2;
\ No newline at end of file
@@ -0,0 +1,4 @@
+INSERT INTO memos (user_id, title, content, created_at, updated_at)
+VALUES
+  (1, 'Groceries', 'Milk, eggs and bread', '2025-02-12 00:00:00', '2025-02-12 12:00:00'),
+  (2, 'Meeting', 'Prepare slides for Monday', '2025-02-13 00:00:00', '2025-02-13 12:00:00');
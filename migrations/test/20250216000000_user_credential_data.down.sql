@@ -0,0 +1 @@
+UPDATE users SET email = NULL, password_hash = NULL WHERE id IN (1, 2);
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- both passwords are "password"
+UPDATE users SET email = 'alice@example.com', password_hash = '$argon2id$v=19$m=19456,t=2,p=1$pvJ8Qrj2pPnxYcdRKVPuKQ$Z94QwPkriVNKTSWYpwriHfkvcXn1loTSXJ0/k/TL7zc' WHERE id = 1;
+UPDATE users SET email = 'bob@example.com', password_hash = '$argon2id$v=19$m=19456,t=2,p=1$pvJ8Qrj2pPnxYcdRKVPuKQ$Z94QwPkriVNKTSWYpwriHfkvcXn1loTSXJ0/k/TL7zc' WHERE id = 2;
@@ -0,0 +1 @@
+DELETE FROM user_roles WHERE user_id IN (1, 2);
@@ -0,0 +1,21 @@
+/// One change to an entity, as kept in the append-only `audit_events`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEventEntity {
+    pub id: i64,
+    /// The user who made the change; `None` for the system itself, e.g.
+    /// the purge job.
+    pub actor_id: Option<i32>,
+    pub action: String,
+    /// The table the entity lives in, e.g. `users`.
+    pub entity_type: String,
+    pub entity_id: i32,
+    /// The fields that changed, as they were before; `None` when the entity
+    /// was created.
+    pub old_values: Option<serde_json::Value>,
+    /// The fields that changed, as they are now; `None` when the entity was
+    /// deleted.
+    pub new_values: Option<serde_json::Value>,
+    /// The request the change was made in, if any.
+    pub request_id: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
@@ -0,0 +1,6 @@
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct CredentialEntity {
+    pub user_id: i32,
+    pub email: String,
+    pub password_hash: String,
+}
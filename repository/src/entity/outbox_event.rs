@@ -0,0 +1,15 @@
+/// A domain event in the `outbox`, waiting to be delivered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEventEntity {
+    pub id: i64,
+    /// The kind of event, e.g. `UserCreated`.
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    /// How often delivery was started, counting the one under way.
+    pub attempts: i32,
+    /// When the event may be claimed (again).
+    pub next_attempt_at: chrono::NaiveDateTime,
+    /// Why the last attempt failed.
+    pub last_error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
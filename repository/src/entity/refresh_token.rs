@@ -0,0 +1,10 @@
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct RefreshTokenEntity {
+    pub id: i32,
+    pub user_id: i32,
+    /// SHA-256 of the token handed to the client; the token itself is never stored.
+    pub token_hash: String,
+    pub expires_at: chrono::NaiveDateTime,
+    pub revoked_at: Option<chrono::NaiveDateTime>,
+    pub created_at: chrono::NaiveDateTime,
+}
@@ -1,7 +1,11 @@
-#[derive(Debug, sqlx::FromRow)]
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow, serde::Serialize, serde::Deserialize)]
 pub struct UserEntity {
     pub id: i32,
     pub name: String,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    /// Incremented by every update; the basis of the user's ETag.
+    pub version: i32,
+    /// Set while the user is in the trash.
+    pub deleted_at: Option<chrono::NaiveDateTime>,
 }
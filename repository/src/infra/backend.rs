@@ -0,0 +1,234 @@
+use crate::infra::cache::Cache;
+use crate::infra::memory::MemoryStore;
+use crate::infra::replica::ReplicaSet;
+use crate::infra::{db, migration, postgres, sqlite};
+use crate::repository::audit::{
+    AuditRepository, AuditRepositoryImpl, InMemoryAuditRepository, SqliteAuditRepository,
+};
+use crate::repository::credential::{
+    CredentialRepository, CredentialRepositoryImpl, InMemoryCredentialRepository,
+    SqliteCredentialRepository,
+};
+use crate::repository::memo::{
+    InMemoryMemoRepository, MemoRepository, MemoRepositoryImpl, SqliteMemoRepository,
+};
+use crate::repository::outbox::{
+    InMemoryOutboxRepository, OutboxRepository, OutboxRepositoryImpl, SqliteOutboxRepository,
+};
+use crate::repository::refresh_token::{
+    InMemoryRefreshTokenRepository, RefreshTokenRepository, RefreshTokenRepositoryImpl,
+    SqliteRefreshTokenRepository,
+};
+use crate::repository::role::{
+    InMemoryRoleRepository, RoleRepository, RoleRepositoryImpl, SqliteRoleRepository,
+};
+use crate::repository::unit_of_work::{
+    CachedTransactionManager, InMemoryTransactionManager, SqliteTransactionManager,
+    TransactionManager, TransactionManagerImpl,
+};
+use crate::repository::user::{
+    CachedUserRepository, InMemoryUserRepository, SqliteUserRepository, UserRepository,
+    UserRepositoryImpl,
+};
+use shared::settings::DatabaseSettings;
+use sqlx::migrate::MigrateError;
+use sqlx::{PgPool, SqlitePool};
+use std::sync::Arc;
+
+/// `database.url` scheme that keeps everything in process.
+pub const MEMORY_SCHEME: &str = "memory://";
+/// `database.url` scheme of a SQLite file, e.g. `sqlite://memo.db`.
+pub const SQLITE_SCHEME: &str = "sqlite:";
+
+/// The storage the repositories run on, picked by the scheme of
+/// `database.url`.
+#[derive(Debug, Clone)]
+pub enum Backend {
+    /// The primary and its read replicas, if any.
+    Postgres(Arc<PgPool>, Option<Arc<ReplicaSet>>),
+    Sqlite(Arc<SqlitePool>),
+    /// Starts out without any users or memos and is lost on exit; its roles
+    /// are the fixed [`ROLES`](crate::infra::memory::ROLES).
+    Memory(MemoryStore),
+}
+
+impl Backend {
+    pub async fn connect(settings: &DatabaseSettings) -> Self {
+        if settings.url.starts_with(MEMORY_SCHEME) {
+            Backend::Memory(MemoryStore::new())
+        } else if settings.url.starts_with(SQLITE_SCHEME) {
+            Backend::Sqlite(sqlite::pool(settings).await)
+        } else {
+            Backend::Postgres(
+                postgres::pool(settings).await,
+                ReplicaSet::connect(settings),
+            )
+        }
+    }
+
+    /// Applies every pending migration.
+    pub async fn migrate(&self) -> Result<(), MigrateError> {
+        match self {
+            Backend::Postgres(pool, _) => migration::up(pool).await,
+            Backend::Sqlite(pool) => migration::up(pool).await,
+            Backend::Memory(_) => Ok(()),
+        }
+    }
+
+    /// Checks that the database answers.
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        match self {
+            Backend::Postgres(pool, _) => db::ping(pool).await,
+            Backend::Sqlite(pool) => db::ping(pool).await,
+            Backend::Memory(_) => Ok(()),
+        }
+    }
+
+    /// Counts the known migrations that have not been applied yet.
+    pub async fn pending_migrations(&self) -> Result<usize, MigrateError> {
+        match self {
+            Backend::Postgres(pool, _) => migration::pending(pool).await,
+            Backend::Sqlite(pool) => migration::pending(pool).await,
+            Backend::Memory(_) => Ok(0),
+        }
+    }
+
+    /// Publishes the state of the connection pool, if there is one.
+    pub async fn record_metrics(&self) {
+        match self {
+            Backend::Postgres(pool, _) => db::record_metrics(pool).await,
+            Backend::Sqlite(pool) => db::record_metrics(pool).await,
+            Backend::Memory(_) => {}
+        }
+    }
+
+    /// Closes the connection pools, if there are any.
+    pub async fn close(&self) {
+        match self {
+            Backend::Postgres(pool, replicas) => {
+                pool.close().await;
+                if let Some(replicas) = replicas {
+                    replicas.close().await;
+                }
+            }
+            Backend::Sqlite(pool) => pool.close().await,
+            Backend::Memory(_) => {}
+        }
+    }
+
+    pub fn repositories(&self) -> Repositories {
+        match self {
+            Backend::Postgres(pool, replicas) => Repositories {
+                users: Arc::new(
+                    UserRepositoryImpl::new(pool.clone()).with_replicas(replicas.clone()),
+                ),
+                primary_users: Arc::new(UserRepositoryImpl::new(pool.clone())),
+                memos: Arc::new(MemoRepositoryImpl::new(pool.clone())),
+                credentials: Arc::new(CredentialRepositoryImpl::new(pool.clone())),
+                refresh_tokens: Arc::new(RefreshTokenRepositoryImpl::new(pool.clone())),
+                roles: Arc::new(RoleRepositoryImpl::new(pool.clone())),
+                audit: Arc::new(AuditRepositoryImpl::new(pool.clone())),
+                outbox: Arc::new(OutboxRepositoryImpl::new(pool.clone())),
+                transactions: Arc::new(
+                    TransactionManagerImpl::new(pool.clone()).with_replicas(replicas.clone()),
+                ),
+            },
+            Backend::Sqlite(pool) => Repositories {
+                users: Arc::new(SqliteUserRepository::new(pool.clone())),
+                primary_users: Arc::new(SqliteUserRepository::new(pool.clone())),
+                memos: Arc::new(SqliteMemoRepository::new(pool.clone())),
+                credentials: Arc::new(SqliteCredentialRepository::new(pool.clone())),
+                refresh_tokens: Arc::new(SqliteRefreshTokenRepository::new(pool.clone())),
+                roles: Arc::new(SqliteRoleRepository::new(pool.clone())),
+                audit: Arc::new(SqliteAuditRepository::new(pool.clone())),
+                outbox: Arc::new(SqliteOutboxRepository::new(pool.clone())),
+                transactions: Arc::new(SqliteTransactionManager::new(pool.clone())),
+            },
+            Backend::Memory(store) => Repositories {
+                users: Arc::new(InMemoryUserRepository::new(store.clone())),
+                primary_users: Arc::new(InMemoryUserRepository::new(store.clone())),
+                memos: Arc::new(InMemoryMemoRepository::new(store.clone())),
+                credentials: Arc::new(InMemoryCredentialRepository::new(store.clone())),
+                refresh_tokens: Arc::new(InMemoryRefreshTokenRepository::new(store.clone())),
+                roles: Arc::new(InMemoryRoleRepository::new(store.clone())),
+                audit: Arc::new(InMemoryAuditRepository::new(store.clone())),
+                outbox: Arc::new(InMemoryOutboxRepository::new(store.clone())),
+                transactions: Arc::new(InMemoryTransactionManager::new(store.clone())),
+            },
+        }
+    }
+}
+
+/// Every repository of one backend.
+#[derive(Clone)]
+pub struct Repositories {
+    pub users: Arc<dyn UserRepository>,
+    /// `users`, but never reading from a replica.
+    pub primary_users: Arc<dyn UserRepository>,
+    pub memos: Arc<dyn MemoRepository>,
+    pub credentials: Arc<dyn CredentialRepository>,
+    pub refresh_tokens: Arc<dyn RefreshTokenRepository>,
+    pub roles: Arc<dyn RoleRepository>,
+    pub audit: Arc<dyn AuditRepository>,
+    pub outbox: Arc<dyn OutboxRepository>,
+    pub transactions: Arc<dyn TransactionManager>,
+}
+
+impl Repositories {
+    /// Serves user lookups from `cache`, dropping users from it whenever
+    /// they are written, directly or in a unit of work.
+    pub fn with_user_cache(self, cache: Arc<dyn Cache>) -> Self {
+        Self {
+            users: Arc::new(CachedUserRepository::new(
+                self.users,
+                self.primary_users.clone(),
+                cache.clone(),
+            )),
+            transactions: Arc::new(CachedTransactionManager::new(self.transactions, cache)),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_url_needs_no_database() {
+        // given
+        let settings = DatabaseSettings {
+            url: "memory://".to_string(),
+            ..DatabaseSettings::default()
+        };
+        // when
+        let backend = Backend::connect(&settings).await;
+        // then
+        assert!(matches!(backend, Backend::Memory(_)));
+        assert_eq!(backend.pending_migrations().await.unwrap(), 0);
+        let roles = backend.repositories().roles;
+        assert!(roles.find_roles_for_user(1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_url_opens_a_file() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let settings = DatabaseSettings {
+            url: format!("sqlite://{}", dir.path().join("memo.db").display()),
+            ..DatabaseSettings::default()
+        };
+        // when
+        let backend = Backend::connect(&settings).await;
+        // then
+        assert!(matches!(backend, Backend::Sqlite(_)));
+        assert!(backend.pending_migrations().await.unwrap() > 0);
+        backend.migrate().await.unwrap();
+        assert_eq!(backend.pending_migrations().await.unwrap(), 0);
+        backend.ping().await.unwrap();
+        let roles = backend.repositories().roles;
+        assert!(roles.find_roles_for_user(1).await.unwrap().is_empty());
+        assert!(dir.path().join("memo.db").exists());
+    }
+}
@@ -0,0 +1,221 @@
+use crate::infra::redis::RedisCache;
+use shared::settings::CacheSettings;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A key-value cache whose entries expire. Failures of the cache itself
+/// are logged and look like misses, so that a cache going away only costs
+/// latency.
+#[async_trait::async_trait]
+pub trait Cache: Send + Sync + std::fmt::Debug {
+    async fn get(&self, key: &str) -> Option<String>;
+    async fn set(&self, key: &str, value: String);
+    /// Like [`Cache::set`], but leaves a value or tombstone already stored
+    /// under `key` alone.
+    async fn add(&self, key: &str, value: String);
+    /// Drops `key`, leaving a tombstone that keeps [`Cache::add`] from
+    /// storing it again for the invalidation grace period. A value read
+    /// before the invalidation thus cannot be added after it.
+    async fn invalidate(&self, key: &str);
+}
+
+/// The cache `settings` ask for, or `None` when caching is off.
+pub fn connect(settings: &CacheSettings) -> Option<Arc<dyn Cache>> {
+    if !settings.enabled {
+        return None;
+    }
+    Some(match &settings.redis_url {
+        Some(url) => Arc::new(RedisCache::new(
+            url,
+            settings.ttl(),
+            settings.invalidation_grace(),
+            settings.redis_timeout(),
+        )),
+        None => Arc::new(MemoryCache::new(
+            settings.capacity,
+            settings.ttl(),
+            settings.invalidation_grace(),
+        )),
+    })
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// `None` for a tombstone.
+    value: Option<String>,
+    expires_at: Instant,
+    /// Position in [`Lru::order`].
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<String, Entry>,
+    /// Keys by when they were last used, oldest first.
+    order: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+impl Lru {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.used);
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    /// Stores `value` under `key` until `ttl` has passed, evicting the least
+    /// recently used entries beyond `capacity`.
+    fn insert(&mut self, key: &str, value: Option<String>, ttl: Duration, capacity: usize) {
+        self.remove(key);
+        let used = self.tick();
+        self.entries.insert(
+            key.to_string(),
+            Entry {
+                value,
+                expires_at: Instant::now() + ttl,
+                used,
+            },
+        );
+        self.order.insert(used, key.to_string());
+        while self.entries.len() > capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    /// Drops `key` if it has expired, telling whether it is still there.
+    fn expire(&mut self, key: &str) -> bool {
+        let Some(entry) = self.entries.get(key) else {
+            return false;
+        };
+        if entry.expires_at <= Instant::now() {
+            self.remove(key);
+            return false;
+        }
+        true
+    }
+}
+
+/// Keeps up to `capacity` entries in process, dropping the least recently
+/// used one to make room.
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    ttl: Duration,
+    grace: Duration,
+    lru: Mutex<Lru>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize, ttl: Duration, grace: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            grace,
+            lru: Mutex::new(Lru::default()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> Option<String> {
+        let mut lru = self.lru.lock().expect("cache poisoned");
+        if !lru.expire(key) {
+            return None;
+        }
+        let used = lru.tick();
+        let entry = lru.entries.get_mut(key).expect("checked above");
+        let previous = std::mem::replace(&mut entry.used, used);
+        let value = entry.value.clone();
+        lru.order.remove(&previous);
+        lru.order.insert(used, key.to_string());
+        value
+    }
+
+    async fn set(&self, key: &str, value: String) {
+        let mut lru = self.lru.lock().expect("cache poisoned");
+        lru.insert(key, Some(value), self.ttl, self.capacity);
+    }
+
+    async fn add(&self, key: &str, value: String) {
+        let mut lru = self.lru.lock().expect("cache poisoned");
+        if !lru.expire(key) {
+            lru.insert(key, Some(value), self.ttl, self.capacity);
+        }
+    }
+
+    async fn invalidate(&self, key: &str) {
+        let mut lru = self.lru.lock().expect("cache poisoned");
+        lru.insert(key, None, self.grace, self.capacity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_least_recently_used_is_evicted() {
+        // given
+        let cache = MemoryCache::new(2, Duration::from_secs(60), Duration::from_secs(5));
+        cache.set("a", "1".to_string()).await;
+        cache.set("b", "2".to_string()).await;
+        cache.get("a").await;
+        // when
+        cache.set("c", "3".to_string()).await;
+        // then
+        assert_eq!(cache.get("a").await.as_deref(), Some("1"));
+        assert_eq!(cache.get("b").await, None);
+        assert_eq!(cache.get("c").await.as_deref(), Some("3"));
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire() {
+        // given
+        let cache = MemoryCache::new(2, Duration::from_millis(50), Duration::from_secs(5));
+        cache.set("a", "1".to_string()).await;
+        // when
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        // then
+        assert_eq!(cache.get("a").await, None);
+        assert!(cache.lru.lock().unwrap().order.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_add_keeps_what_is_stored() {
+        // given
+        let cache = MemoryCache::new(2, Duration::from_secs(60), Duration::from_secs(5));
+        cache.set("a", "1".to_string()).await;
+        // when
+        cache.add("a", "2".to_string()).await;
+        cache.add("b", "3".to_string()).await;
+        // then
+        assert_eq!(cache.get("a").await.as_deref(), Some("1"));
+        assert_eq!(cache.get("b").await.as_deref(), Some("3"));
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_blocks_adds_for_the_grace_period() {
+        // given
+        let cache = MemoryCache::new(2, Duration::from_secs(60), Duration::from_millis(50));
+        cache.set("a", "1".to_string()).await;
+        // when
+        cache.invalidate("a").await;
+        cache.add("a", "stale".to_string()).await;
+        // then
+        assert_eq!(cache.get("a").await, None);
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        cache.add("a", "2".to_string()).await;
+        assert_eq!(cache.get("a").await.as_deref(), Some("2"));
+    }
+}
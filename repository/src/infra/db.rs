@@ -0,0 +1,116 @@
+use shared::AppError;
+use sqlx::pool::PoolConnection;
+use sqlx::{Connection, Database, Pool, Postgres, Transaction};
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Checks that the database answers.
+pub async fn ping<DB: Database>(pool: &Pool<DB>) -> Result<(), sqlx::Error> {
+    pool.acquire().await?.ping().await
+}
+
+/// Publishes the pool's state as `db_pool_*` gauges.
+pub async fn record_metrics<DB: Database>(pool: &Pool<DB>) {
+    let (size, idle) = (pool.size(), pool.num_idle() as u32);
+    metrics::gauge!("db_pool_connections").set(size as f64);
+    metrics::gauge!("db_pool_idle_connections").set(idle as f64);
+    metrics::gauge!("db_pool_in_use_connections").set(size.saturating_sub(idle) as f64);
+    metrics::gauge!("db_pool_max_connections").set(pool.options().get_max_connections() as f64);
+}
+
+/// Begins a transaction on a connection of `pool`, recording the wait like
+/// [`Db::acquire`] does.
+pub async fn begin<DB: Database>(pool: &Pool<DB>) -> Result<Transaction<'static, DB>, AppError> {
+    Ok(timed_acquire(pool.begin()).await?)
+}
+
+/// Awaits the checkout of a connection and records how long it took in the
+/// `db_pool_acquire_seconds` histogram.
+async fn timed_acquire<T>(checkout: impl Future<Output = T>) -> T {
+    let started = Instant::now();
+    let connection = checkout.await;
+    metrics::histogram!("db_pool_acquire_seconds").record(started.elapsed().as_secs_f64());
+    connection
+}
+
+/// An open transaction shared by the repositories of one unit of work;
+/// `None` once it has been committed or rolled back.
+pub type SharedTransaction<DB = Postgres> = Arc<Mutex<Option<Transaction<'static, DB>>>>;
+
+/// Where a repository runs its queries: on any connection of the pool, or
+/// on the transaction of a unit of work.
+pub enum Db<DB: Database = Postgres> {
+    Pool(Arc<Pool<DB>>),
+    Transaction(SharedTransaction<DB>),
+}
+
+impl<DB: Database> Db<DB> {
+    /// Checks out the connection for the next statement. Within a unit of
+    /// work, statements queue up on its single transaction.
+    pub async fn acquire(&self) -> Result<DbConnection<'_, DB>, AppError> {
+        match self {
+            Db::Pool(pool) => Ok(DbConnection::Pooled(timed_acquire(pool.acquire()).await?)),
+            Db::Transaction(transaction) => {
+                let guard = transaction.lock().await;
+                if guard.is_none() {
+                    tracing::error!("query on a unit of work that has already finished");
+                    return Err(AppError::InternalServerError);
+                }
+                Ok(DbConnection::Transaction(guard))
+            }
+        }
+    }
+}
+
+impl<DB: Database> Clone for Db<DB> {
+    fn clone(&self) -> Self {
+        match self {
+            Db::Pool(pool) => Db::Pool(pool.clone()),
+            Db::Transaction(transaction) => Db::Transaction(transaction.clone()),
+        }
+    }
+}
+
+impl<DB: Database> From<Arc<Pool<DB>>> for Db<DB> {
+    fn from(pool: Arc<Pool<DB>>) -> Self {
+        Db::Pool(pool)
+    }
+}
+
+impl<DB: Database> std::fmt::Debug for Db<DB> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Db::Pool(pool) => f.debug_tuple("Pool").field(pool).finish(),
+            Db::Transaction(_) => f.write_str("Transaction"),
+        }
+    }
+}
+
+/// A connection checked out by [`Db::acquire`].
+pub enum DbConnection<'a, DB: Database = Postgres> {
+    Pooled(PoolConnection<DB>),
+    Transaction(MutexGuard<'a, Option<Transaction<'static, DB>>>),
+}
+
+impl<DB: Database> Deref for DbConnection<'_, DB> {
+    type Target = DB::Connection;
+
+    fn deref(&self) -> &DB::Connection {
+        match self {
+            DbConnection::Pooled(connection) => connection,
+            DbConnection::Transaction(guard) => guard.as_ref().expect("checked by acquire"),
+        }
+    }
+}
+
+impl<DB: Database> DerefMut for DbConnection<'_, DB> {
+    fn deref_mut(&mut self) -> &mut DB::Connection {
+        match self {
+            DbConnection::Pooled(connection) => connection,
+            DbConnection::Transaction(guard) => guard.as_mut().expect("checked by acquire"),
+        }
+    }
+}
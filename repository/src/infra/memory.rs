@@ -0,0 +1,456 @@
+//! In-process stand-in for the Postgres schema, for local runs and fast
+//! tests. It follows the database where callers can tell: ids come from
+//! sequences that never hand out a number twice, even when the write fails
+//! or is rolled back, and timestamps are UTC with microsecond precision,
+//! fixed for the whole of a unit of work like `CURRENT_TIMESTAMP`.
+
+use crate::entity::audit_event::AuditEventEntity;
+use crate::entity::memo::MemoEntity;
+use crate::entity::outbox_event::OutboxEventEntity;
+use crate::entity::refresh_token::RefreshTokenEntity;
+use crate::entity::user::UserEntity;
+use chrono::{NaiveDateTime, SubsecRound};
+use shared::query::{CursorValue, Direction, Keyed, ListQuery, Page, SortField};
+use shared::AppError;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicI32, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+
+/// Roles and the permissions they grant, as seeded by the `rbac` and
+/// `audit_event` migrations.
+pub const ROLES: &[(&str, &[&str])] = &[
+    (
+        "admin",
+        &[
+            "audit:read",
+            "memos:delete",
+            "memos:write",
+            "roles:manage",
+            "users:delete",
+            "users:write",
+        ],
+    ),
+    (
+        "member",
+        &["memos:delete", "memos:write", "users:delete", "users:write"],
+    ),
+    ("read-only", &[]),
+];
+
+/// A row of `users`: the entity plus its login credential, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserRow {
+    pub user: UserEntity,
+    pub email: Option<String>,
+    pub password_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Tables {
+    pub users: BTreeMap<i32, UserRow>,
+    pub memos: BTreeMap<i32, MemoEntity>,
+    pub refresh_tokens: BTreeMap<i32, RefreshTokenEntity>,
+    /// `(user_id, role)` pairs.
+    pub user_roles: BTreeSet<(i32, String)>,
+    /// Only ever appended to.
+    pub audit_events: BTreeMap<i64, AuditEventEntity>,
+    pub outbox: BTreeMap<i64, OutboxEventEntity>,
+}
+
+impl Tables {
+    /// Removes user `id` together with the rows that reference it, like
+    /// `ON DELETE CASCADE`.
+    pub fn remove_user(&mut self, id: i32) {
+        self.users.remove(&id);
+        self.memos.retain(|_, memo| memo.user_id != id);
+        self.refresh_tokens.retain(|_, token| token.user_id != id);
+        self.user_roles.retain(|(user_id, _)| *user_id != id);
+    }
+
+    /// Fails with `AppError::Conflict` if two users outside the trash share
+    /// an email or two refresh tokens a hash, or if a row references a
+    /// missing user.
+    fn check_constraints(&self) -> Result<(), AppError> {
+        let mut emails = BTreeSet::new();
+        let mut hashes = BTreeSet::new();
+        let unique = self
+            .users
+            .values()
+            .filter(|row| row.user.deleted_at.is_none())
+            .filter_map(|row| row.email.as_deref())
+            .all(|email| emails.insert(email))
+            && self
+                .refresh_tokens
+                .values()
+                .all(|token| hashes.insert(token.token_hash.as_str()));
+        let referenced = self
+            .memos
+            .values()
+            .map(|memo| memo.user_id)
+            .chain(self.refresh_tokens.values().map(|token| token.user_id))
+            .chain(self.user_roles.iter().map(|(user_id, _)| *user_id))
+            .all(|user_id| self.users.contains_key(&user_id));
+        if unique && referenced {
+            Ok(())
+        } else {
+            Err(AppError::Conflict)
+        }
+    }
+
+    /// Fails like a foreign key on `users (id)` unless user `id` exists.
+    pub fn check_user(&self, id: i32) -> Result<(), AppError> {
+        if self.users.contains_key(&id) {
+            Ok(())
+        } else {
+            tracing::error!(user_id = id, "no user with this id");
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Sequences {
+    users: AtomicI32,
+    memos: AtomicI32,
+    refresh_tokens: AtomicI32,
+    audit_events: AtomicI32,
+    outbox: AtomicI32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Sequence {
+    Users,
+    Memos,
+    RefreshTokens,
+    AuditEvents,
+    Outbox,
+}
+
+/// Handle on the tables; clones share them.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    tables: Arc<Mutex<Tables>>,
+    sequences: Arc<Sequences>,
+    /// The transaction time while this is the snapshot of a unit of work.
+    frozen_at: Option<NaiveDateTime>,
+    /// The tables as they were when this snapshot was taken.
+    base: Option<Arc<Tables>>,
+}
+
+impl MemoryStore {
+    /// A store without any rows. Roles are not stored: the built-in
+    /// [`ROLES`] are all there is. Tests that need users and memos start
+    /// from `seeded` instead.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read<T>(&self, f: impl FnOnce(&Tables) -> T) -> T {
+        f(&self.tables.lock().expect("memory store poisoned"))
+    }
+
+    /// Runs `f` atomically. `f` must not change anything when it fails.
+    pub fn write<T>(
+        &self,
+        f: impl FnOnce(&mut Tables) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        f(&mut self.tables.lock().expect("memory store poisoned"))
+    }
+
+    /// Takes the next value of `sequence`; like `nextval`, it is consumed
+    /// even if the write it was taken for fails.
+    pub fn next_id(&self, sequence: Sequence) -> i32 {
+        let counter = match sequence {
+            Sequence::Users => &self.sequences.users,
+            Sequence::Memos => &self.sequences.memos,
+            Sequence::RefreshTokens => &self.sequences.refresh_tokens,
+            Sequence::AuditEvents => &self.sequences.audit_events,
+            Sequence::Outbox => &self.sequences.outbox,
+        };
+        counter.fetch_add(1, AtomicOrdering::SeqCst) + 1
+    }
+
+    /// `CURRENT_TIMESTAMP`: the start of the unit of work, or the current
+    /// time outside of one.
+    pub fn now(&self) -> NaiveDateTime {
+        self.frozen_at
+            .unwrap_or_else(|| chrono::Utc::now().naive_utc().trunc_subsecs(6))
+    }
+
+    /// Copies the tables for a unit of work.
+    pub fn snapshot(&self) -> MemoryStore {
+        let tables = self.read(Tables::clone);
+        MemoryStore {
+            tables: Arc::new(Mutex::new(tables.clone())),
+            sequences: self.sequences.clone(),
+            frozen_at: Some(self.now()),
+            base: Some(Arc::new(tables)),
+        }
+    }
+
+    /// Applies the rows `snapshot` changed since it was taken. Fails with
+    /// `AppError::Conflict`, changing nothing, if another write changed one
+    /// of those rows meanwhile or the result breaks a unique or foreign key
+    /// constraint; the caller may then retry the unit of work. Writes to
+    /// other rows do not get in the way.
+    pub fn commit(&self, snapshot: &MemoryStore) -> Result<(), AppError> {
+        let Some(base) = &snapshot.base else {
+            tracing::error!("committing a memory store that is not a snapshot");
+            return Err(AppError::InternalServerError);
+        };
+        let changed = snapshot.read(Tables::clone);
+        let mut tables = self.tables.lock().expect("memory store poisoned");
+        let mut merged = tables.clone();
+        merge(&mut merged.users, &base.users, &changed.users)?;
+        merge(&mut merged.memos, &base.memos, &changed.memos)?;
+        merge(
+            &mut merged.refresh_tokens,
+            &base.refresh_tokens,
+            &changed.refresh_tokens,
+        )?;
+        merge(
+            &mut merged.audit_events,
+            &base.audit_events,
+            &changed.audit_events,
+        )?;
+        merge(&mut merged.outbox, &base.outbox, &changed.outbox)?;
+        for removed in base.user_roles.difference(&changed.user_roles) {
+            merged.user_roles.remove(removed);
+        }
+        for added in changed.user_roles.difference(&base.user_roles) {
+            merged.user_roles.insert(added.clone());
+        }
+        merged.check_constraints()?;
+        *tables = merged;
+        Ok(())
+    }
+
+    /// The data of `migrations/test`, for tests that run against both
+    /// backends.
+    #[cfg(test)]
+    pub fn seeded() -> Self {
+        let store = Self::new();
+        let at = |value: &str| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").unwrap();
+        // "password", as in the Postgres seed
+        let password_hash = "$argon2id$v=19$m=19456,t=2,p=1$pvJ8Qrj2pPnxYcdRKVPuKQ$Z94QwPkriVNKTSWYpwriHfkvcXn1loTSXJ0/k/TL7zc";
+        store
+            .write(|tables| {
+                for (name, email, role, day) in [
+                    ("Alice", "alice@example.com", "admin", 10),
+                    ("Bob", "bob@example.com", "member", 11),
+                ] {
+                    let id = store.next_id(Sequence::Users);
+                    let user = UserEntity {
+                        id,
+                        name: name.to_string(),
+                        created_at: at(&format!("2025-02-{day} 00:00:00")),
+                        updated_at: at(&format!("2025-02-{day} 12:00:00")),
+                        version: 1,
+                        deleted_at: None,
+                    };
+                    tables.users.insert(
+                        id,
+                        UserRow {
+                            user,
+                            email: Some(email.to_string()),
+                            password_hash: Some(password_hash.to_string()),
+                        },
+                    );
+                    tables.user_roles.insert((id, role.to_string()));
+                }
+                for (user_id, title, content, day) in [
+                    (1, "Groceries", "Milk, eggs and bread", 12),
+                    (2, "Meeting", "Prepare slides for Monday", 13),
+                ] {
+                    let id = store.next_id(Sequence::Memos);
+                    let memo = MemoEntity {
+                        id,
+                        user_id,
+                        title: title.to_string(),
+                        content: content.to_string(),
+                        created_at: at(&format!("2025-02-{day} 00:00:00")),
+                        updated_at: at(&format!("2025-02-{day} 12:00:00")),
+                    };
+                    tables.memos.insert(id, memo);
+                }
+                Ok(())
+            })
+            .unwrap();
+        store
+    }
+}
+
+/// Applies to `live` the rows that differ between `base` and `changed`,
+/// unless `live` no longer has the row as it was in `base`.
+fn merge<K: Ord + Clone, V: Clone + PartialEq>(
+    live: &mut BTreeMap<K, V>,
+    base: &BTreeMap<K, V>,
+    changed: &BTreeMap<K, V>,
+) -> Result<(), AppError> {
+    let keys: BTreeSet<&K> = base.keys().chain(changed.keys()).collect();
+    for key in keys {
+        let (before, after) = (base.get(key), changed.get(key));
+        if before == after {
+            continue;
+        }
+        if live.get(key) != before {
+            return Err(AppError::Conflict);
+        }
+        match after {
+            Some(row) => live.insert(key.clone(), row.clone()),
+            None => live.remove(key),
+        };
+    }
+    Ok(())
+}
+
+/// Pages `rows`, all of which match the filter, the way
+/// [`crate::infra::query::push_page`] pages a query. Text compares by code
+/// point, like the `C` collation.
+pub fn page<F: SortField, Q, T: Keyed<F>>(rows: Vec<T>, query: &ListQuery<F, Q>) -> Page<T> {
+    let total = rows.len() as i64;
+    let keys =
+        |row: &T| -> Vec<CursorValue> { query.sort.iter().map(|key| row.key(key.field)).collect() };
+    let mut rows: Vec<(Vec<CursorValue>, T)> =
+        rows.into_iter().map(|row| (keys(&row), row)).collect();
+    rows.sort_by(|(a, _), (b, _)| compare(query, a, b));
+    let rows = rows
+        .into_iter()
+        .filter(|(key, _)| match &query.cursor {
+            Some(cursor) => compare(query, key, &cursor.values) == Ordering::Greater,
+            None => true,
+        })
+        .take(query.limit as usize + 1)
+        .map(|(_, row)| row)
+        .collect();
+    Page::from_rows(rows, query, total)
+}
+
+fn compare<F: SortField, Q>(
+    query: &ListQuery<F, Q>,
+    a: &[CursorValue],
+    b: &[CursorValue],
+) -> Ordering {
+    query
+        .sort
+        .iter()
+        .zip(a.iter().zip(b))
+        .map(|(key, (a, b))| {
+            let ordering = match (a, b) {
+                (CursorValue::Int(a), CursorValue::Int(b)) => a.cmp(b),
+                (CursorValue::Text(a), CursorValue::Text(b)) => a.cmp(b),
+                (CursorValue::Timestamp(a), CursorValue::Timestamp(b)) => a.cmp(b),
+                _ => Ordering::Equal,
+            };
+            match key.direction {
+                Direction::Asc => ordering,
+                Direction::Desc => ordering.reverse(),
+            }
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_sequences_survive_rollback() {
+        // given
+        let store = MemoryStore::new();
+        let snapshot = store.snapshot();
+        // when the unit of work takes an id and is dropped
+        let discarded = snapshot.next_id(Sequence::Users);
+        let next = store.next_id(Sequence::Users);
+        // then
+        assert_eq!(discarded, 1);
+        assert_eq!(next, 2);
+    }
+
+    #[test]
+    fn test_snapshot_time_is_fixed() {
+        // given
+        let store = MemoryStore::new();
+        // when
+        let snapshot = store.snapshot();
+        let first = snapshot.now();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        // then
+        assert_eq!(snapshot.now(), first);
+        assert!(store.now() > first);
+        assert_eq!(first.and_utc().timestamp_subsec_nanos() % 1000, 0);
+    }
+
+    fn rename(store: &MemoryStore, id: i32, name: &str) {
+        store
+            .write(|tables| {
+                tables.users.get_mut(&id).unwrap().user.name = name.to_string();
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_commit_after_concurrent_write_to_the_same_row_conflicts() {
+        // given
+        let store = MemoryStore::seeded();
+        let snapshot = store.snapshot();
+        rename(&snapshot, 1, "Alicia");
+        rename(&store, 1, "Alison");
+        // when
+        let result = store.commit(&snapshot);
+        // then
+        assert!(matches!(result, Err(AppError::Conflict)));
+        assert_eq!(
+            store.read(|tables| tables.users[&1].user.name.clone()),
+            "Alison"
+        );
+    }
+
+    #[test]
+    fn test_commit_after_concurrent_write_to_other_rows() {
+        // given
+        let store = MemoryStore::seeded();
+        let snapshot = store.snapshot();
+        rename(&snapshot, 1, "Alicia");
+        rename(&store, 2, "Robert");
+        // when
+        let result = store.commit(&snapshot);
+        // then
+        assert!(result.is_ok());
+        let names = store.read(|tables| {
+            tables
+                .users
+                .values()
+                .map(|row| row.user.name.clone())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(names, vec!["Alicia", "Robert"]);
+    }
+
+    #[test]
+    fn test_commit_breaking_a_constraint_conflicts() {
+        // given
+        let store = MemoryStore::seeded();
+        let snapshot = store.snapshot();
+        snapshot
+            .write(|tables| {
+                tables.users.get_mut(&1).unwrap().email = Some("carol@example.com".to_string());
+                Ok(())
+            })
+            .unwrap();
+        store
+            .write(|tables| {
+                tables.users.get_mut(&2).unwrap().email = Some("carol@example.com".to_string());
+                Ok(())
+            })
+            .unwrap();
+        // when
+        let result = store.commit(&snapshot);
+        // then
+        assert!(matches!(result, Err(AppError::Conflict)));
+    }
+}
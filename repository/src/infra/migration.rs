@@ -0,0 +1,178 @@
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+use sqlx::{Database, Pool, Postgres, Sqlite};
+
+/// Schema migrations embedded from `migrations/schema`.
+pub static MIGRATOR: Migrator = sqlx::migrate!("../migrations/schema");
+
+/// The same schema for SQLite, embedded from `migrations/sqlite`. Every
+/// Postgres migration has a counterpart with the same version.
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("../migrations/sqlite");
+
+/// A database with a set of schema migrations.
+pub trait Migrations: Database {
+    fn migrator() -> &'static Migrator;
+}
+
+impl Migrations for Postgres {
+    fn migrator() -> &'static Migrator {
+        &MIGRATOR
+    }
+}
+
+impl Migrations for Sqlite {
+    fn migrator() -> &'static Migrator {
+        &SQLITE_MIGRATOR
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub applied: bool,
+}
+
+/// Applies every pending migration.
+pub async fn up<DB: Migrations>(pool: &Pool<DB>) -> Result<(), MigrateError>
+where
+    DB::Connection: Migrate,
+{
+    DB::migrator().run(pool).await
+}
+
+/// Reverts the last `steps` applied migrations and returns their versions.
+pub async fn down<DB: Migrations>(pool: &Pool<DB>, steps: usize) -> Result<Vec<i64>, MigrateError>
+where
+    DB::Connection: Migrate,
+{
+    let applied = applied_versions(pool).await?;
+    if steps == 0 || applied.is_empty() {
+        return Ok(vec![]);
+    }
+    let keep = applied.len().saturating_sub(steps);
+    let target = if keep == 0 { 0 } else { applied[keep - 1] };
+    DB::migrator().undo(pool, target).await?;
+    Ok(applied[keep..].iter().rev().copied().collect())
+}
+
+/// Lists every known migration and whether it has been applied.
+pub async fn status<DB: Migrations>(pool: &Pool<DB>) -> Result<Vec<MigrationStatus>, MigrateError>
+where
+    DB::Connection: Migrate,
+{
+    let applied = applied_versions(pool).await?;
+    Ok(DB::migrator()
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            description: migration.description.to_string(),
+            applied: applied.contains(&migration.version),
+        })
+        .collect())
+}
+
+/// Counts the known migrations that have not been applied yet.
+pub async fn pending<DB: Migrations>(pool: &Pool<DB>) -> Result<usize, MigrateError>
+where
+    DB::Connection: Migrate,
+{
+    Ok(status(pool)
+        .await?
+        .into_iter()
+        .filter(|migration| !migration.applied)
+        .count())
+}
+
+async fn applied_versions<DB: Migrations>(pool: &Pool<DB>) -> Result<Vec<i64>, MigrateError>
+where
+    DB::Connection: Migrate,
+{
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let mut versions: Vec<i64> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .filter(|version| {
+            DB::migrator()
+                .iter()
+                .any(|migration| migration.version == *version)
+        })
+        .collect();
+    versions.sort_unstable();
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::infra::testcontainer::PostgresContainer;
+    use crate::infra::testsqlite::SqliteDatabase;
+
+    #[tokio::test]
+    async fn test_status() {
+        // given
+        let container = PostgresContainer::new().await;
+        // when
+        let migrations = status(&container.pool()).await.unwrap();
+        // then
+        let known = MIGRATOR
+            .iter()
+            .filter(|migration| migration.migration_type.is_up_migration())
+            .count();
+        assert_eq!(migrations.len(), known);
+        assert_eq!(migrations[0].version, 20250211000000);
+        assert_eq!(migrations[0].description, "user");
+        assert!(migrations.iter().all(|migration| migration.applied));
+    }
+
+    #[tokio::test]
+    async fn test_down_and_up() {
+        // given
+        let container = PostgresContainer::unseeded().await;
+        let pool = container.pool();
+        // when
+        let reverted = down(&pool, 1).await.unwrap();
+        // then
+        let latest = MIGRATOR.iter().last().unwrap().version;
+        assert_eq!(reverted, vec![latest]);
+        let migrations = status(&pool).await.unwrap();
+        let (last, rest) = migrations.split_last().unwrap();
+        assert!(!last.applied);
+        assert!(rest.iter().all(|migration| migration.applied));
+        assert_eq!(pending(&pool).await.unwrap(), 1);
+        // when
+        up(&pool).await.unwrap();
+        // then
+        let migrations = status(&pool).await.unwrap();
+        assert!(migrations.iter().all(|migration| migration.applied));
+        assert_eq!(pending(&pool).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_matches_postgres_and_reverts() {
+        // given
+        let database = SqliteDatabase::unseeded().await;
+        let pool = database.pool();
+        let versions = |migrator: &Migrator| {
+            migrator
+                .iter()
+                .map(|migration| (migration.version, migration.migration_type))
+                .collect::<Vec<_>>()
+        };
+        // then every migration has a SQLite counterpart
+        assert_eq!(versions(&SQLITE_MIGRATOR), versions(&MIGRATOR));
+        // when
+        let reverted = down(&pool, usize::MAX).await.unwrap();
+        // then
+        assert_eq!(reverted.len(), status(&pool).await.unwrap().len());
+        assert_eq!(pending(&pool).await.unwrap(), reverted.len());
+        // when
+        up(&pool).await.unwrap();
+        // then
+        assert_eq!(pending(&pool).await.unwrap(), 0);
+    }
+}
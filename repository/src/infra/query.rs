@@ -0,0 +1,81 @@
+use shared::query::{CursorValue, Direction, ListQuery, SortField, SortKey};
+use sqlx::{Database, Encode, QueryBuilder, Type};
+
+/// Appends the keyset condition, `ORDER BY` and `LIMIT` of `query` to a
+/// `SELECT ... WHERE <filter>` statement. One row more than the page size is
+/// fetched so that [`shared::query::Page::from_rows`] can tell whether
+/// another page follows.
+pub fn push_page<'a, DB, F, Q>(builder: &mut QueryBuilder<'a, DB>, query: &ListQuery<F, Q>)
+where
+    DB: Database,
+    F: SortField,
+    i64: Encode<'a, DB> + Type<DB>,
+    String: Encode<'a, DB> + Type<DB>,
+    chrono::NaiveDateTime: Encode<'a, DB> + Type<DB>,
+{
+    if let Some(cursor) = &query.cursor {
+        // (a > x) OR (a = x AND b < y) OR (a = x AND b = y AND id > z) ...
+        builder.push(" AND (");
+        for (i, key) in query.sort.iter().enumerate() {
+            if i > 0 {
+                builder.push(" OR ");
+            }
+            builder.push("(");
+            for (previous, value) in query.sort[..i].iter().zip(&cursor.values) {
+                builder.push(previous.field.column()).push(" = ");
+                push_value(builder, value);
+                builder.push(" AND ");
+            }
+            builder.push(key.field.column()).push(match key.direction {
+                Direction::Asc => " > ",
+                Direction::Desc => " < ",
+            });
+            push_value(builder, &cursor.values[i]);
+            builder.push(")");
+        }
+        builder.push(")");
+    }
+    builder.push(" ORDER BY ");
+    push_order_by(builder, &query.sort);
+    builder
+        .push(" LIMIT ")
+        .push_bind(i64::from(query.limit) + 1);
+}
+
+fn push_order_by<DB: Database, F: SortField>(
+    builder: &mut QueryBuilder<'_, DB>,
+    sort: &[SortKey<F>],
+) {
+    for (i, key) in sort.iter().enumerate() {
+        if i > 0 {
+            builder.push(", ");
+        }
+        builder.push(key.field.column()).push(match key.direction {
+            Direction::Asc => " ASC",
+            Direction::Desc => " DESC",
+        });
+    }
+}
+
+fn push_value<'a, DB>(builder: &mut QueryBuilder<'a, DB>, value: &CursorValue)
+where
+    DB: Database,
+    i64: Encode<'a, DB> + Type<DB>,
+    String: Encode<'a, DB> + Type<DB>,
+    chrono::NaiveDateTime: Encode<'a, DB> + Type<DB>,
+{
+    match value {
+        CursorValue::Int(value) => builder.push_bind(*value),
+        CursorValue::Text(value) => builder.push_bind(value.clone()),
+        CursorValue::Timestamp(value) => builder.push_bind(*value),
+    };
+}
+
+/// Escapes `LIKE` wildcards so that `value` matches literally inside `%...%`.
+pub fn contains_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
@@ -0,0 +1,241 @@
+use crate::infra::cache::Cache;
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use redis::{AsyncCommands, Client, RedisError, RedisResult};
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+/// Stored by [`Cache::invalidate`]; no JSON document looks like it.
+const TOMBSTONE: &str = "\0invalidated";
+
+/// A [`Cache`] on a Redis server, shared by every instance that uses it.
+/// The connection is opened on first use and reopened after failures.
+pub struct RedisCache {
+    client: Client,
+    ttl: Duration,
+    grace: Duration,
+    timeout: Duration,
+    connection: OnceCell<ConnectionManager>,
+}
+
+impl RedisCache {
+    /// `url` is `redis://[[username]:password@]host[:port][/db]`.
+    pub fn new(url: &str, ttl: Duration, grace: Duration, timeout: Duration) -> Self {
+        Self {
+            client: Client::open(url).expect("Invalid cache.redis_url"),
+            ttl,
+            grace,
+            timeout,
+            connection: OnceCell::new(),
+        }
+    }
+
+    /// Runs `command` on the connection within the timeout.
+    async fn run<T, F>(&self, command: impl FnOnce(ConnectionManager) -> F) -> RedisResult<T>
+    where
+        F: Future<Output = RedisResult<T>>,
+    {
+        let run = async {
+            let connection = self
+                .connection
+                .get_or_try_init(|| {
+                    let config = ConnectionManagerConfig::new()
+                        .set_number_of_retries(1)
+                        .set_connection_timeout(self.timeout)
+                        .set_response_timeout(self.timeout);
+                    ConnectionManager::new_with_config(self.client.clone(), config)
+                })
+                .await?;
+            command(connection.clone()).await
+        };
+        tokio::time::timeout(self.timeout, run)
+            .await
+            .unwrap_or_else(|_| {
+                Err(RedisError::from(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "redis command timed out",
+                )))
+            })
+    }
+
+    fn failed(&self, err: RedisError) {
+        tracing::warn!(
+            error = %err,
+            address = %self.client.get_connection_info().addr,
+            "redis cache failed"
+        );
+        metrics::counter!("cache_errors_total").increment(1);
+    }
+}
+
+impl std::fmt::Debug for RedisCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let info = self.client.get_connection_info();
+        f.debug_struct("RedisCache")
+            .field("address", &info.addr.to_string())
+            .field("database", &info.redis.db)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait::async_trait]
+impl Cache for RedisCache {
+    async fn get(&self, key: &str) -> Option<String> {
+        self.run(|mut connection| async move { connection.get(key).await })
+            .await
+            .unwrap_or_else(|err| {
+                self.failed(err);
+                None
+            })
+            .filter(|value: &String| value != TOMBSTONE)
+    }
+
+    async fn set(&self, key: &str, value: String) {
+        let ttl = self.ttl.as_millis() as u64;
+        if let Err(err) =
+            self.run(|mut connection| async move {
+                connection.pset_ex::<_, _, ()>(key, value, ttl).await
+            })
+            .await
+        {
+            self.failed(err);
+        }
+    }
+
+    async fn add(&self, key: &str, value: String) {
+        let ttl = self.ttl.as_millis() as u64;
+        if let Err(err) = self
+            .run(|mut connection| async move {
+                redis::cmd("SET")
+                    .arg(key)
+                    .arg(value)
+                    .arg("NX")
+                    .arg("PX")
+                    .arg(ttl)
+                    .query_async::<()>(&mut connection)
+                    .await
+            })
+            .await
+        {
+            self.failed(err);
+        }
+    }
+
+    async fn invalidate(&self, key: &str) {
+        let grace = self.grace.as_millis() as u64;
+        if let Err(err) = self
+            .run(|mut connection| async move {
+                connection.pset_ex::<_, _, ()>(key, TOMBSTONE, grace).await
+            })
+            .await
+        {
+            self.failed(err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::infra::testcontainer::RedisContainer;
+
+    fn cache(url: &str) -> RedisCache {
+        RedisCache::new(
+            url,
+            Duration::from_secs(60),
+            Duration::from_secs(5),
+            Duration::from_millis(200),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_set_get_and_invalidate() {
+        // given
+        let redis = RedisContainer::new(Some("secret")).await;
+        let cache = cache(&format!("redis://:secret@{}/3", redis.address()));
+        // when
+        cache.set("users:1", "{\"id\":1}".to_string()).await;
+        // then
+        assert_eq!(cache.get("users:1").await.as_deref(), Some("{\"id\":1}"));
+        let other_database = self::cache(&format!("redis://:secret@{}/0", redis.address()));
+        assert_eq!(other_database.get("users:1").await, None);
+        // when
+        cache.invalidate("users:1").await;
+        // then
+        assert_eq!(cache.get("users:1").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_add_does_not_replace_a_tombstone() {
+        // given
+        let redis = RedisContainer::new(None).await;
+        let cache = cache(&format!("redis://{}", redis.address()));
+        cache.add("users:1", "{\"version\":1}".to_string()).await;
+        cache.add("users:1", "{\"version\":2}".to_string()).await;
+        assert_eq!(
+            cache.get("users:1").await.as_deref(),
+            Some("{\"version\":1}")
+        );
+        // when
+        cache.invalidate("users:1").await;
+        cache.add("users:1", "{\"version\":1}".to_string()).await;
+        // then
+        assert_eq!(cache.get("users:1").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_entries_expire() {
+        // given
+        let redis = RedisContainer::new(None).await;
+        let cache = RedisCache::new(
+            &format!("redis://{}", redis.address()),
+            Duration::from_millis(50),
+            Duration::from_secs(5),
+            Duration::from_millis(200),
+        );
+        cache.set("users:1", "{}".to_string()).await;
+        // when
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        // then
+        assert_eq!(cache.get("users:1").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_password_is_a_miss() {
+        // given
+        let redis = RedisContainer::new(Some("secret")).await;
+        let cache = cache(&format!("redis://:wrong@{}", redis.address()));
+        // when
+        cache.set("users:1", "{}".to_string()).await;
+        // then
+        assert_eq!(cache.get("users:1").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_after_the_connection_drops() {
+        // given
+        let redis = RedisContainer::new(None).await;
+        let cache = cache(&format!("redis://{}", redis.address()));
+        cache.set("users:1", "{}".to_string()).await;
+        // when
+        redis.drop_connections().await;
+        let _during = cache.get("users:1").await;
+        let after = cache.get("users:1").await;
+        // then
+        assert_eq!(after.as_deref(), Some("{}"));
+    }
+
+    #[tokio::test]
+    async fn test_unresponsive_server_times_out() {
+        // given a server that accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let cache = cache(&format!("redis://{}", listener.local_addr().unwrap()));
+        // when
+        let started = std::time::Instant::now();
+        let value = cache.get("users:1").await;
+        // then
+        assert_eq!(value, None);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
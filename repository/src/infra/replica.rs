@@ -0,0 +1,323 @@
+//! Read replicas of the Postgres primary. Reads are spread across the
+//! replicas in turn, except that a caller who just wrote reads from the
+//! primary for a while, so that replication lag cannot hide its own writes.
+//! A replica whose read fails is left out for a while and the read is
+//! repeated on the primary.
+
+use crate::infra::db::Db;
+use shared::settings::DatabaseSettings;
+use shared::AppError;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct Replica {
+    pool: Arc<PgPool>,
+    /// Set while the replica is left out after a failed read.
+    down_until: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug)]
+pub struct ReplicaSet {
+    replicas: Vec<Replica>,
+    next: AtomicUsize,
+    sticky_for: Duration,
+    retry_after: Duration,
+    /// When each caller last wrote, for the callers still in their window.
+    writes: Mutex<HashMap<String, Instant>>,
+}
+
+impl ReplicaSet {
+    pub fn new(pools: Vec<Arc<PgPool>>, sticky_for: Duration, retry_after: Duration) -> Self {
+        Self {
+            replicas: pools
+                .into_iter()
+                .map(|pool| Replica {
+                    pool,
+                    down_until: Mutex::new(None),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+            sticky_for,
+            retry_after,
+            writes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Pools for `settings.replica_urls`, or `None` without any. The pools
+    /// connect lazily, so that a replica that is down at startup is only
+    /// left out rather than keeping the server from starting, and give up
+    /// on a connection after `replica_timeout_ms` rather than the primary's
+    /// acquire timeout.
+    pub fn connect(settings: &DatabaseSettings) -> Option<Arc<Self>> {
+        if settings.replica_urls.is_empty() {
+            return None;
+        }
+        let pools = settings
+            .replica_urls
+            .iter()
+            .map(|url| {
+                Arc::new(
+                    PgPoolOptions::new()
+                        .max_connections(settings.max_connections)
+                        .min_connections(settings.min_connections)
+                        .acquire_timeout(settings.replica_timeout())
+                        .idle_timeout(settings.idle_timeout())
+                        .connect_lazy(url)
+                        .expect("Invalid replica URL"),
+                )
+            })
+            .collect();
+        Some(Arc::new(Self::new(
+            pools,
+            settings.replica_sticky(),
+            settings.replica_retry(),
+        )))
+    }
+
+    /// Runs `read` on a replica, or on `primary` when the caller is within
+    /// its window, no replica is up or the replica fails. Within a unit of
+    /// work, `primary` is its transaction and always used.
+    pub async fn read<T, F, Fut>(&self, primary: &Db, read: F) -> Result<T, AppError>
+    where
+        F: Fn(Db) -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        if matches!(primary, Db::Pool(_)) && !self.is_sticky() {
+            if let Some(index) = self.pick() {
+                match read(Db::Pool(self.replicas[index].pool.clone())).await {
+                    Err(AppError::InternalServerError) => {
+                        tracing::warn!(replica = index, "read replica failed, using the primary");
+                        metrics::counter!("db_replica_failures_total").increment(1);
+                        self.mark_down(index);
+                    }
+                    result => {
+                        metrics::counter!("db_reads_total", "target" => "replica").increment(1);
+                        return result;
+                    }
+                }
+            }
+        }
+        metrics::counter!("db_reads_total", "target" => "primary").increment(1);
+        read(primary.clone()).await
+    }
+
+    /// Keeps the current caller's reads on the primary for the sticky
+    /// window. Writes made outside of [`shared::caller::scope`] are not
+    /// tracked.
+    pub fn record_write(&self) {
+        let Some(caller) = shared::caller::current() else {
+            return;
+        };
+        if self.sticky_for.is_zero() {
+            return;
+        }
+        let now = Instant::now();
+        let mut writes = self.writes.lock().expect("replica set poisoned");
+        writes.retain(|_, at| now.duration_since(*at) < self.sticky_for);
+        writes.insert(caller, now);
+    }
+
+    pub async fn close(&self) {
+        for replica in &self.replicas {
+            replica.pool.close().await;
+        }
+    }
+
+    fn is_sticky(&self) -> bool {
+        let Some(caller) = shared::caller::current() else {
+            return false;
+        };
+        let writes = self.writes.lock().expect("replica set poisoned");
+        writes
+            .get(&caller)
+            .is_some_and(|at| at.elapsed() < self.sticky_for)
+    }
+
+    /// The next replica in turn that is not left out.
+    fn pick(&self) -> Option<usize> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+        (0..self.replicas.len())
+            .map(|offset| (start + offset) % self.replicas.len())
+            .find(|&index| {
+                let down_until = self.replicas[index]
+                    .down_until
+                    .lock()
+                    .expect("replica set poisoned");
+                down_until.is_none_or(|until| now >= until)
+            })
+    }
+
+    fn mark_down(&self, index: usize) {
+        *self.replicas[index]
+            .down_until
+            .lock()
+            .expect("replica set poisoned") = Some(Instant::now() + self.retry_after);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::entity::user::UserEntity;
+    use crate::infra::cache::MemoryCache;
+    use crate::infra::testcontainer::PostgresContainer;
+    use crate::repository::unit_of_work::{TransactionManager, TransactionManagerImpl};
+    use crate::repository::user::{
+        CachedUserRepository, UserFilter, UserQuery, UserRepository, UserRepositoryImpl,
+    };
+
+    struct Cluster {
+        _primary: PostgresContainer,
+        replica: PostgresContainer,
+        primary_pool: Arc<PgPool>,
+        replicas: Arc<ReplicaSet>,
+    }
+
+    /// A primary and a "replica" that start with the same data but do not
+    /// replicate, so that it shows where each read went.
+    async fn cluster(sticky_for: Duration) -> Cluster {
+        let primary = PostgresContainer::new().await;
+        let replica = PostgresContainer::new().await;
+        let replicas = Arc::new(ReplicaSet::new(
+            vec![replica.pool()],
+            sticky_for,
+            Duration::from_secs(60),
+        ));
+        Cluster {
+            primary_pool: primary.pool(),
+            _primary: primary,
+            replica,
+            replicas,
+        }
+    }
+
+    fn repository(cluster: &Cluster) -> UserRepositoryImpl {
+        UserRepositoryImpl::new(cluster.primary_pool.clone())
+            .with_replicas(Some(cluster.replicas.clone()))
+    }
+
+    fn carol() -> UserEntity {
+        let now = chrono::Utc::now().naive_utc();
+        UserEntity {
+            id: 0,
+            name: "Carol".to_string(),
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+        }
+    }
+
+    async fn create(repository: &UserRepositoryImpl) -> i32 {
+        repository.create_user(carol()).await.unwrap().id
+    }
+
+    #[tokio::test]
+    async fn test_reads_go_to_replica_and_writes_to_primary() {
+        // given
+        let cluster = cluster(Duration::from_secs(60)).await;
+        let repository = repository(&cluster);
+        // when
+        let id = create(&repository).await;
+        // then
+        assert!(repository.find_by_id(id).await.unwrap().is_none());
+        assert!(UserRepositoryImpl::new(cluster.primary_pool.clone())
+            .find_by_id(id)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(repository.find_by_id(1).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_caller_reads_its_own_writes() {
+        // given
+        let cluster = cluster(Duration::from_millis(300)).await;
+        let repository = repository(&cluster);
+        // when
+        let id = shared::caller::scope("7".to_string(), create(&repository)).await;
+        // then
+        let own = shared::caller::scope("7".to_string(), repository.find_by_id(id)).await;
+        let other = shared::caller::scope("8".to_string(), repository.find_by_id(id)).await;
+        assert!(own.unwrap().is_some());
+        assert!(other.unwrap().is_none());
+        // when the window has passed
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        // then
+        let own = shared::caller::scope("7".to_string(), repository.find_by_id(id)).await;
+        assert!(own.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_commit_keeps_caller_on_primary() {
+        // given
+        let cluster = cluster(Duration::from_secs(60)).await;
+        let repository = repository(&cluster);
+        let transactions = TransactionManagerImpl::new(cluster.primary_pool.clone())
+            .with_replicas(Some(cluster.replicas.clone()));
+        // when
+        let id = shared::caller::scope("7".to_string(), async {
+            let unit_of_work = transactions.begin().await.unwrap();
+            let id = unit_of_work.users().create_user(carol()).await.unwrap().id;
+            unit_of_work.commit().await.unwrap();
+            id
+        })
+        .await;
+        // then
+        let own = shared::caller::scope("7".to_string(), repository.find_by_id(id)).await;
+        assert!(own.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_failed_replica_falls_back_to_primary() {
+        // given
+        let cluster = cluster(Duration::ZERO).await;
+        let repository = repository(&cluster);
+        let id = create(&repository).await;
+        cluster.replica.pool().close().await;
+        // when
+        let user = repository.find_by_id(id).await;
+        // then
+        assert_eq!(user.unwrap().unwrap().id, id);
+        assert!(cluster.replicas.pick().is_none());
+        let page = repository
+            .get_users(&UserQuery::new(UserFilter::default(), None, None, None).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(page.total, 3);
+    }
+
+    #[tokio::test]
+    async fn test_cache_is_not_filled_from_a_lagging_replica() {
+        // given
+        let cluster = cluster(Duration::from_secs(60)).await;
+        let repository = CachedUserRepository::new(
+            Arc::new(repository(&cluster)),
+            Arc::new(UserRepositoryImpl::new(cluster.primary_pool.clone())),
+            Arc::new(MemoryCache::new(
+                10,
+                Duration::from_secs(60),
+                Duration::from_secs(5),
+            )),
+        );
+        let mut user = repository.find_by_id(1).await.unwrap().unwrap();
+        user.name = "Alicia".to_string();
+        shared::caller::scope("7".to_string(), repository.update_user(user, None))
+            .await
+            .unwrap();
+        // when another caller misses the cache
+        let other = shared::caller::scope("8".to_string(), repository.find_by_id(1)).await;
+        // then it sees the write, and so do later hits
+        assert_eq!(other.unwrap().unwrap().name, "Alicia");
+        let hit = shared::caller::scope("8".to_string(), repository.find_by_id(1)).await;
+        assert_eq!(hit.unwrap().unwrap().name, "Alicia");
+    }
+}
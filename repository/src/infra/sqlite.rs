@@ -0,0 +1,32 @@
+use chrono::SubsecRound;
+use shared::settings::DatabaseSettings;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Opens the database file, creating it if need be, with foreign keys
+/// enforced as in Postgres.
+pub async fn pool(settings: &DatabaseSettings) -> Arc<SqlitePool> {
+    let options = SqliteConnectOptions::from_str(&settings.url)
+        .expect("Invalid SQLite URL")
+        .create_if_missing(true)
+        .foreign_keys(true);
+    Arc::new(
+        SqlitePoolOptions::new()
+            .max_connections(settings.max_connections)
+            .min_connections(settings.min_connections)
+            .acquire_timeout(settings.acquire_timeout())
+            .idle_timeout(settings.idle_timeout())
+            .connect_with(options)
+            .await
+            .expect("Failed to open SQLite"),
+    )
+}
+
+/// Stands in for `CURRENT_TIMESTAMP`, which SQLite only keeps to the second
+/// and writes in another format than bound values. Timestamps are compared
+/// as text, so every one of them is bound from here instead.
+pub fn now() -> chrono::NaiveDateTime {
+    chrono::Utc::now().naive_utc().trunc_subsecs(6)
+}
@@ -1,7 +1,9 @@
+use crate::infra::migration::MIGRATOR;
 use sqlx::PgPool;
 use std::sync::Arc;
-use testcontainers::{runners::AsyncRunner, ContainerAsync};
+use testcontainers::{runners::AsyncRunner, ContainerAsync, ImageExt};
 use testcontainers_modules::postgres::Postgres;
+use testcontainers_modules::redis::{Redis, REDIS_PORT};
 
 pub struct PostgresContainer {
     _container: ContainerAsync<Postgres>,
@@ -9,7 +11,20 @@ pub struct PostgresContainer {
 }
 
 impl PostgresContainer {
+    /// Starts Postgres with the schema applied and the test data seeded.
     pub async fn new() -> Self {
+        let container = Self::unseeded().await;
+        // migrations/test only holds seed data on top of the schema
+        let mut seed = sqlx::migrate!("../migrations/test");
+        seed.set_ignore_missing(true)
+            .run(container.pool.as_ref())
+            .await
+            .expect("Failed to seed test data");
+        container
+    }
+
+    /// Starts Postgres with only the schema applied.
+    pub async fn unseeded() -> Self {
         let _container = Postgres::default()
             .with_user("postgres")
             .with_password("postgres")
@@ -26,7 +41,7 @@ impl PostgresContainer {
                 .await
                 .expect("Failed to connect to Postgres"),
         );
-        sqlx::migrate!("../migrations/test")
+        MIGRATOR
             .run(pool.as_ref())
             .await
             .expect("Failed to run migrations");
@@ -38,3 +53,54 @@ impl PostgresContainer {
         self.pool.clone()
     }
 }
+
+pub struct RedisContainer {
+    _container: ContainerAsync<Redis>,
+    address: String,
+    password: Option<String>,
+}
+
+impl RedisContainer {
+    /// Starts Redis; with `password`, clients have to `AUTH` first.
+    pub async fn new(password: Option<&str>) -> Self {
+        let mut command = vec!["redis-server".to_string()];
+        if let Some(password) = password {
+            command.extend(["--requirepass".to_string(), password.to_string()]);
+        }
+        let _container = Redis::default().with_cmd(command).start().await.unwrap();
+        let address = format!(
+            "localhost:{}",
+            _container.get_host_port_ipv4(REDIS_PORT).await.unwrap()
+        );
+        Self {
+            _container,
+            address,
+            password: password.map(str::to_string),
+        }
+    }
+
+    /// `host:port` to put in a `redis://` URL.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Closes every client connection, as a restart of the server would.
+    pub async fn drop_connections(&self) {
+        let password = self.password.as_deref().unwrap_or_default();
+        let url = format!("redis://:{password}@{}", self.address);
+        let mut connection = redis::Client::open(url)
+            .unwrap()
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap();
+        let _: i64 = redis::cmd("CLIENT")
+            .arg("KILL")
+            .arg("TYPE")
+            .arg("normal")
+            .arg("SKIPME")
+            .arg("yes")
+            .query_async(&mut connection)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,49 @@
+use crate::infra::migration::SQLITE_MIGRATOR;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+pub struct SqliteDatabase {
+    _dir: TempDir,
+    pool: Arc<SqlitePool>,
+}
+
+impl SqliteDatabase {
+    /// Opens a fresh database file with the schema applied and the test
+    /// data seeded.
+    pub async fn new() -> Self {
+        let database = Self::unseeded().await;
+        // the Postgres seed data is plain enough SQL for SQLite too
+        let mut seed = sqlx::migrate!("../migrations/test");
+        seed.set_ignore_missing(true)
+            .run(database.pool.as_ref())
+            .await
+            .expect("Failed to seed test data");
+        database
+    }
+
+    /// Opens a fresh database file with only the schema applied.
+    pub async fn unseeded() -> Self {
+        let _dir = tempfile::tempdir().unwrap();
+        let options = SqliteConnectOptions::new()
+            .filename(_dir.path().join("test.db"))
+            .create_if_missing(true)
+            .foreign_keys(true);
+        let pool = Arc::new(
+            SqlitePool::connect_with(options)
+                .await
+                .expect("Failed to open SQLite"),
+        );
+        SQLITE_MIGRATOR
+            .run(pool.as_ref())
+            .await
+            .expect("Failed to run migrations");
+
+        Self { _dir, pool }
+    }
+
+    pub fn pool(&self) -> Arc<SqlitePool> {
+        self.pool.clone()
+    }
+}
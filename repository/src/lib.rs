@@ -1,11 +1,34 @@
 pub mod entity {
+    pub mod audit_event;
+    pub mod credential;
+    pub mod memo;
+    pub mod outbox_event;
+    pub mod refresh_token;
     pub mod user;
 }
 pub mod infra {
+    pub mod backend;
+    pub mod cache;
+    pub mod db;
+    pub mod memory;
+    pub mod migration;
     pub mod postgres;
+    pub mod query;
+    pub mod redis;
+    pub mod replica;
+    pub mod sqlite;
     #[cfg(test)]
     pub mod testcontainer;
+    #[cfg(test)]
+    pub mod testsqlite;
 }
 pub mod repository {
+    pub mod audit;
+    pub mod credential;
+    pub mod memo;
+    pub mod outbox;
+    pub mod refresh_token;
+    pub mod role;
+    pub mod unit_of_work;
     pub mod user;
 }
@@ -0,0 +1,453 @@
+use crate::entity::audit_event::AuditEventEntity;
+use crate::infra::db::Db;
+use crate::infra::memory::{self, MemoryStore, Sequence};
+use crate::infra::query::push_page;
+use crate::infra::sqlite;
+use shared::query::{CursorKind, CursorValue, Keyed, ListQuery, Page, SortField};
+use shared::AppError;
+use sqlx::types::Json;
+use sqlx::{Database, Encode, QueryBuilder, Sqlite, Type};
+use tracing::instrument;
+
+/// A row of `audit_events`, whose JSON columns sqlx decodes through [`Json`].
+#[derive(sqlx::FromRow)]
+struct AuditEventRow {
+    id: i64,
+    actor_id: Option<i32>,
+    action: String,
+    entity_type: String,
+    entity_id: i32,
+    old_values: Option<Json<serde_json::Value>>,
+    new_values: Option<Json<serde_json::Value>>,
+    request_id: Option<String>,
+    created_at: chrono::NaiveDateTime,
+}
+
+impl From<AuditEventRow> for AuditEventEntity {
+    fn from(row: AuditEventRow) -> Self {
+        Self {
+            id: row.id,
+            actor_id: row.actor_id,
+            action: row.action,
+            entity_type: row.entity_type,
+            entity_id: row.entity_id,
+            old_values: row.old_values.map(|Json(values)| values),
+            new_values: row.new_values.map(|Json(values)| values),
+            request_id: row.request_id,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuditSortField {
+    Id,
+    CreatedAt,
+}
+
+impl SortField for AuditSortField {
+    const TIE_BREAKER: Self = AuditSortField::Id;
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(AuditSortField::Id),
+            "created_at" => Some(AuditSortField::CreatedAt),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        self.column()
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            AuditSortField::Id => "id",
+            AuditSortField::CreatedAt => "created_at",
+        }
+    }
+
+    fn kind(&self) -> CursorKind {
+        match self {
+            AuditSortField::Id => CursorKind::Int,
+            AuditSortField::CreatedAt => CursorKind::Timestamp,
+        }
+    }
+}
+
+impl Keyed<AuditSortField> for AuditEventEntity {
+    fn key(&self, field: AuditSortField) -> CursorValue {
+        match field {
+            AuditSortField::Id => CursorValue::Int(self.id),
+            AuditSortField::CreatedAt => CursorValue::Timestamp(self.created_at),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditFilter {
+    /// Only events about entities of this type, e.g. `users`.
+    pub entity_type: Option<String>,
+    /// Only events about the entity with this id.
+    pub entity_id: Option<i32>,
+}
+
+pub type AuditQuery = ListQuery<AuditSortField, AuditFilter>;
+
+/// The audit log. Events can be appended and listed, never changed.
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait AuditRepository: Send + Sync {
+    /// Appends `event`; its id and time are assigned here.
+    async fn record(&self, event: AuditEventEntity) -> Result<AuditEventEntity, AppError>;
+    async fn get_events(&self, query: &AuditQuery) -> Result<Page<AuditEventEntity>, AppError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct AuditRepositoryImpl {
+    pub db: Db,
+}
+
+impl AuditRepositoryImpl {
+    pub fn new(db: impl Into<Db>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditRepository for AuditRepositoryImpl {
+    #[instrument(skip(self, event), fields(entity_type = %event.entity_type, entity_id = event.entity_id))]
+    async fn record(&self, event: AuditEventEntity) -> Result<AuditEventEntity, AppError> {
+        let row = sqlx::query_as::<_, AuditEventRow>(
+            r#"
+            INSERT INTO audit_events
+                (actor_id, action, entity_type, entity_id, old_values, new_values, request_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *;
+            "#,
+        )
+        .bind(event.actor_id)
+        .bind(&event.action)
+        .bind(&event.entity_type)
+        .bind(event.entity_id)
+        .bind(event.old_values.map(Json))
+        .bind(event.new_values.map(Json))
+        .bind(&event.request_id)
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(row.into())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_events(&self, query: &AuditQuery) -> Result<Page<AuditEventEntity>, AppError> {
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM audit_events WHERE TRUE");
+        push_filter(&mut count, &query.filter);
+        let total: i64 = count
+            .build_query_scalar()
+            .fetch_one(&mut *self.db.acquire().await?)
+            .await?;
+
+        let mut select = QueryBuilder::new("SELECT * FROM audit_events WHERE TRUE");
+        push_filter(&mut select, &query.filter);
+        push_page(&mut select, query);
+        let rows = select
+            .build_query_as::<AuditEventRow>()
+            .fetch_all(&mut *self.db.acquire().await?)
+            .await?;
+        let entities = rows.into_iter().map(AuditEventEntity::from).collect();
+        Ok(Page::from_rows(entities, query, total))
+    }
+}
+
+/// [`AuditRepository`] on SQLite.
+#[derive(Debug, Clone)]
+pub struct SqliteAuditRepository {
+    pub db: Db<Sqlite>,
+}
+
+impl SqliteAuditRepository {
+    pub fn new(db: impl Into<Db<Sqlite>>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditRepository for SqliteAuditRepository {
+    #[instrument(skip(self, event), fields(entity_type = %event.entity_type, entity_id = event.entity_id))]
+    async fn record(&self, event: AuditEventEntity) -> Result<AuditEventEntity, AppError> {
+        let row = sqlx::query_as::<_, AuditEventRow>(
+            r#"
+            INSERT INTO audit_events
+                (actor_id, action, entity_type, entity_id, old_values, new_values, request_id,
+                 created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *;
+            "#,
+        )
+        .bind(event.actor_id)
+        .bind(&event.action)
+        .bind(&event.entity_type)
+        .bind(event.entity_id)
+        .bind(event.old_values.map(Json))
+        .bind(event.new_values.map(Json))
+        .bind(&event.request_id)
+        .bind(sqlite::now())
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(row.into())
+    }
+
+    #[instrument(skip(self))]
+    async fn get_events(&self, query: &AuditQuery) -> Result<Page<AuditEventEntity>, AppError> {
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM audit_events WHERE TRUE");
+        push_filter(&mut count, &query.filter);
+        let total: i64 = count
+            .build_query_scalar()
+            .fetch_one(&mut *self.db.acquire().await?)
+            .await?;
+
+        let mut select = QueryBuilder::new("SELECT * FROM audit_events WHERE TRUE");
+        push_filter(&mut select, &query.filter);
+        push_page(&mut select, query);
+        let rows = select
+            .build_query_as::<AuditEventRow>()
+            .fetch_all(&mut *self.db.acquire().await?)
+            .await?;
+        let entities = rows.into_iter().map(AuditEventEntity::from).collect();
+        Ok(Page::from_rows(entities, query, total))
+    }
+}
+
+fn push_filter<'a, DB>(builder: &mut QueryBuilder<'a, DB>, filter: &AuditFilter)
+where
+    DB: Database,
+    i32: Encode<'a, DB> + Type<DB>,
+    String: Encode<'a, DB> + Type<DB>,
+{
+    if let Some(entity_type) = &filter.entity_type {
+        builder
+            .push(" AND entity_type = ")
+            .push_bind(entity_type.clone());
+    }
+    if let Some(entity_id) = filter.entity_id {
+        builder.push(" AND entity_id = ").push_bind(entity_id);
+    }
+}
+
+/// [`AuditRepository`] on a [`MemoryStore`].
+#[derive(Debug, Clone)]
+pub struct InMemoryAuditRepository {
+    pub store: MemoryStore,
+}
+
+impl InMemoryAuditRepository {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditRepository for InMemoryAuditRepository {
+    async fn record(&self, event: AuditEventEntity) -> Result<AuditEventEntity, AppError> {
+        let event = AuditEventEntity {
+            id: self.store.next_id(Sequence::AuditEvents).into(),
+            created_at: self.store.now(),
+            ..event
+        };
+        self.store.write(|tables| {
+            tables.audit_events.insert(event.id, event.clone());
+            Ok(event)
+        })
+    }
+
+    async fn get_events(&self, query: &AuditQuery) -> Result<Page<AuditEventEntity>, AppError> {
+        let filter = &query.filter;
+        let events = self.store.read(|tables| {
+            tables
+                .audit_events
+                .values()
+                .filter(|event| {
+                    filter
+                        .entity_type
+                        .as_ref()
+                        .is_none_or(|entity_type| &event.entity_type == entity_type)
+                })
+                .filter(|event| filter.entity_id.is_none_or(|id| event.entity_id == id))
+                .cloned()
+                .collect()
+        });
+        Ok(memory::page(events, query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::infra::testcontainer::PostgresContainer;
+    use crate::infra::testsqlite::SqliteDatabase;
+    use serde_json::json;
+
+    /// Runs every case against each backend.
+    macro_rules! conformance {
+        ($($case:ident),* $(,)?) => {
+            mod postgres {
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        let container = super::PostgresContainer::new().await;
+                        let repository = super::AuditRepositoryImpl::new(container.pool());
+                        super::$case(&repository).await;
+                    }
+                )*
+            }
+
+            mod sqlite {
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        let database = super::SqliteDatabase::new().await;
+                        let repository = super::SqliteAuditRepository::new(database.pool());
+                        super::$case(&repository).await;
+                    }
+                )*
+            }
+
+            mod memory {
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        let store = super::MemoryStore::seeded();
+                        super::$case(&super::InMemoryAuditRepository::new(store)).await;
+                    }
+                )*
+            }
+        };
+    }
+
+    conformance!(test_record, test_get_events_filtered_and_paged);
+
+    fn event(entity_type: &str, entity_id: i32, action: &str) -> AuditEventEntity {
+        AuditEventEntity {
+            id: 0,
+            actor_id: Some(1),
+            action: action.to_string(),
+            entity_type: entity_type.to_string(),
+            entity_id,
+            old_values: Some(json!({ "name": "Bob" })),
+            new_values: Some(json!({ "name": "Robert" })),
+            request_id: Some("abc-123".to_string()),
+            created_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    async fn test_record(repository: &dyn AuditRepository) {
+        // when
+        let recorded = repository
+            .record(event("users", 2, "update"))
+            .await
+            .unwrap();
+        let created = repository
+            .record(AuditEventEntity {
+                actor_id: None,
+                old_values: None,
+                request_id: None,
+                ..event("users", 3, "create")
+            })
+            .await
+            .unwrap();
+        // then
+        assert!(created.id > recorded.id);
+        let page = repository
+            .get_events(&AuditQuery::new(AuditFilter::default(), None, None, None).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(page.items, vec![recorded.clone(), created]);
+        assert_eq!(recorded.actor_id, Some(1));
+        assert_eq!(recorded.action, "update");
+        assert_eq!(recorded.old_values, Some(json!({ "name": "Bob" })));
+        assert_eq!(recorded.new_values, Some(json!({ "name": "Robert" })));
+        assert_eq!(recorded.request_id.as_deref(), Some("abc-123"));
+    }
+
+    async fn test_get_events_filtered_and_paged(repository: &dyn AuditRepository) {
+        // given
+        for (entity_type, entity_id) in [("users", 1), ("users", 2), ("memos", 1), ("users", 1)] {
+            repository
+                .record(event(entity_type, entity_id, "update"))
+                .await
+                .unwrap();
+        }
+        let filter = AuditFilter {
+            entity_type: Some("users".to_string()),
+            entity_id: Some(1),
+        };
+        // when
+        let first = repository
+            .get_events(&AuditQuery::new(filter.clone(), Some("-id"), Some(1), None).unwrap())
+            .await
+            .unwrap();
+        let cursor = first.next_cursor.as_ref().unwrap().encode();
+        let second = repository
+            .get_events(&AuditQuery::new(filter, Some("-id"), Some(1), Some(&cursor)).unwrap())
+            .await
+            .unwrap();
+        // then
+        assert_eq!(first.total, 2);
+        assert!(second.next_cursor.is_none());
+        let ids: Vec<i64> = first
+            .items
+            .iter()
+            .chain(&second.items)
+            .map(|e| e.id)
+            .collect();
+        assert!(ids[0] > ids[1]);
+        assert!(first
+            .items
+            .iter()
+            .chain(&second.items)
+            .all(|event| event.entity_type == "users" && event.entity_id == 1));
+    }
+
+    #[tokio::test]
+    async fn test_events_cannot_be_changed() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = AuditRepositoryImpl::new(container.pool());
+        repository
+            .record(event("users", 1, "update"))
+            .await
+            .unwrap();
+        // when
+        let updated = sqlx::query("UPDATE audit_events SET action = 'create';")
+            .execute(&*container.pool())
+            .await;
+        let deleted = sqlx::query("DELETE FROM audit_events;")
+            .execute(&*container.pool())
+            .await;
+        // then
+        assert!(updated.is_err());
+        assert!(deleted.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_events_cannot_be_changed() {
+        // given
+        let database = SqliteDatabase::new().await;
+        let repository = SqliteAuditRepository::new(database.pool());
+        repository
+            .record(event("users", 1, "update"))
+            .await
+            .unwrap();
+        // when
+        let updated = sqlx::query("UPDATE audit_events SET action = 'create';")
+            .execute(&*database.pool())
+            .await;
+        let deleted = sqlx::query("DELETE FROM audit_events;")
+            .execute(&*database.pool())
+            .await;
+        // then
+        assert!(updated.is_err());
+        assert!(deleted.is_err());
+    }
+}
@@ -0,0 +1,481 @@
+use crate::entity::credential::CredentialEntity;
+use crate::entity::user::UserEntity;
+use crate::infra::db::Db;
+use crate::infra::memory::{MemoryStore, Sequence, UserRow};
+use crate::infra::sqlite;
+use shared::AppError;
+use sqlx::{Connection, Sqlite};
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait CredentialRepository: Send + Sync {
+    async fn find_by_email(&self, email: &str) -> Result<Option<CredentialEntity>, AppError>;
+    /// Creates the user together with its login credential.
+    /// The `user_id` of `credential` is ignored. New users get the `member` role.
+    async fn create_user(
+        &self,
+        user: UserEntity,
+        credential: CredentialEntity,
+    ) -> Result<UserEntity, AppError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct CredentialRepositoryImpl {
+    pub db: Db,
+}
+
+impl CredentialRepositoryImpl {
+    pub fn new(db: impl Into<Db>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialRepository for CredentialRepositoryImpl {
+    async fn find_by_email(&self, email: &str) -> Result<Option<CredentialEntity>, AppError> {
+        let entity = sqlx::query_as::<_, CredentialEntity>(
+            r#"
+            SELECT id AS user_id, email, password_hash
+            FROM users
+            WHERE email = $1 AND password_hash IS NOT NULL AND deleted_at IS NULL;
+            "#,
+        )
+        .bind(email)
+        .fetch_optional(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    async fn create_user(
+        &self,
+        user: UserEntity,
+        credential: CredentialEntity,
+    ) -> Result<UserEntity, AppError> {
+        let mut connection = self.db.acquire().await?;
+        let mut tx = connection.begin().await?;
+        let entity = sqlx::query_as::<_, UserEntity>(
+            r#"
+            INSERT INTO users (name, email, password_hash)
+            VALUES ($1, $2, $3)
+            RETURNING *;
+            "#,
+        )
+        .bind(&user.name)
+        .bind(&credential.email)
+        .bind(&credential.password_hash)
+        .fetch_one(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO user_roles (user_id, role_id)
+            SELECT $1, id FROM roles WHERE name = 'member';
+            "#,
+        )
+        .bind(entity.id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(entity)
+    }
+}
+
+/// [`CredentialRepository`] on SQLite.
+#[derive(Debug, Clone)]
+pub struct SqliteCredentialRepository {
+    pub db: Db<Sqlite>,
+}
+
+impl SqliteCredentialRepository {
+    pub fn new(db: impl Into<Db<Sqlite>>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialRepository for SqliteCredentialRepository {
+    async fn find_by_email(&self, email: &str) -> Result<Option<CredentialEntity>, AppError> {
+        let entity = sqlx::query_as::<_, CredentialEntity>(
+            r#"
+            SELECT id AS user_id, email, password_hash
+            FROM users
+            WHERE email = $1 AND password_hash IS NOT NULL AND deleted_at IS NULL;
+            "#,
+        )
+        .bind(email)
+        .fetch_optional(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    async fn create_user(
+        &self,
+        user: UserEntity,
+        credential: CredentialEntity,
+    ) -> Result<UserEntity, AppError> {
+        let mut connection = self.db.acquire().await?;
+        let mut tx = connection.begin().await?;
+        let entity = sqlx::query_as::<_, UserEntity>(
+            r#"
+            INSERT INTO users (name, email, password_hash, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $4)
+            RETURNING *;
+            "#,
+        )
+        .bind(&user.name)
+        .bind(&credential.email)
+        .bind(&credential.password_hash)
+        .bind(sqlite::now())
+        .fetch_one(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO user_roles (user_id, role_id)
+            SELECT $1, id FROM roles WHERE name = 'member';
+            "#,
+        )
+        .bind(entity.id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(entity)
+    }
+}
+
+/// [`CredentialRepository`] on a [`MemoryStore`].
+#[derive(Debug, Clone)]
+pub struct InMemoryCredentialRepository {
+    pub store: MemoryStore,
+}
+
+impl InMemoryCredentialRepository {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl CredentialRepository for InMemoryCredentialRepository {
+    async fn find_by_email(&self, email: &str) -> Result<Option<CredentialEntity>, AppError> {
+        Ok(self.store.read(|tables| {
+            tables
+                .users
+                .values()
+                .filter(|row| row.user.deleted_at.is_none())
+                .find(|row| row.email.as_deref() == Some(email))
+                .and_then(|row| {
+                    Some(CredentialEntity {
+                        user_id: row.user.id,
+                        email: email.to_string(),
+                        password_hash: row.password_hash.clone()?,
+                    })
+                })
+        }))
+    }
+
+    async fn create_user(
+        &self,
+        user: UserEntity,
+        credential: CredentialEntity,
+    ) -> Result<UserEntity, AppError> {
+        let now = self.store.now();
+        let user = UserEntity {
+            id: self.store.next_id(Sequence::Users),
+            name: user.name,
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+        };
+        self.store.write(|tables| {
+            // users in the trash give up their email
+            if tables.users.values().any(|row| {
+                row.user.deleted_at.is_none()
+                    && row.email.as_deref() == Some(credential.email.as_str())
+            }) {
+                return Err(AppError::Conflict);
+            }
+            tables.users.insert(
+                user.id,
+                UserRow {
+                    user: user.clone(),
+                    email: Some(credential.email),
+                    password_hash: Some(credential.password_hash),
+                },
+            );
+            tables.user_roles.insert((user.id, "member".to_string()));
+            Ok(user)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::infra::testcontainer::PostgresContainer;
+    use crate::infra::testsqlite::SqliteDatabase;
+    use crate::repository::role::{InMemoryRoleRepository, RoleRepository, SqliteRoleRepository};
+    use crate::repository::user::{
+        InMemoryUserRepository, SqliteUserRepository, UserRepository, UserRepositoryImpl,
+    };
+
+    /// Registers Alice's email again once she is in the trash; restoring
+    /// her then conflicts with the new owner of the email.
+    async fn test_reregister_email_of_deleted_user(
+        credentials: &dyn CredentialRepository,
+        users: &dyn UserRepository,
+    ) {
+        // given
+        users.delete_user(1, None).await.unwrap();
+        let now = chrono::Utc::now().naive_utc();
+        // when
+        let user = credentials
+            .create_user(
+                UserEntity {
+                    id: 0,
+                    name: "Alice".to_string(),
+                    version: 1,
+                    deleted_at: None,
+                    created_at: now,
+                    updated_at: now,
+                },
+                CredentialEntity {
+                    user_id: 0,
+                    email: "alice@example.com".to_string(),
+                    password_hash: "hash".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        // then
+        let credential = credentials
+            .find_by_email("alice@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(credential.user_id, user.id);
+        let restored = users.restore_user(1).await;
+        assert!(matches!(restored, Err(AppError::Conflict)));
+    }
+
+    #[tokio::test]
+    async fn test_find_by_email() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = CredentialRepositoryImpl::new(container.pool());
+        // when
+        let credential = repository.find_by_email("alice@example.com").await.unwrap();
+        // then
+        assert!(credential.is_some());
+        let credential = credential.unwrap();
+        assert_eq!(credential.user_id, 1);
+        assert_eq!(credential.email, "alice@example.com");
+        assert!(credential.password_hash.starts_with("$argon2id$"));
+    }
+
+    #[tokio::test]
+    async fn test_find_by_email_not_found() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = CredentialRepositoryImpl::new(container.pool());
+        // when
+        let credential = repository
+            .find_by_email("nobody@example.com")
+            .await
+            .unwrap();
+        // then
+        assert!(credential.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_find_by_email_of_deleted_user() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = CredentialRepositoryImpl::new(container.pool());
+        UserRepositoryImpl::new(container.pool())
+            .delete_user(1, None)
+            .await
+            .unwrap();
+        // when
+        let credential = repository.find_by_email("alice@example.com").await.unwrap();
+        // then
+        assert!(credential.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_create_user() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = CredentialRepositoryImpl::new(container.pool());
+        let current_time = chrono::Utc::now().naive_utc();
+        // when
+        let user = repository
+            .create_user(
+                UserEntity {
+                    id: 0,
+                    name: "Kate".to_string(),
+                    version: 1,
+                    deleted_at: None,
+                    created_at: current_time,
+                    updated_at: current_time,
+                },
+                CredentialEntity {
+                    user_id: 0,
+                    email: "kate@example.com".to_string(),
+                    password_hash: "hash".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(user.id, 3);
+        assert_eq!(user.name, "Kate");
+        let credential = repository
+            .find_by_email("kate@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(credential.user_id, 3);
+        assert_eq!(credential.password_hash, "hash");
+        let roles: Vec<String> = sqlx::query_scalar(
+            "SELECT roles.name FROM user_roles JOIN roles ON roles.id = user_roles.role_id WHERE user_id = 3;",
+        )
+        .fetch_all(&*container.pool())
+        .await
+        .unwrap();
+        assert_eq!(roles, vec!["member"]);
+    }
+
+    #[tokio::test]
+    async fn test_create_user_duplicate_email() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = CredentialRepositoryImpl::new(container.pool());
+        let current_time = chrono::Utc::now().naive_utc();
+        // when
+        let result = repository
+            .create_user(
+                UserEntity {
+                    id: 0,
+                    name: "Alice".to_string(),
+                    version: 1,
+                    deleted_at: None,
+                    created_at: current_time,
+                    updated_at: current_time,
+                },
+                CredentialEntity {
+                    user_id: 0,
+                    email: "alice@example.com".to_string(),
+                    password_hash: "hash".to_string(),
+                },
+            )
+            .await;
+        // then
+        assert!(matches!(result, Err(AppError::Conflict)));
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_create_user() {
+        // given
+        let database = SqliteDatabase::new().await;
+        let repository = SqliteCredentialRepository::new(database.pool());
+        let credential = |email: &str| CredentialEntity {
+            user_id: 0,
+            email: email.to_string(),
+            password_hash: "$argon2id$hash".to_string(),
+        };
+        let user = UserEntity {
+            id: 0,
+            name: "Kate".to_string(),
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: chrono::Utc::now().naive_utc(),
+            version: 1,
+            deleted_at: None,
+        };
+        // when
+        let duplicate = repository
+            .create_user(user.clone(), credential("alice@example.com"))
+            .await;
+        let created = repository
+            .create_user(user, credential("kate@example.com"))
+            .await
+            .unwrap();
+        // then
+        assert!(matches!(duplicate, Err(AppError::Conflict)));
+        let found = repository.find_by_email("kate@example.com").await.unwrap();
+        assert_eq!(found.unwrap().user_id, created.id);
+        let roles = SqliteRoleRepository::new(database.pool())
+            .find_roles_for_user(created.id)
+            .await
+            .unwrap();
+        assert_eq!(roles, vec!["member"]);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_create_user() {
+        // given
+        let store = MemoryStore::seeded();
+        let repository = InMemoryCredentialRepository::new(store.clone());
+        let credential = |email: &str| CredentialEntity {
+            user_id: 0,
+            email: email.to_string(),
+            password_hash: "$argon2id$hash".to_string(),
+        };
+        let user = UserEntity {
+            id: 0,
+            name: "Kate".to_string(),
+            created_at: chrono::Utc::now().naive_utc(),
+            updated_at: chrono::Utc::now().naive_utc(),
+            version: 1,
+            deleted_at: None,
+        };
+        // when
+        let duplicate = repository
+            .create_user(user.clone(), credential("alice@example.com"))
+            .await;
+        let created = repository
+            .create_user(user, credential("kate@example.com"))
+            .await
+            .unwrap();
+        // then the failed insert still used up an id
+        assert!(matches!(duplicate, Err(AppError::Conflict)));
+        assert_eq!(created.id, 4);
+        let found = repository.find_by_email("kate@example.com").await.unwrap();
+        assert_eq!(found.unwrap().user_id, 4);
+        let roles = InMemoryRoleRepository::new(store)
+            .find_roles_for_user(4)
+            .await
+            .unwrap();
+        assert_eq!(roles, vec!["member"]);
+    }
+
+    #[tokio::test]
+    async fn test_reregister_email_of_deleted_user_on_postgres() {
+        let container = PostgresContainer::new().await;
+        test_reregister_email_of_deleted_user(
+            &CredentialRepositoryImpl::new(container.pool()),
+            &UserRepositoryImpl::new(container.pool()),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_reregister_email_of_deleted_user_on_sqlite() {
+        let database = SqliteDatabase::new().await;
+        test_reregister_email_of_deleted_user(
+            &SqliteCredentialRepository::new(database.pool()),
+            &SqliteUserRepository::new(database.pool()),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_reregister_email_of_deleted_user_in_memory() {
+        let store = MemoryStore::seeded();
+        test_reregister_email_of_deleted_user(
+            &InMemoryCredentialRepository::new(store.clone()),
+            &InMemoryUserRepository::new(store),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,350 @@
+use crate::entity::memo::MemoEntity;
+use crate::infra::db::Db;
+use crate::infra::memory::{MemoryStore, Sequence};
+use crate::infra::sqlite;
+use shared::AppError;
+use sqlx::Sqlite;
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait MemoRepository: Send + Sync {
+    async fn get_memos(&self) -> Result<Vec<MemoEntity>, AppError>;
+    async fn find_by_id(&self, id: i32) -> Result<Option<MemoEntity>, AppError>;
+    async fn create_memo(&self, memo: MemoEntity) -> Result<MemoEntity, AppError>;
+    async fn update_memo(&self, memo: MemoEntity) -> Result<MemoEntity, AppError>;
+    async fn delete_memo(&self, id: i32) -> Result<(), AppError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoRepositoryImpl {
+    pub db: Db,
+}
+
+impl MemoRepositoryImpl {
+    pub fn new(db: impl Into<Db>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl MemoRepository for MemoRepositoryImpl {
+    async fn get_memos(&self) -> Result<Vec<MemoEntity>, AppError> {
+        let entities = sqlx::query_as::<_, MemoEntity>("SELECT * FROM memos ORDER BY id;")
+            .fetch_all(&mut *self.db.acquire().await?)
+            .await?;
+        Ok(entities)
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<Option<MemoEntity>, AppError> {
+        let entity = sqlx::query_as::<_, MemoEntity>("SELECT * FROM memos WHERE id = $1;")
+            .bind(id)
+            .fetch_optional(&mut *self.db.acquire().await?)
+            .await?;
+        Ok(entity)
+    }
+
+    async fn create_memo(&self, memo: MemoEntity) -> Result<MemoEntity, AppError> {
+        let entity = sqlx::query_as::<_, MemoEntity>(
+            r#"
+            INSERT INTO memos (user_id, title, content)
+            VALUES ($1, $2, $3)
+            RETURNING *;
+            "#,
+        )
+        .bind(memo.user_id)
+        .bind(&memo.title)
+        .bind(&memo.content)
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    async fn update_memo(&self, memo: MemoEntity) -> Result<MemoEntity, AppError> {
+        let entity = sqlx::query_as::<_, MemoEntity>(
+            r#"
+            UPDATE memos
+            SET title = $2, content = $3, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            RETURNING *;
+            "#,
+        )
+        .bind(memo.id)
+        .bind(&memo.title)
+        .bind(&memo.content)
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    async fn delete_memo(&self, id: i32) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM memos WHERE id = $1;")
+            .bind(id)
+            .execute(&mut *self.db.acquire().await?)
+            .await?;
+        Ok(())
+    }
+}
+
+/// [`MemoRepository`] on SQLite.
+#[derive(Debug, Clone)]
+pub struct SqliteMemoRepository {
+    pub db: Db<Sqlite>,
+}
+
+impl SqliteMemoRepository {
+    pub fn new(db: impl Into<Db<Sqlite>>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl MemoRepository for SqliteMemoRepository {
+    async fn get_memos(&self) -> Result<Vec<MemoEntity>, AppError> {
+        let entities = sqlx::query_as::<_, MemoEntity>("SELECT * FROM memos ORDER BY id;")
+            .fetch_all(&mut *self.db.acquire().await?)
+            .await?;
+        Ok(entities)
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<Option<MemoEntity>, AppError> {
+        let entity = sqlx::query_as::<_, MemoEntity>("SELECT * FROM memos WHERE id = $1;")
+            .bind(id)
+            .fetch_optional(&mut *self.db.acquire().await?)
+            .await?;
+        Ok(entity)
+    }
+
+    async fn create_memo(&self, memo: MemoEntity) -> Result<MemoEntity, AppError> {
+        let entity = sqlx::query_as::<_, MemoEntity>(
+            r#"
+            INSERT INTO memos (user_id, title, content, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $4)
+            RETURNING *;
+            "#,
+        )
+        .bind(memo.user_id)
+        .bind(&memo.title)
+        .bind(&memo.content)
+        .bind(sqlite::now())
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    async fn update_memo(&self, memo: MemoEntity) -> Result<MemoEntity, AppError> {
+        let entity = sqlx::query_as::<_, MemoEntity>(
+            r#"
+            UPDATE memos
+            SET title = $2, content = $3, updated_at = $4
+            WHERE id = $1
+            RETURNING *;
+            "#,
+        )
+        .bind(memo.id)
+        .bind(&memo.title)
+        .bind(&memo.content)
+        .bind(sqlite::now())
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    async fn delete_memo(&self, id: i32) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM memos WHERE id = $1;")
+            .bind(id)
+            .execute(&mut *self.db.acquire().await?)
+            .await?;
+        Ok(())
+    }
+}
+
+/// [`MemoRepository`] on a [`MemoryStore`].
+#[derive(Debug, Clone)]
+pub struct InMemoryMemoRepository {
+    pub store: MemoryStore,
+}
+
+impl InMemoryMemoRepository {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl MemoRepository for InMemoryMemoRepository {
+    async fn get_memos(&self) -> Result<Vec<MemoEntity>, AppError> {
+        Ok(self
+            .store
+            .read(|tables| tables.memos.values().cloned().collect()))
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<Option<MemoEntity>, AppError> {
+        Ok(self.store.read(|tables| tables.memos.get(&id).cloned()))
+    }
+
+    async fn create_memo(&self, memo: MemoEntity) -> Result<MemoEntity, AppError> {
+        let now = self.store.now();
+        let memo = MemoEntity {
+            id: self.store.next_id(Sequence::Memos),
+            created_at: now,
+            updated_at: now,
+            ..memo
+        };
+        self.store.write(|tables| {
+            tables.check_user(memo.user_id)?;
+            tables.memos.insert(memo.id, memo.clone());
+            Ok(memo)
+        })
+    }
+
+    async fn update_memo(&self, memo: MemoEntity) -> Result<MemoEntity, AppError> {
+        let now = self.store.now();
+        self.store.write(|tables| {
+            let stored = tables.memos.get_mut(&memo.id).ok_or(AppError::NotFound)?;
+            stored.title = memo.title;
+            stored.content = memo.content;
+            stored.updated_at = now;
+            Ok(stored.clone())
+        })
+    }
+
+    async fn delete_memo(&self, id: i32) -> Result<(), AppError> {
+        self.store.write(|tables| {
+            tables.memos.remove(&id);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::infra::testcontainer::PostgresContainer;
+
+    #[tokio::test]
+    async fn test_get_memos() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = MemoRepositoryImpl::new(container.pool());
+        // when
+        let memos = repository.get_memos().await.unwrap();
+        // then
+        assert_eq!(memos.len(), 2);
+        assert_eq!(memos[0].id, 1);
+        assert_eq!(memos[0].user_id, 1);
+        assert_eq!(memos[0].title, "Groceries");
+        assert_eq!(memos[0].content, "Milk, eggs and bread");
+        assert_eq!(
+            memos[0].created_at,
+            chrono::NaiveDateTime::parse_from_str("2025-02-12 00:00:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap()
+        );
+        assert_eq!(
+            memos[0].updated_at,
+            chrono::NaiveDateTime::parse_from_str("2025-02-12 12:00:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap()
+        );
+        assert_eq!(memos[1].id, 2);
+        assert_eq!(memos[1].user_id, 2);
+        assert_eq!(memos[1].title, "Meeting");
+    }
+
+    #[tokio::test]
+    async fn test_find_by_id() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = MemoRepositoryImpl::new(container.pool());
+        // when
+        let memo = repository.find_by_id(1).await.unwrap();
+        // then
+        assert!(memo.is_some());
+        let memo = memo.unwrap();
+        assert_eq!(memo.id, 1);
+        assert_eq!(memo.user_id, 1);
+        assert_eq!(memo.title, "Groceries");
+        assert_eq!(memo.content, "Milk, eggs and bread");
+    }
+
+    #[tokio::test]
+    async fn test_create_memo() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = MemoRepositoryImpl::new(container.pool());
+        let current_time = chrono::Utc::now().naive_utc();
+        // when
+        let memo = repository
+            .create_memo(MemoEntity {
+                id: 0,
+                user_id: 2,
+                title: "Todo".to_string(),
+                content: "Water the plants".to_string(),
+                created_at: current_time,
+                updated_at: current_time,
+            })
+            .await
+            .unwrap();
+        // then
+        assert_eq!(memo.id, 3);
+        assert_eq!(memo.user_id, 2);
+        assert_eq!(memo.title, "Todo");
+        assert_eq!(memo.content, "Water the plants");
+        assert!(memo.created_at > current_time);
+        assert!(memo.updated_at > current_time);
+    }
+
+    #[tokio::test]
+    async fn test_create_memo_for_unknown_user() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = MemoRepositoryImpl::new(container.pool());
+        let current_time = chrono::Utc::now().naive_utc();
+        // when
+        let result = repository
+            .create_memo(MemoEntity {
+                id: 0,
+                user_id: 99,
+                title: "Todo".to_string(),
+                content: "Water the plants".to_string(),
+                created_at: current_time,
+                updated_at: current_time,
+            })
+            .await;
+        // then
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_memo() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = MemoRepositoryImpl::new(container.pool());
+        let mut previous = repository.find_by_id(1).await.unwrap().unwrap();
+        previous.title = "Shopping".to_string();
+        previous.content = "Milk and cheese".to_string();
+        let previous_created_at = previous.created_at;
+        let previous_updated_at = previous.updated_at;
+
+        // when
+        let memo = repository.update_memo(previous).await.unwrap();
+        // then
+        assert_eq!(memo.id, 1);
+        assert_eq!(memo.user_id, 1);
+        assert_eq!(memo.title, "Shopping");
+        assert_eq!(memo.content, "Milk and cheese");
+        assert_eq!(memo.created_at, previous_created_at);
+        assert!(memo.updated_at > previous_updated_at);
+    }
+
+    #[tokio::test]
+    async fn test_delete_memo() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = MemoRepositoryImpl::new(container.pool());
+        // when
+        let _ = repository.delete_memo(1).await;
+        // then
+        let memo = repository.find_by_id(1).await.unwrap();
+        assert!(memo.is_none());
+    }
+}
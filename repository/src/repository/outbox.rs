@@ -0,0 +1,443 @@
+use crate::entity::outbox_event::OutboxEventEntity;
+use crate::infra::db::Db;
+use crate::infra::memory::{MemoryStore, Sequence};
+use crate::infra::sqlite;
+use chrono::NaiveDateTime;
+use shared::AppError;
+use sqlx::types::Json;
+use sqlx::Sqlite;
+use tracing::instrument;
+
+/// A row of `outbox`, whose payload sqlx decodes through [`Json`].
+#[derive(sqlx::FromRow)]
+struct OutboxEventRow {
+    id: i64,
+    event_type: String,
+    payload: Json<serde_json::Value>,
+    attempts: i32,
+    next_attempt_at: NaiveDateTime,
+    last_error: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+impl From<OutboxEventRow> for OutboxEventEntity {
+    fn from(row: OutboxEventRow) -> Self {
+        Self {
+            id: row.id,
+            event_type: row.event_type,
+            payload: row.payload.0,
+            attempts: row.attempts,
+            next_attempt_at: row.next_attempt_at,
+            last_error: row.last_error,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Domain events waiting to be delivered. They are appended in the unit of
+/// work of the change they describe and removed once delivered.
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait OutboxRepository: Send + Sync {
+    /// Appends `event`, due at once; its id and time are assigned here.
+    async fn append(&self, event: OutboxEventEntity) -> Result<OutboxEventEntity, AppError>;
+    /// Takes up to `limit` events that are due at `now`, oldest first, and
+    /// counts an attempt for each. Nobody else can claim them again before
+    /// `lease_until`, when they are due again unless completed.
+    async fn claim(
+        &self,
+        now: NaiveDateTime,
+        lease_until: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<OutboxEventEntity>, AppError>;
+    /// Removes event `id`, which has been delivered.
+    async fn complete(&self, id: i64) -> Result<(), AppError>;
+    /// Makes event `id`, which failed with `error`, due again at `retry_at`.
+    async fn retry(&self, id: i64, retry_at: NaiveDateTime, error: &str) -> Result<(), AppError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct OutboxRepositoryImpl {
+    pub db: Db,
+}
+
+impl OutboxRepositoryImpl {
+    pub fn new(db: impl Into<Db>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl OutboxRepository for OutboxRepositoryImpl {
+    #[instrument(skip(self, event), fields(event_type = %event.event_type))]
+    async fn append(&self, event: OutboxEventEntity) -> Result<OutboxEventEntity, AppError> {
+        let row = sqlx::query_as::<_, OutboxEventRow>(
+            "INSERT INTO outbox (event_type, payload) VALUES ($1, $2) RETURNING *;",
+        )
+        .bind(&event.event_type)
+        .bind(Json(event.payload))
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(row.into())
+    }
+
+    #[instrument(skip(self))]
+    async fn claim(
+        &self,
+        now: NaiveDateTime,
+        lease_until: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<OutboxEventEntity>, AppError> {
+        // other dispatchers skip the rows being claimed instead of waiting
+        let rows = sqlx::query_as::<_, OutboxEventRow>(
+            r#"
+            UPDATE outbox SET attempts = attempts + 1, next_attempt_at = $2
+            WHERE id IN (
+                SELECT id FROM outbox WHERE next_attempt_at <= $1
+                ORDER BY id LIMIT $3 FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *;
+            "#,
+        )
+        .bind(now)
+        .bind(lease_until)
+        .bind(limit)
+        .fetch_all(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(oldest_first(rows))
+    }
+
+    #[instrument(skip(self))]
+    async fn complete(&self, id: i64) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM outbox WHERE id = $1;")
+            .bind(id)
+            .execute(&mut *self.db.acquire().await?)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, error))]
+    async fn retry(&self, id: i64, retry_at: NaiveDateTime, error: &str) -> Result<(), AppError> {
+        sqlx::query("UPDATE outbox SET next_attempt_at = $2, last_error = $3 WHERE id = $1;")
+            .bind(id)
+            .bind(retry_at)
+            .bind(error)
+            .execute(&mut *self.db.acquire().await?)
+            .await?;
+        Ok(())
+    }
+}
+
+/// [`OutboxRepository`] on SQLite.
+#[derive(Debug, Clone)]
+pub struct SqliteOutboxRepository {
+    pub db: Db<Sqlite>,
+}
+
+impl SqliteOutboxRepository {
+    pub fn new(db: impl Into<Db<Sqlite>>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl OutboxRepository for SqliteOutboxRepository {
+    #[instrument(skip(self, event), fields(event_type = %event.event_type))]
+    async fn append(&self, event: OutboxEventEntity) -> Result<OutboxEventEntity, AppError> {
+        let now = sqlite::now();
+        let row = sqlx::query_as::<_, OutboxEventRow>(
+            r#"
+            INSERT INTO outbox (event_type, payload, next_attempt_at, created_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *;
+            "#,
+        )
+        .bind(&event.event_type)
+        .bind(Json(event.payload))
+        .bind(now)
+        .bind(now)
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(row.into())
+    }
+
+    #[instrument(skip(self))]
+    async fn claim(
+        &self,
+        now: NaiveDateTime,
+        lease_until: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<OutboxEventEntity>, AppError> {
+        // SQLite has a single writer, so nobody can claim the rows meanwhile
+        let rows = sqlx::query_as::<_, OutboxEventRow>(
+            r#"
+            UPDATE outbox SET attempts = attempts + 1, next_attempt_at = $2
+            WHERE id IN (
+                SELECT id FROM outbox WHERE next_attempt_at <= $1 ORDER BY id LIMIT $3
+            )
+            RETURNING *;
+            "#,
+        )
+        .bind(now)
+        .bind(lease_until)
+        .bind(limit)
+        .fetch_all(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(oldest_first(rows))
+    }
+
+    #[instrument(skip(self))]
+    async fn complete(&self, id: i64) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM outbox WHERE id = $1;")
+            .bind(id)
+            .execute(&mut *self.db.acquire().await?)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(skip(self, error))]
+    async fn retry(&self, id: i64, retry_at: NaiveDateTime, error: &str) -> Result<(), AppError> {
+        sqlx::query("UPDATE outbox SET next_attempt_at = $2, last_error = $3 WHERE id = $1;")
+            .bind(id)
+            .bind(retry_at)
+            .bind(error)
+            .execute(&mut *self.db.acquire().await?)
+            .await?;
+        Ok(())
+    }
+}
+
+/// `RETURNING` gives no order.
+fn oldest_first(rows: Vec<OutboxEventRow>) -> Vec<OutboxEventEntity> {
+    let mut events: Vec<OutboxEventEntity> = rows.into_iter().map(Into::into).collect();
+    events.sort_by_key(|event| event.id);
+    events
+}
+
+/// [`OutboxRepository`] on a [`MemoryStore`].
+#[derive(Debug, Clone)]
+pub struct InMemoryOutboxRepository {
+    pub store: MemoryStore,
+}
+
+impl InMemoryOutboxRepository {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl OutboxRepository for InMemoryOutboxRepository {
+    async fn append(&self, event: OutboxEventEntity) -> Result<OutboxEventEntity, AppError> {
+        let now = self.store.now();
+        let event = OutboxEventEntity {
+            id: self.store.next_id(Sequence::Outbox).into(),
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+            ..event
+        };
+        self.store.write(|tables| {
+            tables.outbox.insert(event.id, event.clone());
+            Ok(event)
+        })
+    }
+
+    async fn claim(
+        &self,
+        now: NaiveDateTime,
+        lease_until: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<OutboxEventEntity>, AppError> {
+        self.store.write(|tables| {
+            let ids: Vec<i64> = tables
+                .outbox
+                .values()
+                .filter(|event| event.next_attempt_at <= now)
+                .take(limit as usize)
+                .map(|event| event.id)
+                .collect();
+            let mut claimed = Vec::with_capacity(ids.len());
+            for id in ids {
+                let event = tables.outbox.get_mut(&id).expect("listed above");
+                event.attempts += 1;
+                event.next_attempt_at = lease_until;
+                claimed.push(event.clone());
+            }
+            Ok(claimed)
+        })
+    }
+
+    async fn complete(&self, id: i64) -> Result<(), AppError> {
+        self.store.write(|tables| {
+            tables.outbox.remove(&id);
+            Ok(())
+        })
+    }
+
+    async fn retry(&self, id: i64, retry_at: NaiveDateTime, error: &str) -> Result<(), AppError> {
+        self.store.write(|tables| {
+            if let Some(event) = tables.outbox.get_mut(&id) {
+                event.next_attempt_at = retry_at;
+                event.last_error = Some(error.to_string());
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::infra::testcontainer::PostgresContainer;
+    use crate::infra::testsqlite::SqliteDatabase;
+    use crate::repository::unit_of_work::{TransactionManager, TransactionManagerImpl};
+    use chrono::Duration;
+    use serde_json::json;
+
+    /// Runs every case against each backend.
+    macro_rules! conformance {
+        ($($case:ident),* $(,)?) => {
+            mod postgres {
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        let container = super::PostgresContainer::new().await;
+                        let repository = super::OutboxRepositoryImpl::new(container.pool());
+                        super::$case(&repository).await;
+                    }
+                )*
+            }
+
+            mod sqlite {
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        let database = super::SqliteDatabase::new().await;
+                        let repository = super::SqliteOutboxRepository::new(database.pool());
+                        super::$case(&repository).await;
+                    }
+                )*
+            }
+
+            mod memory {
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        let store = super::MemoryStore::seeded();
+                        super::$case(&super::InMemoryOutboxRepository::new(store)).await;
+                    }
+                )*
+            }
+        };
+    }
+
+    conformance!(
+        test_append_and_claim,
+        test_claimed_events_are_leased,
+        test_retry_and_complete
+    );
+
+    fn event(user_id: i32) -> OutboxEventEntity {
+        let now = chrono::Utc::now().naive_utc();
+        OutboxEventEntity {
+            id: 0,
+            event_type: "UserDeleted".to_string(),
+            payload: json!({ "type": "UserDeleted", "userId": user_id }),
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+        }
+    }
+
+    fn later(seconds: i64) -> NaiveDateTime {
+        chrono::Utc::now().naive_utc() + Duration::seconds(seconds)
+    }
+
+    async fn test_append_and_claim(repository: &dyn OutboxRepository) {
+        // given
+        let first = repository.append(event(1)).await.unwrap();
+        let second = repository.append(event(2)).await.unwrap();
+        // when
+        let claimed = repository.claim(later(1), later(60), 10).await.unwrap();
+        // then
+        assert!(second.id > first.id);
+        assert_eq!(first.attempts, 0);
+        assert_eq!(first.payload, json!({ "type": "UserDeleted", "userId": 1 }));
+        let ids: Vec<i64> = claimed.iter().map(|event| event.id).collect();
+        assert_eq!(ids, vec![first.id, second.id]);
+        assert!(claimed.iter().all(|event| event.attempts == 1));
+        assert_eq!(claimed[0].payload, first.payload);
+    }
+
+    async fn test_claimed_events_are_leased(repository: &dyn OutboxRepository) {
+        // given
+        for user_id in 1..=3 {
+            repository.append(event(user_id)).await.unwrap();
+        }
+        // when
+        let first = repository.claim(later(1), later(60), 2).await.unwrap();
+        let second = repository.claim(later(1), later(60), 2).await.unwrap();
+        let after_lease = repository.claim(later(61), later(120), 10).await.unwrap();
+        // then
+        assert_eq!(first.len(), 2);
+        assert_eq!(second.len(), 1);
+        assert!(second[0].id > first[1].id);
+        assert_eq!(after_lease.len(), 3);
+        assert_eq!(after_lease[0].attempts, 2);
+    }
+
+    async fn test_retry_and_complete(repository: &dyn OutboxRepository) {
+        // given
+        let failed = repository.append(event(1)).await.unwrap();
+        let delivered = repository.append(event(2)).await.unwrap();
+        repository.claim(later(1), later(60), 10).await.unwrap();
+        // when
+        repository
+            .retry(failed.id, later(5), "webhook answered 503")
+            .await
+            .unwrap();
+        repository.complete(delivered.id).await.unwrap();
+        // then
+        assert!(repository
+            .claim(later(1), later(60), 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let retried = repository.claim(later(6), later(60), 10).await.unwrap();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].id, failed.id);
+        assert_eq!(retried[0].attempts, 2);
+        assert_eq!(
+            retried[0].last_error.as_deref(),
+            Some("webhook answered 503")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_events_are_only_kept_with_their_change() {
+        // given
+        let container = PostgresContainer::new().await;
+        let transactions = TransactionManagerImpl::new(container.pool());
+        let repository = OutboxRepositoryImpl::new(container.pool());
+        // when
+        let discarded = transactions.begin().await.unwrap();
+        discarded.outbox().append(event(1)).await.unwrap();
+        discarded.rollback().await.unwrap();
+        let kept = transactions.begin().await.unwrap();
+        kept.outbox().append(event(2)).await.unwrap();
+        // then nothing is claimable before the commit
+        assert!(repository
+            .claim(later(1), later(60), 10)
+            .await
+            .unwrap()
+            .is_empty());
+        kept.commit().await.unwrap();
+        let claimed = repository.claim(later(1), later(60), 10).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].payload["userId"], 2);
+    }
+}
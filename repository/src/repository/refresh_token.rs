@@ -0,0 +1,304 @@
+use crate::entity::refresh_token::RefreshTokenEntity;
+use crate::infra::db::Db;
+use crate::infra::memory::{MemoryStore, Sequence};
+use crate::infra::sqlite;
+use shared::AppError;
+use sqlx::Sqlite;
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait RefreshTokenRepository: Send + Sync {
+    async fn create(&self, token: RefreshTokenEntity) -> Result<RefreshTokenEntity, AppError>;
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<RefreshTokenEntity>, AppError>;
+    /// Revokes the token unless it already was; returns whether this call revoked it.
+    async fn revoke(&self, id: i32) -> Result<bool, AppError>;
+    async fn revoke_all_for_user(&self, user_id: i32) -> Result<(), AppError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct RefreshTokenRepositoryImpl {
+    pub db: Db,
+}
+
+impl RefreshTokenRepositoryImpl {
+    pub fn new(db: impl Into<Db>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenRepository for RefreshTokenRepositoryImpl {
+    async fn create(&self, token: RefreshTokenEntity) -> Result<RefreshTokenEntity, AppError> {
+        let entity = sqlx::query_as::<_, RefreshTokenEntity>(
+            r#"
+            INSERT INTO refresh_tokens (user_id, token_hash, expires_at)
+            VALUES ($1, $2, $3)
+            RETURNING *;
+            "#,
+        )
+        .bind(token.user_id)
+        .bind(&token.token_hash)
+        .bind(token.expires_at)
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<RefreshTokenEntity>, AppError> {
+        let entity = sqlx::query_as::<_, RefreshTokenEntity>(
+            "SELECT * FROM refresh_tokens WHERE token_hash = $1;",
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    async fn revoke(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND revoked_at IS NULL;
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn revoke_all_for_user(&self, user_id: i32) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND revoked_at IS NULL;
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(())
+    }
+}
+
+/// [`RefreshTokenRepository`] on SQLite.
+#[derive(Debug, Clone)]
+pub struct SqliteRefreshTokenRepository {
+    pub db: Db<Sqlite>,
+}
+
+impl SqliteRefreshTokenRepository {
+    pub fn new(db: impl Into<Db<Sqlite>>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenRepository for SqliteRefreshTokenRepository {
+    async fn create(&self, token: RefreshTokenEntity) -> Result<RefreshTokenEntity, AppError> {
+        let entity = sqlx::query_as::<_, RefreshTokenEntity>(
+            r#"
+            INSERT INTO refresh_tokens (user_id, token_hash, expires_at, created_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *;
+            "#,
+        )
+        .bind(token.user_id)
+        .bind(&token.token_hash)
+        .bind(token.expires_at)
+        .bind(sqlite::now())
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<RefreshTokenEntity>, AppError> {
+        let entity = sqlx::query_as::<_, RefreshTokenEntity>(
+            "SELECT * FROM refresh_tokens WHERE token_hash = $1;",
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    async fn revoke(&self, id: i32) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = $2
+            WHERE id = $1 AND revoked_at IS NULL;
+            "#,
+        )
+        .bind(id)
+        .bind(sqlite::now())
+        .execute(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn revoke_all_for_user(&self, user_id: i32) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = $2
+            WHERE user_id = $1 AND revoked_at IS NULL;
+            "#,
+        )
+        .bind(user_id)
+        .bind(sqlite::now())
+        .execute(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(())
+    }
+}
+
+/// [`RefreshTokenRepository`] on a [`MemoryStore`].
+#[derive(Debug, Clone)]
+pub struct InMemoryRefreshTokenRepository {
+    pub store: MemoryStore,
+}
+
+impl InMemoryRefreshTokenRepository {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenRepository for InMemoryRefreshTokenRepository {
+    async fn create(&self, token: RefreshTokenEntity) -> Result<RefreshTokenEntity, AppError> {
+        let token = RefreshTokenEntity {
+            id: self.store.next_id(Sequence::RefreshTokens),
+            revoked_at: None,
+            created_at: self.store.now(),
+            ..token
+        };
+        self.store.write(|tables| {
+            tables.check_user(token.user_id)?;
+            if tables
+                .refresh_tokens
+                .values()
+                .any(|stored| stored.token_hash == token.token_hash)
+            {
+                return Err(AppError::Conflict);
+            }
+            tables.refresh_tokens.insert(token.id, token.clone());
+            Ok(token)
+        })
+    }
+
+    async fn find_by_hash(&self, token_hash: &str) -> Result<Option<RefreshTokenEntity>, AppError> {
+        Ok(self.store.read(|tables| {
+            tables
+                .refresh_tokens
+                .values()
+                .find(|token| token.token_hash == token_hash)
+                .cloned()
+        }))
+    }
+
+    async fn revoke(&self, id: i32) -> Result<bool, AppError> {
+        let now = self.store.now();
+        self.store
+            .write(|tables| match tables.refresh_tokens.get_mut(&id) {
+                Some(token) if token.revoked_at.is_none() => {
+                    token.revoked_at = Some(now);
+                    Ok(true)
+                }
+                _ => Ok(false),
+            })
+    }
+
+    async fn revoke_all_for_user(&self, user_id: i32) -> Result<(), AppError> {
+        let now = self.store.now();
+        self.store.write(|tables| {
+            tables
+                .refresh_tokens
+                .values_mut()
+                .filter(|token| token.user_id == user_id && token.revoked_at.is_none())
+                .for_each(|token| token.revoked_at = Some(now));
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::infra::testcontainer::PostgresContainer;
+
+    fn token(user_id: i32, token_hash: &str) -> RefreshTokenEntity {
+        let now = chrono::Utc::now().naive_utc();
+        RefreshTokenEntity {
+            id: 0,
+            user_id,
+            token_hash: token_hash.to_string(),
+            expires_at: now + chrono::Duration::days(14),
+            revoked_at: None,
+            created_at: now,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_and_find_by_hash() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = RefreshTokenRepositoryImpl::new(container.pool());
+        // when
+        let created = repository.create(token(1, "hash-1")).await.unwrap();
+        // then
+        let found = repository.find_by_hash("hash-1").await.unwrap().unwrap();
+        assert_eq!(found.id, created.id);
+        assert_eq!(found.user_id, 1);
+        assert!(found.revoked_at.is_none());
+        assert!(repository.find_by_hash("unknown").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_revoke() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = RefreshTokenRepositoryImpl::new(container.pool());
+        let created = repository.create(token(1, "hash-1")).await.unwrap();
+        // when
+        let first = repository.revoke(created.id).await.unwrap();
+        let second = repository.revoke(created.id).await.unwrap();
+        // then
+        assert!(first);
+        assert!(!second);
+        let found = repository.find_by_hash("hash-1").await.unwrap().unwrap();
+        assert!(found.revoked_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_for_user() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = RefreshTokenRepositoryImpl::new(container.pool());
+        repository.create(token(1, "hash-1")).await.unwrap();
+        repository.create(token(1, "hash-2")).await.unwrap();
+        repository.create(token(2, "hash-3")).await.unwrap();
+        // when
+        repository.revoke_all_for_user(1).await.unwrap();
+        // then
+        let revoked = |hash: &'static str| {
+            let repository = repository.clone();
+            async move {
+                repository
+                    .find_by_hash(hash)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .revoked_at
+                    .is_some()
+            }
+        };
+        assert!(revoked("hash-1").await);
+        assert!(revoked("hash-2").await);
+        assert!(!revoked("hash-3").await);
+    }
+}
@@ -0,0 +1,368 @@
+use crate::infra::db::Db;
+use crate::infra::memory::{MemoryStore, ROLES};
+use shared::AppError;
+use sqlx::Sqlite;
+use std::collections::BTreeSet;
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait RoleRepository: Send + Sync {
+    async fn find_roles_for_user(&self, user_id: i32) -> Result<Vec<String>, AppError>;
+    /// Returns the union of the permissions of every role the user holds.
+    async fn find_permissions_for_user(&self, user_id: i32) -> Result<Vec<String>, AppError>;
+    /// Grants the role; returns false if the user or the role does not exist.
+    async fn grant(&self, user_id: i32, role: &str) -> Result<bool, AppError>;
+    /// Revokes the role; returns false if the user did not hold it.
+    async fn revoke(&self, user_id: i32, role: &str) -> Result<bool, AppError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct RoleRepositoryImpl {
+    pub db: Db,
+}
+
+impl RoleRepositoryImpl {
+    pub fn new(db: impl Into<Db>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleRepository for RoleRepositoryImpl {
+    async fn find_roles_for_user(&self, user_id: i32) -> Result<Vec<String>, AppError> {
+        let roles = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT roles.name
+            FROM user_roles
+            JOIN roles ON roles.id = user_roles.role_id
+            WHERE user_roles.user_id = $1
+            ORDER BY roles.name;
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(roles)
+    }
+
+    async fn find_permissions_for_user(&self, user_id: i32) -> Result<Vec<String>, AppError> {
+        let permissions = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT DISTINCT permissions.name
+            FROM user_roles
+            JOIN role_permissions ON role_permissions.role_id = user_roles.role_id
+            JOIN permissions ON permissions.id = role_permissions.permission_id
+            WHERE user_roles.user_id = $1
+            ORDER BY permissions.name;
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(permissions)
+    }
+
+    async fn grant(&self, user_id: i32, role: &str) -> Result<bool, AppError> {
+        // the no-op update makes an existing grant return its row as well
+        let granted = sqlx::query_scalar::<_, i32>(
+            r#"
+            INSERT INTO user_roles (user_id, role_id)
+            SELECT users.id, roles.id
+            FROM users, roles
+            WHERE users.id = $1 AND roles.name = $2
+            ON CONFLICT (user_id, role_id) DO UPDATE SET role_id = EXCLUDED.role_id
+            RETURNING user_id;
+            "#,
+        )
+        .bind(user_id)
+        .bind(role)
+        .fetch_optional(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(granted.is_some())
+    }
+
+    async fn revoke(&self, user_id: i32, role: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM user_roles
+            USING roles
+            WHERE user_roles.role_id = roles.id
+              AND user_roles.user_id = $1
+              AND roles.name = $2;
+            "#,
+        )
+        .bind(user_id)
+        .bind(role)
+        .execute(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// [`RoleRepository`] on SQLite.
+#[derive(Debug, Clone)]
+pub struct SqliteRoleRepository {
+    pub db: Db<Sqlite>,
+}
+
+impl SqliteRoleRepository {
+    pub fn new(db: impl Into<Db<Sqlite>>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleRepository for SqliteRoleRepository {
+    async fn find_roles_for_user(&self, user_id: i32) -> Result<Vec<String>, AppError> {
+        let roles = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT roles.name
+            FROM user_roles
+            JOIN roles ON roles.id = user_roles.role_id
+            WHERE user_roles.user_id = $1
+            ORDER BY roles.name;
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(roles)
+    }
+
+    async fn find_permissions_for_user(&self, user_id: i32) -> Result<Vec<String>, AppError> {
+        let permissions = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT DISTINCT permissions.name
+            FROM user_roles
+            JOIN role_permissions ON role_permissions.role_id = user_roles.role_id
+            JOIN permissions ON permissions.id = role_permissions.permission_id
+            WHERE user_roles.user_id = $1
+            ORDER BY permissions.name;
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(permissions)
+    }
+
+    async fn grant(&self, user_id: i32, role: &str) -> Result<bool, AppError> {
+        // the no-op update makes an existing grant return its row as well
+        let granted = sqlx::query_scalar::<_, i32>(
+            r#"
+            INSERT INTO user_roles (user_id, role_id)
+            SELECT users.id, roles.id
+            FROM users, roles
+            WHERE users.id = $1 AND roles.name = $2
+            ON CONFLICT (user_id, role_id) DO UPDATE SET role_id = excluded.role_id
+            RETURNING user_id;
+            "#,
+        )
+        .bind(user_id)
+        .bind(role)
+        .fetch_optional(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(granted.is_some())
+    }
+
+    async fn revoke(&self, user_id: i32, role: &str) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM user_roles
+            WHERE user_id = $1
+              AND role_id IN (SELECT id FROM roles WHERE name = $2);
+            "#,
+        )
+        .bind(user_id)
+        .bind(role)
+        .execute(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// [`RoleRepository`] on a [`MemoryStore`], with the roles of [`ROLES`].
+#[derive(Debug, Clone)]
+pub struct InMemoryRoleRepository {
+    pub store: MemoryStore,
+}
+
+impl InMemoryRoleRepository {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleRepository for InMemoryRoleRepository {
+    async fn find_roles_for_user(&self, user_id: i32) -> Result<Vec<String>, AppError> {
+        Ok(self.store.read(|tables| {
+            tables
+                .user_roles
+                .iter()
+                .filter(|(holder, _)| *holder == user_id)
+                .map(|(_, role)| role.clone())
+                .collect()
+        }))
+    }
+
+    async fn find_permissions_for_user(&self, user_id: i32) -> Result<Vec<String>, AppError> {
+        let roles = self.find_roles_for_user(user_id).await?;
+        let permissions: BTreeSet<String> = ROLES
+            .iter()
+            .filter(|(role, _)| roles.iter().any(|held| held == role))
+            .flat_map(|(_, permissions)| permissions.iter().map(|p| p.to_string()))
+            .collect();
+        Ok(permissions.into_iter().collect())
+    }
+
+    async fn grant(&self, user_id: i32, role: &str) -> Result<bool, AppError> {
+        self.store.write(|tables| {
+            let known = ROLES.iter().any(|(name, _)| *name == role);
+            if !known || !tables.users.contains_key(&user_id) {
+                return Ok(false);
+            }
+            tables.user_roles.insert((user_id, role.to_string()));
+            Ok(true)
+        })
+    }
+
+    async fn revoke(&self, user_id: i32, role: &str) -> Result<bool, AppError> {
+        self.store
+            .write(|tables| Ok(tables.user_roles.remove(&(user_id, role.to_string()))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::infra::testcontainer::PostgresContainer;
+    use crate::infra::testsqlite::SqliteDatabase;
+
+    #[tokio::test]
+    async fn test_find_roles_and_permissions_for_user() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = RoleRepositoryImpl::new(container.pool());
+        // when
+        let roles = repository.find_roles_for_user(2).await.unwrap();
+        let permissions = repository.find_permissions_for_user(2).await.unwrap();
+        // then
+        assert_eq!(roles, vec!["member"]);
+        assert_eq!(
+            permissions,
+            vec!["memos:delete", "memos:write", "users:delete", "users:write"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_grant() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = RoleRepositoryImpl::new(container.pool());
+        // when
+        let granted = repository.grant(2, "admin").await.unwrap();
+        let granted_again = repository.grant(2, "admin").await.unwrap();
+        // then
+        assert!(granted);
+        assert!(granted_again);
+        let roles = repository.find_roles_for_user(2).await.unwrap();
+        assert_eq!(roles, vec!["admin", "member"]);
+        let permissions = repository.find_permissions_for_user(2).await.unwrap();
+        assert!(permissions.contains(&"roles:manage".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_grant_unknown_user_or_role() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = RoleRepositoryImpl::new(container.pool());
+        // when
+        let unknown_user = repository.grant(99, "admin").await.unwrap();
+        let unknown_role = repository.grant(2, "owner").await.unwrap();
+        // then
+        assert!(!unknown_user);
+        assert!(!unknown_role);
+    }
+
+    #[tokio::test]
+    async fn test_revoke() {
+        // given
+        let container = PostgresContainer::new().await;
+        let repository = RoleRepositoryImpl::new(container.pool());
+        // when
+        let revoked = repository.revoke(2, "member").await.unwrap();
+        let revoked_again = repository.revoke(2, "member").await.unwrap();
+        // then
+        assert!(revoked);
+        assert!(!revoked_again);
+        let roles = repository.find_roles_for_user(2).await.unwrap();
+        assert!(roles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_grant_and_revoke() {
+        // given
+        let database = SqliteDatabase::new().await;
+        let repository = SqliteRoleRepository::new(database.pool());
+        // when
+        let granted = repository.grant(2, "admin").await.unwrap();
+        let granted_again = repository.grant(2, "admin").await.unwrap();
+        let unknown_role = repository.grant(2, "owner").await.unwrap();
+        // then
+        assert!(granted);
+        assert!(granted_again);
+        assert!(!unknown_role);
+        let roles = repository.find_roles_for_user(2).await.unwrap();
+        assert_eq!(roles, vec!["admin", "member"]);
+        let permissions = repository.find_permissions_for_user(2).await.unwrap();
+        assert!(permissions.contains(&"roles:manage".to_string()));
+        // when
+        let revoked = repository.revoke(2, "admin").await.unwrap();
+        let revoked_again = repository.revoke(2, "admin").await.unwrap();
+        // then
+        assert!(revoked);
+        assert!(!revoked_again);
+        assert_eq!(
+            repository.find_roles_for_user(2).await.unwrap(),
+            vec!["member"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_grant_and_revoke() {
+        // given
+        let repository = InMemoryRoleRepository::new(MemoryStore::seeded());
+        // when
+        let granted = repository.grant(2, "admin").await.unwrap();
+        let unknown_user = repository.grant(99, "admin").await.unwrap();
+        let unknown_role = repository.grant(2, "owner").await.unwrap();
+        // then
+        assert!(granted);
+        assert!(!unknown_user);
+        assert!(!unknown_role);
+        let roles = repository.find_roles_for_user(2).await.unwrap();
+        assert_eq!(roles, vec!["admin", "member"]);
+        let permissions = repository.find_permissions_for_user(2).await.unwrap();
+        assert_eq!(
+            permissions,
+            vec![
+                "audit:read",
+                "memos:delete",
+                "memos:write",
+                "roles:manage",
+                "users:delete",
+                "users:write"
+            ]
+        );
+        // when
+        let revoked = repository.revoke(2, "admin").await.unwrap();
+        let revoked_again = repository.revoke(2, "admin").await.unwrap();
+        // then
+        assert!(revoked);
+        assert!(!revoked_again);
+        let permissions = repository.find_permissions_for_user(2).await.unwrap();
+        assert!(!permissions.contains(&"roles:manage".to_string()));
+    }
+}
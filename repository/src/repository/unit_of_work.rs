@@ -0,0 +1,640 @@
+use crate::entity::user::UserEntity;
+use crate::infra::cache::Cache;
+use crate::infra::db::{self, Db, SharedTransaction};
+use crate::infra::memory::MemoryStore;
+use crate::infra::replica::ReplicaSet;
+use crate::repository::audit::{
+    AuditRepository, AuditRepositoryImpl, InMemoryAuditRepository, SqliteAuditRepository,
+};
+use crate::repository::credential::{
+    CredentialRepository, CredentialRepositoryImpl, InMemoryCredentialRepository,
+    SqliteCredentialRepository,
+};
+use crate::repository::memo::{
+    InMemoryMemoRepository, MemoRepository, MemoRepositoryImpl, SqliteMemoRepository,
+};
+use crate::repository::outbox::{
+    InMemoryOutboxRepository, OutboxRepository, OutboxRepositoryImpl, SqliteOutboxRepository,
+};
+use crate::repository::refresh_token::{
+    InMemoryRefreshTokenRepository, RefreshTokenRepository, RefreshTokenRepositoryImpl,
+    SqliteRefreshTokenRepository,
+};
+use crate::repository::role::{
+    InMemoryRoleRepository, RoleRepository, RoleRepositoryImpl, SqliteRoleRepository,
+};
+use crate::repository::user::{
+    cache_key, InMemoryUserRepository, SqliteUserRepository, UserQuery, UserRepository,
+    UserRepositoryImpl,
+};
+use shared::query::Page;
+use shared::AppError;
+use sqlx::{Database, PgPool, Sqlite, SqlitePool};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::Mutex;
+
+/// Repositories that all run on one transaction. Nothing they write is
+/// visible to others until [`UnitOfWork::commit`].
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait UnitOfWork: Send + Sync {
+    fn users(&self) -> Arc<dyn UserRepository>;
+    fn memos(&self) -> Arc<dyn MemoRepository>;
+    fn credentials(&self) -> Arc<dyn CredentialRepository>;
+    fn refresh_tokens(&self) -> Arc<dyn RefreshTokenRepository>;
+    fn roles(&self) -> Arc<dyn RoleRepository>;
+    fn audit(&self) -> Arc<dyn AuditRepository>;
+    fn outbox(&self) -> Arc<dyn OutboxRepository>;
+    async fn commit(&self) -> Result<(), AppError>;
+    /// Discards every write; also happens when the unit of work is dropped
+    /// without being committed.
+    async fn rollback(&self) -> Result<(), AppError>;
+}
+
+/// Starts units of work.
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait TransactionManager: Send + Sync {
+    async fn begin(&self) -> Result<Arc<dyn UnitOfWork>, AppError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct TransactionManagerImpl {
+    pub db: Arc<PgPool>,
+    /// Told about every commit, so that the caller reads its writes.
+    pub replicas: Option<Arc<ReplicaSet>>,
+}
+
+impl TransactionManagerImpl {
+    pub fn new(db: Arc<PgPool>) -> Self {
+        Self { db, replicas: None }
+    }
+
+    pub fn with_replicas(mut self, replicas: Option<Arc<ReplicaSet>>) -> Self {
+        self.replicas = replicas;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl TransactionManager for TransactionManagerImpl {
+    async fn begin(&self) -> Result<Arc<dyn UnitOfWork>, AppError> {
+        let transaction = db::begin(&self.db).await?;
+        Ok(Arc::new(UnitOfWorkImpl {
+            transaction: Arc::new(Mutex::new(Some(transaction))),
+            replicas: self.replicas.clone(),
+        }))
+    }
+}
+
+pub struct UnitOfWorkImpl {
+    transaction: SharedTransaction,
+    replicas: Option<Arc<ReplicaSet>>,
+}
+
+impl UnitOfWorkImpl {
+    fn db(&self) -> Db {
+        Db::Transaction(self.transaction.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl UnitOfWork for UnitOfWorkImpl {
+    fn users(&self) -> Arc<dyn UserRepository> {
+        Arc::new(UserRepositoryImpl::new(self.db()))
+    }
+
+    fn memos(&self) -> Arc<dyn MemoRepository> {
+        Arc::new(MemoRepositoryImpl::new(self.db()))
+    }
+
+    fn credentials(&self) -> Arc<dyn CredentialRepository> {
+        Arc::new(CredentialRepositoryImpl::new(self.db()))
+    }
+
+    fn refresh_tokens(&self) -> Arc<dyn RefreshTokenRepository> {
+        Arc::new(RefreshTokenRepositoryImpl::new(self.db()))
+    }
+
+    fn roles(&self) -> Arc<dyn RoleRepository> {
+        Arc::new(RoleRepositoryImpl::new(self.db()))
+    }
+
+    fn audit(&self) -> Arc<dyn AuditRepository> {
+        Arc::new(AuditRepositoryImpl::new(self.db()))
+    }
+
+    fn outbox(&self) -> Arc<dyn OutboxRepository> {
+        Arc::new(OutboxRepositoryImpl::new(self.db()))
+    }
+
+    async fn commit(&self) -> Result<(), AppError> {
+        finish(&self.transaction, true).await?;
+        if let Some(replicas) = &self.replicas {
+            replicas.record_write();
+        }
+        Ok(())
+    }
+
+    async fn rollback(&self) -> Result<(), AppError> {
+        finish(&self.transaction, false).await
+    }
+}
+
+/// Starts units of work on SQLite.
+#[derive(Debug, Clone)]
+pub struct SqliteTransactionManager {
+    pub db: Arc<SqlitePool>,
+}
+
+impl SqliteTransactionManager {
+    pub fn new(db: Arc<SqlitePool>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait::async_trait]
+impl TransactionManager for SqliteTransactionManager {
+    async fn begin(&self) -> Result<Arc<dyn UnitOfWork>, AppError> {
+        let transaction = db::begin(&self.db).await?;
+        Ok(Arc::new(SqliteUnitOfWork {
+            transaction: Arc::new(Mutex::new(Some(transaction))),
+        }))
+    }
+}
+
+pub struct SqliteUnitOfWork {
+    transaction: SharedTransaction<Sqlite>,
+}
+
+impl SqliteUnitOfWork {
+    fn db(&self) -> Db<Sqlite> {
+        Db::Transaction(self.transaction.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl UnitOfWork for SqliteUnitOfWork {
+    fn users(&self) -> Arc<dyn UserRepository> {
+        Arc::new(SqliteUserRepository::new(self.db()))
+    }
+
+    fn memos(&self) -> Arc<dyn MemoRepository> {
+        Arc::new(SqliteMemoRepository::new(self.db()))
+    }
+
+    fn credentials(&self) -> Arc<dyn CredentialRepository> {
+        Arc::new(SqliteCredentialRepository::new(self.db()))
+    }
+
+    fn refresh_tokens(&self) -> Arc<dyn RefreshTokenRepository> {
+        Arc::new(SqliteRefreshTokenRepository::new(self.db()))
+    }
+
+    fn roles(&self) -> Arc<dyn RoleRepository> {
+        Arc::new(SqliteRoleRepository::new(self.db()))
+    }
+
+    fn audit(&self) -> Arc<dyn AuditRepository> {
+        Arc::new(SqliteAuditRepository::new(self.db()))
+    }
+
+    fn outbox(&self) -> Arc<dyn OutboxRepository> {
+        Arc::new(SqliteOutboxRepository::new(self.db()))
+    }
+
+    async fn commit(&self) -> Result<(), AppError> {
+        finish(&self.transaction, true).await
+    }
+
+    async fn rollback(&self) -> Result<(), AppError> {
+        finish(&self.transaction, false).await
+    }
+}
+
+async fn finish<DB: Database>(
+    transaction: &SharedTransaction<DB>,
+    commit: bool,
+) -> Result<(), AppError> {
+    let transaction = transaction.lock().await.take();
+    match transaction {
+        Some(transaction) if commit => Ok(transaction.commit().await?),
+        Some(transaction) => Ok(transaction.rollback().await?),
+        None => {
+            tracing::error!("unit of work finished twice");
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+/// Starts units of work on a [`MemoryStore`].
+#[derive(Debug, Clone)]
+pub struct InMemoryTransactionManager {
+    pub store: MemoryStore,
+}
+
+impl InMemoryTransactionManager {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait::async_trait]
+impl TransactionManager for InMemoryTransactionManager {
+    async fn begin(&self) -> Result<Arc<dyn UnitOfWork>, AppError> {
+        Ok(Arc::new(InMemoryUnitOfWork {
+            store: self.store.clone(),
+            snapshot: self.store.snapshot(),
+            finished: AtomicBool::new(false),
+        }))
+    }
+}
+
+/// Works on a copy of the tables whose changed rows are applied on commit.
+/// The commit fails with `AppError::Conflict` if one of those rows was
+/// written meanwhile.
+pub struct InMemoryUnitOfWork {
+    store: MemoryStore,
+    snapshot: MemoryStore,
+    finished: AtomicBool,
+}
+
+impl InMemoryUnitOfWork {
+    fn finish(&self) -> Result<(), AppError> {
+        if self.finished.swap(true, Ordering::SeqCst) {
+            tracing::error!("unit of work finished twice");
+            return Err(AppError::InternalServerError);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl UnitOfWork for InMemoryUnitOfWork {
+    fn users(&self) -> Arc<dyn UserRepository> {
+        Arc::new(InMemoryUserRepository::new(self.snapshot.clone()))
+    }
+
+    fn memos(&self) -> Arc<dyn MemoRepository> {
+        Arc::new(InMemoryMemoRepository::new(self.snapshot.clone()))
+    }
+
+    fn credentials(&self) -> Arc<dyn CredentialRepository> {
+        Arc::new(InMemoryCredentialRepository::new(self.snapshot.clone()))
+    }
+
+    fn refresh_tokens(&self) -> Arc<dyn RefreshTokenRepository> {
+        Arc::new(InMemoryRefreshTokenRepository::new(self.snapshot.clone()))
+    }
+
+    fn roles(&self) -> Arc<dyn RoleRepository> {
+        Arc::new(InMemoryRoleRepository::new(self.snapshot.clone()))
+    }
+
+    fn audit(&self) -> Arc<dyn AuditRepository> {
+        Arc::new(InMemoryAuditRepository::new(self.snapshot.clone()))
+    }
+
+    fn outbox(&self) -> Arc<dyn OutboxRepository> {
+        Arc::new(InMemoryOutboxRepository::new(self.snapshot.clone()))
+    }
+
+    async fn commit(&self) -> Result<(), AppError> {
+        self.finish()?;
+        self.store.commit(&self.snapshot)
+    }
+
+    async fn rollback(&self) -> Result<(), AppError> {
+        self.finish()
+    }
+}
+
+/// Starts units of work that drop the users they wrote from `cache` once
+/// they commit: the counterpart of [`CachedUserRepository`] for writes made
+/// in a unit of work. Reads within a unit of work bypass the cache.
+///
+/// [`CachedUserRepository`]: crate::repository::user::CachedUserRepository
+#[derive(Clone)]
+pub struct CachedTransactionManager {
+    inner: Arc<dyn TransactionManager>,
+    cache: Arc<dyn Cache>,
+}
+
+impl CachedTransactionManager {
+    pub fn new(inner: Arc<dyn TransactionManager>, cache: Arc<dyn Cache>) -> Self {
+        Self { inner, cache }
+    }
+}
+
+#[async_trait::async_trait]
+impl TransactionManager for CachedTransactionManager {
+    async fn begin(&self) -> Result<Arc<dyn UnitOfWork>, AppError> {
+        Ok(Arc::new(CachedUnitOfWork {
+            inner: self.inner.begin().await?,
+            cache: self.cache.clone(),
+            written: Arc::new(StdMutex::new(BTreeSet::new())),
+        }))
+    }
+}
+
+struct CachedUnitOfWork {
+    inner: Arc<dyn UnitOfWork>,
+    cache: Arc<dyn Cache>,
+    /// Ids of the users written so far.
+    written: Arc<StdMutex<BTreeSet<i32>>>,
+}
+
+#[async_trait::async_trait]
+impl UnitOfWork for CachedUnitOfWork {
+    fn users(&self) -> Arc<dyn UserRepository> {
+        Arc::new(WrittenUsers {
+            inner: self.inner.users(),
+            written: self.written.clone(),
+        })
+    }
+
+    fn memos(&self) -> Arc<dyn MemoRepository> {
+        self.inner.memos()
+    }
+
+    fn credentials(&self) -> Arc<dyn CredentialRepository> {
+        self.inner.credentials()
+    }
+
+    fn refresh_tokens(&self) -> Arc<dyn RefreshTokenRepository> {
+        self.inner.refresh_tokens()
+    }
+
+    fn roles(&self) -> Arc<dyn RoleRepository> {
+        self.inner.roles()
+    }
+
+    fn audit(&self) -> Arc<dyn AuditRepository> {
+        self.inner.audit()
+    }
+
+    fn outbox(&self) -> Arc<dyn OutboxRepository> {
+        self.inner.outbox()
+    }
+
+    async fn commit(&self) -> Result<(), AppError> {
+        self.inner.commit().await?;
+        let written = std::mem::take(&mut *self.written.lock().expect("unit of work poisoned"));
+        for id in written {
+            self.cache.invalidate(&cache_key(id)).await;
+        }
+        Ok(())
+    }
+
+    async fn rollback(&self) -> Result<(), AppError> {
+        self.inner.rollback().await
+    }
+}
+
+/// Notes the users a unit of work writes.
+struct WrittenUsers {
+    inner: Arc<dyn UserRepository>,
+    written: Arc<StdMutex<BTreeSet<i32>>>,
+}
+
+impl WrittenUsers {
+    fn wrote(&self, id: i32) {
+        self.written
+            .lock()
+            .expect("unit of work poisoned")
+            .insert(id);
+    }
+}
+
+#[async_trait::async_trait]
+impl UserRepository for WrittenUsers {
+    async fn get_users(&self, query: &UserQuery) -> Result<Page<UserEntity>, AppError> {
+        self.inner.get_users(query).await
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<Option<UserEntity>, AppError> {
+        self.inner.find_by_id(id).await
+    }
+
+    async fn create_user(&self, user: UserEntity) -> Result<UserEntity, AppError> {
+        self.inner.create_user(user).await
+    }
+
+    async fn update_user(
+        &self,
+        user: UserEntity,
+        expected_version: Option<i32>,
+    ) -> Result<UserEntity, AppError> {
+        self.wrote(user.id);
+        self.inner.update_user(user, expected_version).await
+    }
+
+    async fn delete_user(&self, id: i32, expected_version: Option<i32>) -> Result<(), AppError> {
+        self.wrote(id);
+        self.inner.delete_user(id, expected_version).await
+    }
+
+    async fn restore_user(&self, id: i32) -> Result<UserEntity, AppError> {
+        self.wrote(id);
+        self.inner.restore_user(id).await
+    }
+
+    async fn purge_deleted(
+        &self,
+        deleted_before: chrono::NaiveDateTime,
+    ) -> Result<Vec<i32>, AppError> {
+        self.inner.purge_deleted(deleted_before).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::entity::outbox_event::OutboxEventEntity;
+    use crate::entity::refresh_token::RefreshTokenEntity;
+    use crate::infra::cache::MemoryCache;
+    use crate::infra::testcontainer::PostgresContainer;
+    use crate::infra::testsqlite::SqliteDatabase;
+    use crate::repository::user::CachedUserRepository;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_commit() {
+        // given
+        let container = PostgresContainer::new().await;
+        let transactions = TransactionManagerImpl::new(container.pool());
+        let users = UserRepositoryImpl::new(container.pool());
+        // when
+        let unit_of_work = transactions.begin().await.unwrap();
+        unit_of_work.users().delete_user(1, None).await.unwrap();
+        unit_of_work
+            .refresh_tokens()
+            .revoke_all_for_user(1)
+            .await
+            .unwrap();
+        // then nothing is visible before the commit
+        assert!(users.find_by_id(1).await.unwrap().is_some());
+        unit_of_work.commit().await.unwrap();
+        assert!(users.find_by_id(1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rollback() {
+        // given
+        let container = PostgresContainer::new().await;
+        let transactions = TransactionManagerImpl::new(container.pool());
+        let users = UserRepositoryImpl::new(container.pool());
+        // when
+        let unit_of_work = transactions.begin().await.unwrap();
+        unit_of_work.users().delete_user(1, None).await.unwrap();
+        unit_of_work.rollback().await.unwrap();
+        // then
+        assert!(users.find_by_id(1).await.unwrap().is_some());
+        let result = unit_of_work.users().find_by_id(1).await;
+        assert!(matches!(result, Err(AppError::InternalServerError)));
+    }
+
+    #[tokio::test]
+    async fn test_failed_statement_aborts_the_unit_of_work() {
+        // given
+        let container = PostgresContainer::new().await;
+        let transactions = TransactionManagerImpl::new(container.pool());
+        let unit_of_work = transactions.begin().await.unwrap();
+        // when
+        let missing = unit_of_work.users().delete_user(99, None).await;
+        // then
+        assert!(matches!(missing, Err(AppError::NotFound)));
+        unit_of_work.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_commit_and_rollback() {
+        // given
+        let database = SqliteDatabase::new().await;
+        let transactions = SqliteTransactionManager::new(database.pool());
+        let users = SqliteUserRepository::new(database.pool());
+        // when
+        let discarded = transactions.begin().await.unwrap();
+        discarded.users().delete_user(1, None).await.unwrap();
+        discarded.rollback().await.unwrap();
+        let kept = transactions.begin().await.unwrap();
+        kept.users().delete_user(2, None).await.unwrap();
+        kept.refresh_tokens().revoke_all_for_user(2).await.unwrap();
+        kept.commit().await.unwrap();
+        // then
+        assert!(users.find_by_id(1).await.unwrap().is_some());
+        assert!(users.find_by_id(2).await.unwrap().is_none());
+        let again = kept.commit().await;
+        assert!(matches!(again, Err(AppError::InternalServerError)));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_commit_and_rollback() {
+        // given
+        let store = MemoryStore::seeded();
+        let transactions = InMemoryTransactionManager::new(store.clone());
+        let users = InMemoryUserRepository::new(store);
+        // when
+        let discarded = transactions.begin().await.unwrap();
+        discarded.users().delete_user(1, None).await.unwrap();
+        discarded.rollback().await.unwrap();
+        let kept = transactions.begin().await.unwrap();
+        kept.users().delete_user(2, None).await.unwrap();
+        // then nothing is visible before the commit
+        assert!(users.find_by_id(2).await.unwrap().is_some());
+        kept.commit().await.unwrap();
+        assert!(users.find_by_id(1).await.unwrap().is_some());
+        assert!(users.find_by_id(2).await.unwrap().is_none());
+        let again = kept.commit().await;
+        assert!(matches!(again, Err(AppError::InternalServerError)));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_commit_after_concurrent_write() {
+        // given
+        let store = MemoryStore::seeded();
+        let transactions = InMemoryTransactionManager::new(store.clone());
+        let users = InMemoryUserRepository::new(store);
+        let unit_of_work = transactions.begin().await.unwrap();
+        unit_of_work.users().delete_user(1, None).await.unwrap();
+        users.delete_user(1, None).await.unwrap();
+        // when
+        let result = unit_of_work.commit().await;
+        // then
+        assert!(matches!(result, Err(AppError::Conflict)));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_commit_after_unrelated_concurrent_writes() {
+        // given
+        let store = MemoryStore::seeded();
+        let transactions = InMemoryTransactionManager::new(store.clone());
+        let users = InMemoryUserRepository::new(store.clone());
+        let refresh_tokens = InMemoryRefreshTokenRepository::new(store.clone());
+        let outbox = InMemoryOutboxRepository::new(store);
+        let now = chrono::Utc::now().naive_utc();
+        outbox
+            .append(OutboxEventEntity {
+                id: 0,
+                event_type: "UserDeleted".to_string(),
+                payload: serde_json::json!({ "type": "UserDeleted", "userId": 2 }),
+                attempts: 0,
+                next_attempt_at: now,
+                last_error: None,
+                created_at: now,
+            })
+            .await
+            .unwrap();
+        let unit_of_work = transactions.begin().await.unwrap();
+        unit_of_work.users().delete_user(1, None).await.unwrap();
+        // when a login and the event dispatcher write meanwhile
+        refresh_tokens
+            .create(RefreshTokenEntity {
+                id: 0,
+                user_id: 2,
+                token_hash: "hash".to_string(),
+                expires_at: now,
+                revoked_at: None,
+                created_at: now,
+            })
+            .await
+            .unwrap();
+        let later = now + chrono::Duration::seconds(1);
+        assert_eq!(outbox.claim(later, later, 10).await.unwrap().len(), 1);
+        let result = unit_of_work.commit().await;
+        // then
+        assert!(result.is_ok());
+        assert!(users.find_by_id(1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cached_commit_forgets_written_users() {
+        // given
+        let store = MemoryStore::seeded();
+        let cache: Arc<dyn Cache> = Arc::new(MemoryCache::new(
+            10,
+            Duration::from_secs(60),
+            Duration::from_secs(5),
+        ));
+        let users = Arc::new(InMemoryUserRepository::new(store.clone()));
+        let users = CachedUserRepository::new(users.clone(), users, cache.clone());
+        let transactions = CachedTransactionManager::new(
+            Arc::new(InMemoryTransactionManager::new(store)),
+            cache.clone(),
+        );
+        users.find_by_id(1).await.unwrap();
+        users.find_by_id(2).await.unwrap();
+        // when
+        let unit_of_work = transactions.begin().await.unwrap();
+        unit_of_work.users().delete_user(1, None).await.unwrap();
+        // then
+        assert!(cache.get(&cache_key(1)).await.is_some());
+        unit_of_work.commit().await.unwrap();
+        assert!(cache.get(&cache_key(1)).await.is_none());
+        assert!(cache.get(&cache_key(2)).await.is_some());
+        assert!(users.find_by_id(1).await.unwrap().is_none());
+    }
+}
@@ -1,46 +1,197 @@
 use crate::entity::user::UserEntity;
+use crate::infra::cache::Cache;
+use crate::infra::db::Db;
+use crate::infra::memory::{self, MemoryStore, Sequence, UserRow};
+use crate::infra::query::{contains_pattern, push_page};
+use crate::infra::replica::ReplicaSet;
+use crate::infra::sqlite;
+use shared::query::{CursorKind, CursorValue, Keyed, ListQuery, Page, SortField};
 use shared::AppError;
-use sqlx::PgPool;
+use sqlx::{Database, Encode, QueryBuilder, Sqlite, Type};
+use std::future::Future;
 use std::sync::Arc;
+use tracing::instrument;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UserSortField {
+    Id,
+    Name,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl SortField for UserSortField {
+    const TIE_BREAKER: Self = UserSortField::Id;
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(UserSortField::Id),
+            "name" => Some(UserSortField::Name),
+            "created_at" => Some(UserSortField::CreatedAt),
+            "updated_at" => Some(UserSortField::UpdatedAt),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        self.column()
+    }
+
+    fn column(&self) -> &'static str {
+        match self {
+            UserSortField::Id => "id",
+            UserSortField::Name => "name",
+            UserSortField::CreatedAt => "created_at",
+            UserSortField::UpdatedAt => "updated_at",
+        }
+    }
+
+    fn kind(&self) -> CursorKind {
+        match self {
+            UserSortField::Id => CursorKind::Int,
+            UserSortField::Name => CursorKind::Text,
+            UserSortField::CreatedAt | UserSortField::UpdatedAt => CursorKind::Timestamp,
+        }
+    }
+}
+
+impl Keyed<UserSortField> for UserEntity {
+    fn key(&self, field: UserSortField) -> CursorValue {
+        match field {
+            UserSortField::Id => CursorValue::Int(self.id.into()),
+            UserSortField::Name => CursorValue::Text(self.name.clone()),
+            UserSortField::CreatedAt => CursorValue::Timestamp(self.created_at),
+            UserSortField::UpdatedAt => CursorValue::Timestamp(self.updated_at),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserFilter {
+    /// Case-insensitive substring of the name.
+    pub name_contains: Option<String>,
+    /// Only users created strictly after this time.
+    pub created_after: Option<chrono::NaiveDateTime>,
+    /// Also list users in the trash.
+    pub include_deleted: bool,
+}
+
+pub type UserQuery = ListQuery<UserSortField, UserFilter>;
 
 #[mockall::automock]
 #[async_trait::async_trait]
 pub trait UserRepository: Send + Sync {
-    async fn get_users(&self) -> Result<Vec<UserEntity>, AppError>;
+    async fn get_users(&self, query: &UserQuery) -> Result<Page<UserEntity>, AppError>;
+    /// Finds a user that is not in the trash.
     async fn find_by_id(&self, id: i32) -> Result<Option<UserEntity>, AppError>;
     async fn create_user(&self, user: UserEntity) -> Result<UserEntity, AppError>;
-    async fn update_user(&self, user: UserEntity) -> Result<UserEntity, AppError>;
-    async fn delete_user(&self, id: i32) -> Result<(), AppError>;
+    /// Stores `user` and bumps its version.
+    ///
+    /// With `expected_version`, fails with `AppError::PreconditionFailed`
+    /// unless the stored version still matches.
+    async fn update_user(
+        &self,
+        user: UserEntity,
+        expected_version: Option<i32>,
+    ) -> Result<UserEntity, AppError>;
+    /// Moves the user to the trash.
+    ///
+    /// With `expected_version`, fails with `AppError::PreconditionFailed`
+    /// unless the stored version still matches.
+    async fn delete_user(&self, id: i32, expected_version: Option<i32>) -> Result<(), AppError>;
+    /// Takes the user back out of the trash.
+    ///
+    /// Fails with `AppError::Conflict` if their email has been registered
+    /// again meanwhile.
+    async fn restore_user(&self, id: i32) -> Result<UserEntity, AppError>;
+    /// Permanently removes users deleted before `deleted_before` and
+    /// returns their ids.
+    async fn purge_deleted(
+        &self,
+        deleted_before: chrono::NaiveDateTime,
+    ) -> Result<Vec<i32>, AppError>;
 }
 
 #[derive(Debug, Clone)]
 pub struct UserRepositoryImpl {
-    pub db: Arc<PgPool>,
+    pub db: Db,
+    /// Serve `get_users` and `find_by_id` when set; `db` is the primary.
+    pub replicas: Option<Arc<ReplicaSet>>,
 }
 
 impl UserRepositoryImpl {
-    pub fn new(db: Arc<PgPool>) -> Self {
-        Self { db }
+    pub fn new(db: impl Into<Db>) -> Self {
+        Self {
+            db: db.into(),
+            replicas: None,
+        }
+    }
+
+    pub fn with_replicas(mut self, replicas: Option<Arc<ReplicaSet>>) -> Self {
+        self.replicas = replicas;
+        self
+    }
+
+    /// Runs `read` on a replica if there are any, otherwise on `db`.
+    async fn read<T, F, Fut>(&self, read: F) -> Result<T, AppError>
+    where
+        F: Fn(Db) -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        match &self.replicas {
+            Some(replicas) => replicas.read(&self.db, read).await,
+            None => read(self.db.clone()).await,
+        }
+    }
+
+    /// Sends the caller's next reads to the primary.
+    fn wrote(&self) {
+        if let Some(replicas) = &self.replicas {
+            replicas.record_write();
+        }
     }
 }
 
+async fn find_by_id(db: Db, id: i32) -> Result<Option<UserEntity>, AppError> {
+    let entity = sqlx::query_as::<_, UserEntity>(
+        "SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL;",
+    )
+    .bind(id)
+    .fetch_optional(&mut *db.acquire().await?)
+    .await?;
+    Ok(entity)
+}
+
 #[async_trait::async_trait]
 impl UserRepository for UserRepositoryImpl {
-    async fn get_users(&self) -> Result<Vec<UserEntity>, AppError> {
-        let entities = sqlx::query_as::<_, UserEntity>("SELECT * FROM users;")
-            .fetch_all(&*self.db)
-            .await?;
-        Ok(entities)
+    #[instrument(skip(self))]
+    async fn get_users(&self, query: &UserQuery) -> Result<Page<UserEntity>, AppError> {
+        self.read(|db| async move {
+            let mut count = QueryBuilder::new("SELECT COUNT(*) FROM users WHERE TRUE");
+            push_filter(&mut count, &query.filter);
+            let total: i64 = count
+                .build_query_scalar()
+                .fetch_one(&mut *db.acquire().await?)
+                .await?;
+
+            let mut select = QueryBuilder::new("SELECT * FROM users WHERE TRUE");
+            push_filter(&mut select, &query.filter);
+            push_page(&mut select, query);
+            let entities = select
+                .build_query_as::<UserEntity>()
+                .fetch_all(&mut *db.acquire().await?)
+                .await?;
+            Ok(Page::from_rows(entities, query, total))
+        })
+        .await
     }
 
+    #[instrument(skip(self))]
     async fn find_by_id(&self, id: i32) -> Result<Option<UserEntity>, AppError> {
-        let entity = sqlx::query_as::<_, UserEntity>("SELECT * FROM users WHERE id = $1;")
-            .bind(id)
-            .fetch_optional(&*self.db)
-            .await?;
-        Ok(entity)
+        self.read(|db| find_by_id(db, id)).await
     }
 
+    #[instrument(skip(self, user))]
     async fn create_user(&self, user: UserEntity) -> Result<UserEntity, AppError> {
         let entity = sqlx::query_as::<_, UserEntity>(
             r#"
@@ -50,50 +201,641 @@ impl UserRepository for UserRepositoryImpl {
             "#,
         )
         .bind(&user.name)
-        .fetch_one(&*self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?;
+        self.wrote();
         Ok(entity)
     }
 
-    async fn update_user(&self, user: UserEntity) -> Result<UserEntity, AppError> {
+    #[instrument(skip(self, user), fields(id = user.id))]
+    async fn update_user(
+        &self,
+        user: UserEntity,
+        expected_version: Option<i32>,
+    ) -> Result<UserEntity, AppError> {
         let entity = sqlx::query_as::<_, UserEntity>(
             r#"
             UPDATE users
-            SET name = $2, updated_at = CURRENT_TIMESTAMP
-            WHERE id = $1
+            SET name = $2, updated_at = CURRENT_TIMESTAMP, version = version + 1
+            WHERE id = $1 AND deleted_at IS NULL AND ($3::INTEGER IS NULL OR version = $3)
             RETURNING *;
             "#,
         )
-        .bind(&user.id)
+        .bind(user.id)
         .bind(&user.name)
-        .fetch_one(&*self.db)
+        .bind(expected_version)
+        .fetch_optional(&mut *self.db.acquire().await?)
         .await?;
+        match entity {
+            Some(entity) => {
+                self.wrote();
+                Ok(entity)
+            }
+            None => Err(self.missed(user.id).await),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_user(&self, id: i32, expected_version: Option<i32>) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET deleted_at = CURRENT_TIMESTAMP, version = version + 1
+            WHERE id = $1 AND deleted_at IS NULL AND ($2::INTEGER IS NULL OR version = $2);
+            "#,
+        )
+        .bind(id)
+        .bind(expected_version)
+        .execute(&mut *self.db.acquire().await?)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(self.missed(id).await);
+        }
+        self.wrote();
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn restore_user(&self, id: i32) -> Result<UserEntity, AppError> {
+        let entity = sqlx::query_as::<_, UserEntity>(
+            r#"
+            UPDATE users
+            SET deleted_at = NULL, updated_at = CURRENT_TIMESTAMP, version = version + 1
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            RETURNING *;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *self.db.acquire().await?)
+        .await?;
+        let entity = entity.ok_or(AppError::NotFound)?;
+        self.wrote();
         Ok(entity)
     }
 
-    async fn delete_user(&self, id: i32) -> Result<(), AppError> {
-        sqlx::query("DELETE FROM users WHERE id = $1;")
-            .bind(id)
-            .execute(&*self.db)
+    #[instrument(skip(self))]
+    async fn purge_deleted(
+        &self,
+        deleted_before: chrono::NaiveDateTime,
+    ) -> Result<Vec<i32>, AppError> {
+        let ids =
+            sqlx::query_scalar::<_, i32>("DELETE FROM users WHERE deleted_at < $1 RETURNING id;")
+                .bind(deleted_before)
+                .fetch_all(&mut *self.db.acquire().await?)
+                .await?;
+        Ok(ids)
+    }
+}
+
+impl UserRepositoryImpl {
+    /// Explains why a guarded write to user `id` matched no row.
+    async fn missed(&self, id: i32) -> AppError {
+        let mut connection = match self.db.acquire().await {
+            Ok(connection) => connection,
+            Err(err) => return err,
+        };
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM users WHERE id = $1 AND deleted_at IS NULL);",
+        )
+        .bind(id)
+        .fetch_one(&mut *connection)
+        .await;
+        match exists {
+            Ok(true) => AppError::PreconditionFailed,
+            Ok(false) => AppError::NotFound,
+            Err(err) => err.into(),
+        }
+    }
+}
+
+fn push_filter<'a, DB>(builder: &mut QueryBuilder<'a, DB>, filter: &UserFilter)
+where
+    DB: Database,
+    String: Encode<'a, DB> + Type<DB>,
+    chrono::NaiveDateTime: Encode<'a, DB> + Type<DB>,
+{
+    if !filter.include_deleted {
+        builder.push(" AND deleted_at IS NULL");
+    }
+    if let Some(name) = &filter.name_contains {
+        // SQLite has no ILIKE and no default escape character
+        builder
+            .push(" AND LOWER(name) LIKE LOWER(")
+            .push_bind(contains_pattern(name))
+            .push(") ESCAPE '\\'");
+    }
+    if let Some(created_after) = filter.created_after {
+        builder.push(" AND created_at > ").push_bind(created_after);
+    }
+}
+
+/// [`UserRepository`] on SQLite.
+#[derive(Debug, Clone)]
+pub struct SqliteUserRepository {
+    pub db: Db<Sqlite>,
+}
+
+impl SqliteUserRepository {
+    pub fn new(db: impl Into<Db<Sqlite>>) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserRepository for SqliteUserRepository {
+    #[instrument(skip(self))]
+    async fn get_users(&self, query: &UserQuery) -> Result<Page<UserEntity>, AppError> {
+        let mut count = QueryBuilder::new("SELECT COUNT(*) FROM users WHERE TRUE");
+        push_filter(&mut count, &query.filter);
+        let total: i64 = count
+            .build_query_scalar()
+            .fetch_one(&mut *self.db.acquire().await?)
+            .await?;
+
+        let mut select = QueryBuilder::new("SELECT * FROM users WHERE TRUE");
+        push_filter(&mut select, &query.filter);
+        push_page(&mut select, query);
+        let entities = select
+            .build_query_as::<UserEntity>()
+            .fetch_all(&mut *self.db.acquire().await?)
             .await?;
+        Ok(Page::from_rows(entities, query, total))
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_id(&self, id: i32) -> Result<Option<UserEntity>, AppError> {
+        let entity = sqlx::query_as::<_, UserEntity>(
+            "SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL;",
+        )
+        .bind(id)
+        .fetch_optional(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    #[instrument(skip(self, user))]
+    async fn create_user(&self, user: UserEntity) -> Result<UserEntity, AppError> {
+        let entity = sqlx::query_as::<_, UserEntity>(
+            r#"
+            INSERT INTO users (name, created_at, updated_at)
+            VALUES ($1, $2, $2)
+            RETURNING *;
+            "#,
+        )
+        .bind(&user.name)
+        .bind(sqlite::now())
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+        Ok(entity)
+    }
+
+    #[instrument(skip(self, user), fields(id = user.id))]
+    async fn update_user(
+        &self,
+        user: UserEntity,
+        expected_version: Option<i32>,
+    ) -> Result<UserEntity, AppError> {
+        let entity = sqlx::query_as::<_, UserEntity>(
+            r#"
+            UPDATE users
+            SET name = $2, updated_at = $4, version = version + 1
+            WHERE id = $1 AND deleted_at IS NULL AND ($3 IS NULL OR version = $3)
+            RETURNING *;
+            "#,
+        )
+        .bind(user.id)
+        .bind(&user.name)
+        .bind(expected_version)
+        .bind(sqlite::now())
+        .fetch_optional(&mut *self.db.acquire().await?)
+        .await?;
+        match entity {
+            Some(entity) => Ok(entity),
+            None => Err(self.missed(user.id).await),
+        }
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_user(&self, id: i32, expected_version: Option<i32>) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET deleted_at = $3, version = version + 1
+            WHERE id = $1 AND deleted_at IS NULL AND ($2 IS NULL OR version = $2);
+            "#,
+        )
+        .bind(id)
+        .bind(expected_version)
+        .bind(sqlite::now())
+        .execute(&mut *self.db.acquire().await?)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(self.missed(id).await);
+        }
         Ok(())
     }
+
+    #[instrument(skip(self))]
+    async fn restore_user(&self, id: i32) -> Result<UserEntity, AppError> {
+        let entity = sqlx::query_as::<_, UserEntity>(
+            r#"
+            UPDATE users
+            SET deleted_at = NULL, updated_at = $2, version = version + 1
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            RETURNING *;
+            "#,
+        )
+        .bind(id)
+        .bind(sqlite::now())
+        .fetch_optional(&mut *self.db.acquire().await?)
+        .await?;
+        entity.ok_or(AppError::NotFound)
+    }
+
+    #[instrument(skip(self))]
+    async fn purge_deleted(
+        &self,
+        deleted_before: chrono::NaiveDateTime,
+    ) -> Result<Vec<i32>, AppError> {
+        let ids =
+            sqlx::query_scalar::<_, i32>("DELETE FROM users WHERE deleted_at < $1 RETURNING id;")
+                .bind(deleted_before)
+                .fetch_all(&mut *self.db.acquire().await?)
+                .await?;
+        Ok(ids)
+    }
+}
+
+impl SqliteUserRepository {
+    /// Explains why a guarded write to user `id` matched no row.
+    async fn missed(&self, id: i32) -> AppError {
+        let mut connection = match self.db.acquire().await {
+            Ok(connection) => connection,
+            Err(err) => return err,
+        };
+        let exists = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM users WHERE id = $1 AND deleted_at IS NULL);",
+        )
+        .bind(id)
+        .fetch_one(&mut *connection)
+        .await;
+        match exists {
+            Ok(true) => AppError::PreconditionFailed,
+            Ok(false) => AppError::NotFound,
+            Err(err) => err.into(),
+        }
+    }
+}
+
+/// [`UserRepository`] on a [`MemoryStore`].
+#[derive(Debug, Clone)]
+pub struct InMemoryUserRepository {
+    pub store: MemoryStore,
+}
+
+impl InMemoryUserRepository {
+    pub fn new(store: MemoryStore) -> Self {
+        Self { store }
+    }
+
+    /// Applies `change` to the live user `id` if its version still matches.
+    fn guarded_write(
+        &self,
+        id: i32,
+        expected_version: Option<i32>,
+        change: impl FnOnce(&mut UserEntity, chrono::NaiveDateTime),
+    ) -> Result<UserEntity, AppError> {
+        let now = self.store.now();
+        self.store.write(|tables| {
+            let user = match tables.users.get_mut(&id) {
+                Some(row) if row.user.deleted_at.is_none() => &mut row.user,
+                _ => return Err(AppError::NotFound),
+            };
+            if expected_version.is_some_and(|version| version != user.version) {
+                return Err(AppError::PreconditionFailed);
+            }
+            change(user, now);
+            user.version += 1;
+            Ok(user.clone())
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl UserRepository for InMemoryUserRepository {
+    async fn get_users(&self, query: &UserQuery) -> Result<Page<UserEntity>, AppError> {
+        let filter = &query.filter;
+        let name_contains = filter
+            .name_contains
+            .as_ref()
+            .map(|name| name.to_lowercase());
+        let users = self.store.read(|tables| {
+            tables
+                .users
+                .values()
+                .map(|row| &row.user)
+                .filter(|user| filter.include_deleted || user.deleted_at.is_none())
+                .filter(|user| {
+                    name_contains
+                        .as_ref()
+                        .is_none_or(|name| user.name.to_lowercase().contains(name))
+                })
+                .filter(|user| {
+                    filter
+                        .created_after
+                        .is_none_or(|after| user.created_at > after)
+                })
+                .cloned()
+                .collect()
+        });
+        Ok(memory::page(users, query))
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<Option<UserEntity>, AppError> {
+        Ok(self.store.read(|tables| {
+            tables
+                .users
+                .get(&id)
+                .filter(|row| row.user.deleted_at.is_none())
+                .map(|row| row.user.clone())
+        }))
+    }
+
+    async fn create_user(&self, user: UserEntity) -> Result<UserEntity, AppError> {
+        let now = self.store.now();
+        let user = UserEntity {
+            id: self.store.next_id(Sequence::Users),
+            name: user.name,
+            created_at: now,
+            updated_at: now,
+            version: 1,
+            deleted_at: None,
+        };
+        self.store.write(|tables| {
+            tables.users.insert(
+                user.id,
+                UserRow {
+                    user: user.clone(),
+                    email: None,
+                    password_hash: None,
+                },
+            );
+            Ok(user)
+        })
+    }
+
+    async fn update_user(
+        &self,
+        user: UserEntity,
+        expected_version: Option<i32>,
+    ) -> Result<UserEntity, AppError> {
+        self.guarded_write(user.id, expected_version, |stored, now| {
+            stored.name = user.name;
+            stored.updated_at = now;
+        })
+    }
+
+    async fn delete_user(&self, id: i32, expected_version: Option<i32>) -> Result<(), AppError> {
+        self.guarded_write(id, expected_version, |stored, now| {
+            stored.deleted_at = Some(now);
+        })
+        .map(|_| ())
+    }
+
+    async fn restore_user(&self, id: i32) -> Result<UserEntity, AppError> {
+        let now = self.store.now();
+        self.store.write(|tables| {
+            let email = match tables.users.get(&id) {
+                Some(row) if row.user.deleted_at.is_some() => row.email.clone(),
+                _ => return Err(AppError::NotFound),
+            };
+            // like the unique index on the emails of users outside the trash
+            if email.is_some()
+                && tables
+                    .users
+                    .values()
+                    .any(|row| row.user.deleted_at.is_none() && row.email == email)
+            {
+                return Err(AppError::Conflict);
+            }
+            let Some(row) = tables.users.get_mut(&id) else {
+                return Err(AppError::NotFound);
+            };
+            let user = &mut row.user;
+            user.deleted_at = None;
+            user.updated_at = now;
+            user.version += 1;
+            Ok(user.clone())
+        })
+    }
+
+    async fn purge_deleted(
+        &self,
+        deleted_before: chrono::NaiveDateTime,
+    ) -> Result<Vec<i32>, AppError> {
+        self.store.write(|tables| {
+            let purged: Vec<i32> = tables
+                .users
+                .values()
+                .filter(|row| row.user.deleted_at.is_some_and(|at| at < deleted_before))
+                .map(|row| row.user.id)
+                .collect();
+            for id in &purged {
+                tables.remove_user(*id);
+            }
+            Ok(purged)
+        })
+    }
+}
+
+/// Key of user `id` in the cache of [`CachedUserRepository`].
+pub fn cache_key(id: i32) -> String {
+    format!("memo:users:{id}")
+}
+
+/// [`UserRepository`] that serves `find_by_id` from a [`Cache`] in front of
+/// another implementation and forgets a user whenever it is written.
+/// Listings always go to the wrapped repository.
+#[derive(Clone)]
+pub struct CachedUserRepository {
+    inner: Arc<dyn UserRepository>,
+    primary: Arc<dyn UserRepository>,
+    cache: Arc<dyn Cache>,
+}
+
+impl CachedUserRepository {
+    /// Misses are read from `primary`, the same users as `inner` but never
+    /// on a read replica, which may not have caught up with a write whose
+    /// invalidation came before the miss.
+    pub fn new(
+        inner: Arc<dyn UserRepository>,
+        primary: Arc<dyn UserRepository>,
+        cache: Arc<dyn Cache>,
+    ) -> Self {
+        Self {
+            inner,
+            primary,
+            cache,
+        }
+    }
+
+    async fn cached(&self, id: i32) -> Option<UserEntity> {
+        let value = self.cache.get(&cache_key(id)).await?;
+        match serde_json::from_str(&value) {
+            Ok(user) => Some(user),
+            Err(err) => {
+                tracing::warn!(error = %err, id, "dropping an unreadable cached user");
+                None
+            }
+        }
+    }
+
+    /// Drops user `id` from the cache. Runs whether or not the write
+    /// succeeded, since a failed guarded write may mean the cache is stale.
+    async fn forget(&self, id: i32) {
+        self.cache.invalidate(&cache_key(id)).await;
+    }
+}
+
+#[async_trait::async_trait]
+impl UserRepository for CachedUserRepository {
+    async fn get_users(&self, query: &UserQuery) -> Result<Page<UserEntity>, AppError> {
+        self.inner.get_users(query).await
+    }
+
+    #[instrument(skip(self))]
+    async fn find_by_id(&self, id: i32) -> Result<Option<UserEntity>, AppError> {
+        if let Some(user) = self.cached(id).await {
+            metrics::counter!("cache_requests_total", "cache" => "users", "result" => "hit")
+                .increment(1);
+            return Ok(Some(user));
+        }
+        metrics::counter!("cache_requests_total", "cache" => "users", "result" => "miss")
+            .increment(1);
+        let user = self.primary.find_by_id(id).await?;
+        // only users that exist are cached, so that creating one needs no
+        // invalidation; a write since the read above has left a tombstone,
+        // which the old copy must not replace
+        if let Some(user) = &user {
+            match serde_json::to_string(user) {
+                Ok(value) => self.cache.add(&cache_key(id), value).await,
+                Err(err) => tracing::warn!(error = %err, id, "failed to cache a user"),
+            }
+        }
+        Ok(user)
+    }
+
+    async fn create_user(&self, user: UserEntity) -> Result<UserEntity, AppError> {
+        self.inner.create_user(user).await
+    }
+
+    async fn update_user(
+        &self,
+        user: UserEntity,
+        expected_version: Option<i32>,
+    ) -> Result<UserEntity, AppError> {
+        let id = user.id;
+        let result = self.inner.update_user(user, expected_version).await;
+        self.forget(id).await;
+        result
+    }
+
+    async fn delete_user(&self, id: i32, expected_version: Option<i32>) -> Result<(), AppError> {
+        let result = self.inner.delete_user(id, expected_version).await;
+        self.forget(id).await;
+        result
+    }
+
+    async fn restore_user(&self, id: i32) -> Result<UserEntity, AppError> {
+        let result = self.inner.restore_user(id).await;
+        self.forget(id).await;
+        result
+    }
+
+    async fn purge_deleted(
+        &self,
+        deleted_before: chrono::NaiveDateTime,
+    ) -> Result<Vec<i32>, AppError> {
+        // users in the trash are never cached
+        self.inner.purge_deleted(deleted_before).await
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use crate::infra::testcontainer::PostgresContainer;
+    use crate::infra::cache::MemoryCache;
+    use crate::infra::redis::RedisCache;
+    use crate::infra::testcontainer::{PostgresContainer, RedisContainer};
+    use crate::infra::testsqlite::SqliteDatabase;
+    use chrono::SubsecRound;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use std::time::Duration;
 
-    #[tokio::test]
-    async fn test_get_users() {
-        // given
-        let container = PostgresContainer::new().await;
-        let repository = UserRepositoryImpl::new(container.pool());
+    /// Runs every case of the conformance suite against each backend,
+    /// seeded with the data of `migrations/test`.
+    macro_rules! conformance {
+        ($($case:ident),* $(,)?) => {
+            mod postgres {
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        let container = super::PostgresContainer::new().await;
+                        let repository = super::UserRepositoryImpl::new(container.pool());
+                        super::$case(&repository).await;
+                    }
+                )*
+            }
+
+            mod sqlite {
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        let database = super::SqliteDatabase::new().await;
+                        let repository = super::SqliteUserRepository::new(database.pool());
+                        super::$case(&repository).await;
+                    }
+                )*
+            }
+
+            mod memory {
+                $(
+                    #[tokio::test]
+                    async fn $case() {
+                        let store = super::MemoryStore::seeded();
+                        super::$case(&super::InMemoryUserRepository::new(store)).await;
+                    }
+                )*
+            }
+        };
+    }
+
+    conformance!(
+        test_get_users,
+        test_get_users_pages_through_sort,
+        test_get_users_filtered,
+        test_find_by_id,
+        test_create_user,
+        test_update_user,
+        test_update_user_with_expected_version,
+        test_delete_user,
+        test_delete_missing_user,
+        test_restore_user,
+        test_purge_deleted,
+        test_delete_user_with_stale_version,
+    );
+
+    async fn test_get_users(repository: &dyn UserRepository) {
         // when
-        let users = repository.get_users().await.unwrap();
+        let page = repository
+            .get_users(&UserQuery::new(UserFilter::default(), None, None, None).unwrap())
+            .await
+            .unwrap();
         // then
+        assert_eq!(page.total, 2);
+        assert!(page.next_cursor.is_none());
+        let users = page.items;
         assert_eq!(users.len(), 2);
         assert_eq!(users[0].id, 1);
         assert_eq!(users[0].name, "Alice");
@@ -121,11 +863,85 @@ mod tests {
         );
     }
 
-    #[tokio::test]
-    async fn test_find_by_id() {
+    async fn test_get_users_pages_through_sort(repository: &dyn UserRepository) {
+        // given
+        for name in ["Bob", "Carol", "Alice"] {
+            repository
+                .create_user(UserEntity {
+                    id: 0,
+                    name: name.to_string(),
+                    version: 1,
+                    deleted_at: None,
+                    created_at: chrono::Utc::now().naive_utc(),
+                    updated_at: chrono::Utc::now().naive_utc(),
+                })
+                .await
+                .unwrap();
+        }
+        // when
+        let mut ids = vec![];
+        let mut cursor = None;
+        loop {
+            let query = UserQuery::new(
+                UserFilter::default(),
+                Some("name,-created_at"),
+                Some(2),
+                cursor.as_deref(),
+            )
+            .unwrap();
+            let page = repository.get_users(&query).await.unwrap();
+            assert_eq!(page.total, 5);
+            ids.extend(page.items.iter().map(|user| user.id));
+            match page.next_cursor {
+                Some(next) => cursor = Some(next.encode()),
+                None => break,
+            }
+        }
+        // then
+        // names tie on Alice (5 newer than 1) and Bob (3 newer than 2)
+        assert_eq!(ids, vec![5, 1, 3, 2, 4]);
+    }
+
+    async fn test_get_users_filtered(repository: &dyn UserRepository) {
         // given
-        let container = PostgresContainer::new().await;
-        let repository = UserRepositoryImpl::new(container.pool());
+        let query = |filter| UserQuery::new(filter, None, None, None).unwrap();
+        // when
+        let by_name = repository
+            .get_users(&query(UserFilter {
+                name_contains: Some("LI".to_string()),
+                ..UserFilter::default()
+            }))
+            .await
+            .unwrap();
+        let by_created_at = repository
+            .get_users(&query(UserFilter {
+                created_after: Some(
+                    chrono::NaiveDateTime::parse_from_str(
+                        "2025-02-10 00:00:00",
+                        "%Y-%m-%d %H:%M:%S",
+                    )
+                    .unwrap(),
+                ),
+                ..UserFilter::default()
+            }))
+            .await
+            .unwrap();
+        let wildcard = repository
+            .get_users(&query(UserFilter {
+                name_contains: Some("%".to_string()),
+                ..UserFilter::default()
+            }))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(by_name.total, 1);
+        assert_eq!(by_name.items[0].name, "Alice");
+        assert_eq!(by_created_at.total, 1);
+        assert_eq!(by_created_at.items[0].name, "Bob");
+        assert_eq!(wildcard.total, 0);
+    }
+
+    async fn test_find_by_id(repository: &dyn UserRepository) {
         // when
         let user = repository.find_by_id(1).await.unwrap();
         // then
@@ -145,17 +961,16 @@ mod tests {
         );
     }
 
-    #[tokio::test]
-    async fn test_create_user() {
-        // given
-        let container = PostgresContainer::new().await;
-        let repository = UserRepositoryImpl::new(container.pool());
-        let current_time = chrono::Utc::now().naive_utc();
+    async fn test_create_user(repository: &dyn UserRepository) {
+        // given the time at the precision of a TIMESTAMP column
+        let current_time = chrono::Utc::now().naive_utc().trunc_subsecs(6);
         // when
         let user = repository
             .create_user(UserEntity {
                 id: 0,
                 name: "Kate".to_string(),
+                version: 1,
+                deleted_at: None,
                 created_at: current_time,
                 updated_at: current_time,
             })
@@ -164,39 +979,294 @@ mod tests {
         // then
         assert_eq!(user.id, 3);
         assert_eq!(user.name, "Kate");
-        assert!(user.created_at > current_time);
-        assert!(user.updated_at > current_time);
+        assert!(user.created_at >= current_time);
+        assert_eq!(user.updated_at, user.created_at);
     }
 
-    #[tokio::test]
-    async fn test_update_user() {
+    async fn test_update_user(repository: &dyn UserRepository) {
         // given
-        let container = PostgresContainer::new().await;
-        let repository = UserRepositoryImpl::new(container.pool());
         let mut previous = repository.find_by_id(1).await.unwrap().unwrap();
         previous.name = "Charlie".to_string();
         let previous_created_at = previous.created_at;
         let previous_updated_at = previous.updated_at;
 
         // when
-        let user = repository.update_user(previous).await.unwrap();
+        let user = repository.update_user(previous, None).await.unwrap();
         // then
         assert_eq!(user.id, 1);
         assert_eq!(user.name, "Charlie");
         assert_eq!(user.created_at, previous_created_at);
         assert!(user.updated_at > previous_updated_at);
+        assert_eq!(user.version, 2);
     }
 
-    #[tokio::test]
-    async fn test_delete_user() {
+    async fn test_update_user_with_expected_version(repository: &dyn UserRepository) {
         // given
-        let container = PostgresContainer::new().await;
-        let repository = UserRepositoryImpl::new(container.pool());
+        let mut user = repository.find_by_id(1).await.unwrap().unwrap();
+        user.name = "Charlie".to_string();
+        let first = repository.update_user(user, Some(1)).await.unwrap();
+        // when
+        let stale = repository.update_user(first, Some(1)).await;
+        let missing = repository
+            .update_user(
+                UserEntity {
+                    id: 99,
+                    name: "Nobody".to_string(),
+                    created_at: chrono::Utc::now().naive_utc(),
+                    updated_at: chrono::Utc::now().naive_utc(),
+                    version: 1,
+                    deleted_at: None,
+                },
+                Some(1),
+            )
+            .await;
+        // then
+        assert!(matches!(stale, Err(AppError::PreconditionFailed)));
+        assert!(matches!(missing, Err(AppError::NotFound)));
+        let user = repository.find_by_id(1).await.unwrap().unwrap();
+        assert_eq!(user.version, 2);
+    }
+
+    async fn test_delete_user(repository: &dyn UserRepository) {
         // when
-        let _ = repository.delete_user(1).await;
+        repository.delete_user(1, None).await.unwrap();
         // then
         let user = repository.find_by_id(1).await.unwrap();
         assert!(user.is_none());
-        // no assertion
+        let listed = |include_deleted| {
+            let filter = UserFilter {
+                include_deleted,
+                ..UserFilter::default()
+            };
+            UserQuery::new(filter, None, None, None).unwrap()
+        };
+        let page = repository.get_users(&listed(false)).await.unwrap();
+        assert_eq!(page.items.iter().map(|u| u.id).collect::<Vec<_>>(), vec![2]);
+        let page = repository.get_users(&listed(true)).await.unwrap();
+        assert_eq!(page.total, 2);
+        assert!(page.items[0].deleted_at.is_some());
+        // deleting twice finds nothing to delete
+        let again = repository.delete_user(1, None).await;
+        assert!(matches!(again, Err(AppError::NotFound)));
+    }
+
+    async fn test_delete_missing_user(repository: &dyn UserRepository) {
+        // when
+        let result = repository.delete_user(99, None).await;
+        // then
+        assert!(matches!(result, Err(AppError::NotFound)));
+    }
+
+    async fn test_restore_user(repository: &dyn UserRepository) {
+        // given
+        repository.delete_user(1, None).await.unwrap();
+        // when
+        let user = repository.restore_user(1).await.unwrap();
+        // then
+        assert_eq!(user.id, 1);
+        assert!(user.deleted_at.is_none());
+        assert_eq!(user.version, 3);
+        assert!(repository.find_by_id(1).await.unwrap().is_some());
+        // only users in the trash can be restored
+        let again = repository.restore_user(1).await;
+        assert!(matches!(again, Err(AppError::NotFound)));
+    }
+
+    async fn test_purge_deleted(repository: &dyn UserRepository) {
+        // given
+        repository.delete_user(1, None).await.unwrap();
+        let before_delete = chrono::Utc::now().naive_utc() - chrono::Duration::hours(1);
+        let after_delete = chrono::Utc::now().naive_utc() + chrono::Duration::hours(1);
+        // when
+        let kept = repository.purge_deleted(before_delete).await.unwrap();
+        let purged = repository.purge_deleted(after_delete).await.unwrap();
+        // then
+        assert!(kept.is_empty());
+        assert_eq!(purged, vec![1]);
+        let restored = repository.restore_user(1).await;
+        assert!(matches!(restored, Err(AppError::NotFound)));
+        assert!(repository.find_by_id(2).await.unwrap().is_some());
+    }
+
+    async fn test_delete_user_with_stale_version(repository: &dyn UserRepository) {
+        // when
+        let result = repository.delete_user(1, Some(7)).await;
+        // then
+        assert!(matches!(result, Err(AppError::PreconditionFailed)));
+        assert!(repository.find_by_id(1).await.unwrap().is_some());
+    }
+
+    fn memory_cache() -> MemoryCache {
+        MemoryCache::new(10, Duration::from_secs(60), Duration::from_secs(5))
+    }
+
+    fn carol(id: i32, name: &str) -> UserEntity {
+        let at = chrono::NaiveDate::from_ymd_opt(2025, 2, 14)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        UserEntity {
+            id,
+            name: name.to_string(),
+            created_at: at,
+            updated_at: at,
+            version: 1,
+            deleted_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_find_by_id_counts_hits_and_misses() {
+        // given
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let _guard = metrics::set_default_local_recorder(&recorder);
+        let mut primary = MockUserRepository::new();
+        primary
+            .expect_find_by_id()
+            .times(1)
+            .returning(|id| Ok(Some(carol(id, "Carol"))));
+        let repository = CachedUserRepository::new(
+            Arc::new(MockUserRepository::new()),
+            Arc::new(primary),
+            Arc::new(memory_cache()),
+        );
+        // when
+        let first = repository.find_by_id(3).await.unwrap();
+        let second = repository.find_by_id(3).await.unwrap();
+        // then
+        assert_eq!(first.unwrap().name, "Carol");
+        assert_eq!(second.unwrap().name, "Carol");
+        let mut counters: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let result = key
+                    .key()
+                    .labels()
+                    .find(|label| label.key() == "result")
+                    .map(|label| label.value().to_string());
+                (key.key().name().to_string(), result, value)
+            })
+            .collect();
+        counters.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(
+            counters,
+            vec![
+                (
+                    "cache_requests_total".to_string(),
+                    Some("hit".to_string()),
+                    DebugValue::Counter(1)
+                ),
+                (
+                    "cache_requests_total".to_string(),
+                    Some("miss".to_string()),
+                    DebugValue::Counter(1)
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cached_user_is_forgotten_on_write() {
+        // given
+        let mut primary = MockUserRepository::new();
+        primary
+            .expect_find_by_id()
+            .times(4)
+            .returning(|id| Ok(Some(carol(id, "Carol"))));
+        let mut inner = MockUserRepository::new();
+        inner.expect_update_user().returning(|user, _| Ok(user));
+        inner
+            .expect_delete_user()
+            .returning(|_, _| Err(AppError::PreconditionFailed));
+        inner
+            .expect_restore_user()
+            .returning(|id| Ok(carol(id, "Carol")));
+        let repository =
+            CachedUserRepository::new(Arc::new(inner), Arc::new(primary), Arc::new(memory_cache()));
+        // when each write is followed by a lookup
+        repository.find_by_id(3).await.unwrap();
+        repository
+            .update_user(carol(3, "Caroline"), None)
+            .await
+            .unwrap();
+        repository.find_by_id(3).await.unwrap();
+        let failed = repository.delete_user(3, Some(1)).await;
+        repository.find_by_id(3).await.unwrap();
+        repository.restore_user(3).await.unwrap();
+        repository.find_by_id(3).await.unwrap();
+        // then every lookup went to the primary
+        assert!(matches!(failed, Err(AppError::PreconditionFailed)));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_lookup_that_read_before_a_write_does_not_cache_the_old_copy() {
+        // given a lookup that misses and reads Carol just before a write
+        let (read_tx, read) = tokio::sync::oneshot::channel();
+        let (resume, resumed) = std::sync::mpsc::channel::<()>();
+        let read_tx = std::sync::Mutex::new(Some(read_tx));
+        let resumed = std::sync::Mutex::new(resumed);
+        let mut primary = MockUserRepository::new();
+        primary.expect_find_by_id().times(1).returning(move |id| {
+            let old = carol(id, "Carol");
+            read_tx.lock().unwrap().take().unwrap().send(()).unwrap();
+            resumed.lock().unwrap().recv().unwrap();
+            Ok(Some(old))
+        });
+        let mut inner = MockUserRepository::new();
+        inner.expect_update_user().returning(|user, _| Ok(user));
+        let cache = Arc::new(memory_cache());
+        let repository = Arc::new(CachedUserRepository::new(
+            Arc::new(inner),
+            Arc::new(primary),
+            cache.clone(),
+        ));
+        let lookup = tokio::spawn({
+            let repository = repository.clone();
+            async move { repository.find_by_id(3).await }
+        });
+        read.await.unwrap();
+        // when the write commits and invalidates before the lookup fills
+        repository
+            .update_user(carol(3, "Caroline"), None)
+            .await
+            .unwrap();
+        resume.send(()).unwrap();
+        let looked_up = lookup.await.unwrap().unwrap().unwrap();
+        // then the old copy is returned once but never cached
+        assert_eq!(looked_up.name, "Carol");
+        assert_eq!(cache.get(&cache_key(3)).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_redis_cache_is_shared_between_instances() {
+        // given two instances on the same database and Redis
+        let redis = RedisContainer::new(None).await;
+        let store = MemoryStore::seeded();
+        let instance = || {
+            let users = Arc::new(InMemoryUserRepository::new(store.clone()));
+            CachedUserRepository::new(
+                users.clone(),
+                users,
+                Arc::new(RedisCache::new(
+                    &format!("redis://{}", redis.address()),
+                    Duration::from_secs(60),
+                    Duration::from_secs(5),
+                    Duration::from_millis(200),
+                )),
+            )
+        };
+        let (first, second) = (instance(), instance());
+        first.find_by_id(1).await.unwrap();
+        // when
+        let mut user = second.find_by_id(1).await.unwrap().unwrap();
+        user.name = "Alicia".to_string();
+        second.update_user(user, None).await.unwrap();
+        // then
+        let user = first.find_by_id(1).await.unwrap().unwrap();
+        assert_eq!(user.name, "Alicia");
+        assert_eq!(user.version, 2);
     }
 }
@@ -0,0 +1,34 @@
+use repository::entity::audit_event::AuditEventEntity;
+
+/// A change recorded in the audit log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub id: i64,
+    /// `None` when the system made the change, e.g. the purge job.
+    pub actor_id: Option<i32>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: i32,
+    /// The fields that changed, as they were before.
+    pub before: Option<serde_json::Value>,
+    /// The fields that changed, as they are after.
+    pub after: Option<serde_json::Value>,
+    pub request_id: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl From<AuditEventEntity> for AuditEvent {
+    fn from(entity: AuditEventEntity) -> Self {
+        Self {
+            id: entity.id,
+            actor_id: entity.actor_id,
+            action: entity.action,
+            entity_type: entity.entity_type,
+            entity_id: entity.entity_id,
+            before: entity.old_values,
+            after: entity.new_values,
+            request_id: entity.request_id,
+            created_at: entity.created_at,
+        }
+    }
+}
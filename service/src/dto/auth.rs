@@ -0,0 +1,75 @@
+use shared::validation::Violations;
+use shared::AppError;
+
+/// Shortest password accepted at registration.
+pub const PASSWORD_MIN_LENGTH: u64 = 8;
+/// Longest email `users.email` can hold.
+pub const EMAIL_MAX_LENGTH: u64 = 255;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Registration {
+    pub name: String,
+    pub email: String,
+    pub password: String,
+}
+
+impl Registration {
+    pub fn validate(&self) -> Result<(), AppError> {
+        let mut violations = Violations::new();
+        violations
+            .text("name", &self.name, crate::dto::user::NAME_MAX_LENGTH)
+            .text("email", &self.email, EMAIL_MAX_LENGTH)
+            .check(
+                self.email.contains('@'),
+                "email",
+                "email",
+                "must be an email address",
+            )
+            .check(
+                self.password.chars().count() as u64 >= PASSWORD_MIN_LENGTH,
+                "password",
+                "length",
+                &format!("must be at least {PASSWORD_MIN_LENGTH} characters"),
+            );
+        violations.into_result()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Login {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Lifetime of the access token in seconds.
+    pub expires_in: u64,
+}
+
+/// Role that bypasses ownership checks.
+pub const ADMIN_ROLE: &str = "admin";
+
+/// The authenticated caller of a service method.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Actor {
+    pub user_id: i32,
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+impl Actor {
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == permission)
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.has_role(ADMIN_ROLE)
+    }
+}
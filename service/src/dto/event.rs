@@ -0,0 +1,81 @@
+use repository::entity::outbox_event::OutboxEventEntity;
+use serde::{Deserialize, Serialize};
+
+/// Something that happened to the data, for other systems to react to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+pub enum DomainEvent {
+    UserCreated {
+        user_id: i32,
+        name: String,
+    },
+    UserRenamed {
+        user_id: i32,
+        old_name: String,
+        new_name: String,
+    },
+    /// Moved to the trash.
+    UserDeleted {
+        user_id: i32,
+    },
+    /// Brought back from the trash.
+    UserRestored {
+        user_id: i32,
+    },
+    MemoCreated {
+        memo_id: i32,
+        user_id: i32,
+        title: String,
+    },
+    MemoUpdated {
+        memo_id: i32,
+        user_id: i32,
+        title: String,
+    },
+    MemoDeleted {
+        memo_id: i32,
+        user_id: i32,
+    },
+}
+
+impl DomainEvent {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            DomainEvent::UserCreated { .. } => "UserCreated",
+            DomainEvent::UserRenamed { .. } => "UserRenamed",
+            DomainEvent::UserDeleted { .. } => "UserDeleted",
+            DomainEvent::UserRestored { .. } => "UserRestored",
+            DomainEvent::MemoCreated { .. } => "MemoCreated",
+            DomainEvent::MemoUpdated { .. } => "MemoUpdated",
+            DomainEvent::MemoDeleted { .. } => "MemoDeleted",
+        }
+    }
+}
+
+/// A [`DomainEvent`] as handed to sinks, e.g.
+/// `{"id": 7, "occurredAt": "...", "attempt": 1, "type": "UserDeleted", "userId": 2}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    /// The same on every delivery of the event, so that receivers can drop
+    /// duplicates.
+    pub id: i64,
+    pub occurred_at: chrono::NaiveDateTime,
+    /// 1 on the first delivery.
+    pub attempt: i32,
+    #[serde(flatten)]
+    pub event: DomainEvent,
+}
+
+impl TryFrom<OutboxEventEntity> for Envelope {
+    type Error = serde_json::Error;
+
+    fn try_from(entity: OutboxEventEntity) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: entity.id,
+            occurred_at: entity.created_at,
+            attempt: entity.attempts,
+            event: serde_json::from_value(entity.payload)?,
+        })
+    }
+}
@@ -0,0 +1,53 @@
+use repository::entity::memo::MemoEntity;
+use shared::validation::Violations;
+use shared::AppError;
+
+/// Longest title `memos.title` can hold.
+pub const TITLE_MAX_LENGTH: u64 = 255;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Memo {
+    pub id: i32,
+    pub user_id: i32,
+    pub title: String,
+    pub content: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: chrono::NaiveDateTime,
+}
+
+impl Memo {
+    /// Checks the invariants every stored memo must satisfy.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let mut violations = Violations::new();
+        violations
+            .check(self.user_id > 0, "userId", "range", "must be a positive id")
+            .text("title", &self.title, TITLE_MAX_LENGTH);
+        violations.into_result()
+    }
+}
+
+impl From<MemoEntity> for Memo {
+    fn from(entity: MemoEntity) -> Self {
+        Self {
+            id: entity.id,
+            user_id: entity.user_id,
+            title: entity.title,
+            content: entity.content,
+            created_at: entity.created_at,
+            updated_at: entity.updated_at,
+        }
+    }
+}
+
+impl From<Memo> for MemoEntity {
+    fn from(memo: Memo) -> Self {
+        Self {
+            id: memo.id,
+            user_id: memo.user_id,
+            title: memo.title,
+            content: memo.content,
+            created_at: memo.created_at,
+            updated_at: memo.updated_at,
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use json_patch::PatchErrorKind;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use shared::validation::FieldViolation;
+use shared::AppError;
+
+/// A partial update of a resource in one of the standard JSON formats.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Patch {
+    /// RFC 7396 JSON Merge Patch: members replace fields, `null` removes them.
+    Merge(Value),
+    /// RFC 6902 JSON Patch: a list of `add`, `remove`, `replace`, `move`,
+    /// `copy` and `test` operations.
+    Json(json_patch::Patch),
+}
+
+impl Patch {
+    /// Applies the patch to the JSON form of `target`.
+    ///
+    /// `T` should deny unknown fields, so that a patch introducing a field the
+    /// resource does not have is rejected rather than ignored.
+    pub fn apply<T: Serialize + DeserializeOwned>(&self, target: &T) -> Result<T, AppError> {
+        let mut document =
+            serde_json::to_value(target).map_err(|_| AppError::InternalServerError)?;
+        match self {
+            Patch::Merge(patch) => json_patch::merge(&mut document, patch),
+            Patch::Json(patch) => json_patch::patch(&mut document, patch).map_err(|err| {
+                let code = match err.kind {
+                    PatchErrorKind::TestFailed => "test_failed",
+                    _ => "path",
+                };
+                let message = format!("operation {} failed: {}", err.operation, err.kind);
+                AppError::Validation(vec![FieldViolation::new(
+                    err.path.to_string(),
+                    code,
+                    message,
+                )])
+            })?,
+        }
+        serde_path_to_error::deserialize(document).map_err(|err| {
+            let path = err.path().to_string();
+            AppError::Validation(vec![FieldViolation::from_json(&path, err.inner())])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Changes {
+        name: String,
+        note: Option<String>,
+    }
+
+    fn changes() -> Changes {
+        Changes {
+            name: "Alice".to_string(),
+            note: Some("hello".to_string()),
+        }
+    }
+
+    /// The field and code of the violation `patch` causes.
+    fn rejection(patch: Patch) -> (String, String) {
+        match patch.apply(&changes()) {
+            Err(AppError::Validation(violations)) => {
+                (violations[0].field.clone(), violations[0].code.clone())
+            }
+            other => panic!("expected a validation error, got {other:?}"),
+        }
+    }
+
+    fn pair(field: &str, code: &str) -> (String, String) {
+        (field.to_string(), code.to_string())
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        // given
+        let patch = Patch::Merge(json!({"note": null}));
+        // when
+        let patched = patch.apply(&changes()).unwrap();
+        // then
+        assert_eq!(
+            patched,
+            Changes {
+                name: "Alice".to_string(),
+                note: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_json_patch() {
+        // given
+        let patch: json_patch::Patch = serde_json::from_value(json!([
+            {"op": "test", "path": "/name", "value": "Alice"},
+            {"op": "replace", "path": "/name", "value": "Alicia"},
+        ]))
+        .unwrap();
+        // when
+        let patched = Patch::Json(patch).apply(&changes()).unwrap();
+        // then
+        assert_eq!(patched.name, "Alicia");
+        assert_eq!(patched.note, Some("hello".to_string()));
+    }
+
+    #[test]
+    fn test_rejected_patches() {
+        let json = |operations: Value| Patch::Json(serde_json::from_value(operations).unwrap());
+        assert_eq!(
+            rejection(Patch::Merge(json!({"id": 2}))),
+            pair("id", "unknown")
+        );
+        assert_eq!(
+            rejection(Patch::Merge(json!({"name": null}))),
+            pair("name", "required")
+        );
+        assert_eq!(
+            rejection(Patch::Merge(json!({"name": 1}))),
+            pair("name", "type")
+        );
+        assert_eq!(
+            rejection(json(json!([{"op": "add", "path": "/id", "value": 2}]))),
+            pair("id", "unknown")
+        );
+        assert_eq!(
+            rejection(json(
+                json!([{"op": "test", "path": "/name", "value": "Bob"}])
+            )),
+            pair("/name", "test_failed")
+        );
+        assert_eq!(
+            rejection(json(json!([{"op": "remove", "path": "/missing/deep"}]))),
+            pair("/missing/deep", "path")
+        );
+    }
+}
@@ -1,4 +1,10 @@
 use repository::entity::user::UserEntity;
+use serde::{Deserialize, Serialize};
+use shared::validation::Violations;
+use shared::AppError;
+
+/// Longest name `users.name` can hold.
+pub const NAME_MAX_LENGTH: u64 = 255;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct User {
@@ -6,6 +12,32 @@ pub struct User {
     pub name: String,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    pub version: i32,
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+impl User {
+    /// Checks the invariants every stored user must satisfy.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let mut violations = Violations::new();
+        violations.text("name", &self.name, NAME_MAX_LENGTH);
+        violations.into_result()
+    }
+}
+
+/// The fields of a user that a patch may change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UserChanges {
+    pub name: String,
+}
+
+impl From<&User> for UserChanges {
+    fn from(user: &User) -> Self {
+        Self {
+            name: user.name.clone(),
+        }
+    }
 }
 
 impl From<UserEntity> for User {
@@ -15,6 +47,8 @@ impl From<UserEntity> for User {
             name: entity.name,
             created_at: entity.created_at,
             updated_at: entity.updated_at,
+            version: entity.version,
+            deleted_at: entity.deleted_at,
         }
     }
 }
@@ -26,6 +60,8 @@ impl From<User> for UserEntity {
             name: user.name,
             created_at: user.created_at,
             updated_at: user.updated_at,
+            version: user.version,
+            deleted_at: user.deleted_at,
         }
     }
 }
@@ -1,6 +1,21 @@
 pub mod dto {
+    pub mod audit;
+    pub mod auth;
+    pub mod event;
+    pub mod memo;
+    pub mod patch;
     pub mod user;
 }
+mod metrics;
 pub mod service {
+    pub mod audit;
+    pub mod auth;
+    pub mod event;
+    pub mod memo;
+    pub mod policy;
+    pub mod role;
+    pub mod sink;
+    pub mod token;
+    pub mod transaction;
     pub mod user;
 }
@@ -0,0 +1,56 @@
+use shared::AppError;
+use std::future::Future;
+
+/// Runs the body of a service method, counting its failures in
+/// `service_errors_total` by service, method and error code.
+pub(crate) async fn metered<T>(
+    service: &'static str,
+    method: &'static str,
+    call: impl Future<Output = Result<T, AppError>>,
+) -> Result<T, AppError> {
+    let result = call.await;
+    if let Err(err) = &result {
+        metrics::counter!(
+            "service_errors_total",
+            "service" => service,
+            "method" => method,
+            "error" => err.code(),
+        )
+        .increment(1);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    #[tokio::test]
+    async fn test_metered_counts_errors() {
+        // given
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let _guard = metrics::set_default_local_recorder(&recorder);
+        // when
+        let _ = metered("user", "find_by_id", async { Ok(1) }).await;
+        let _ = metered("user", "find_by_id", async {
+            Err::<i32, _>(AppError::NotFound)
+        })
+        .await;
+        // then
+        let counters: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| (key.key().clone(), value))
+            .collect();
+        assert_eq!(counters.len(), 1);
+        let (key, value) = &counters[0];
+        assert_eq!(key.name(), "service_errors_total");
+        assert!(key
+            .labels()
+            .any(|label| label.key() == "error" && label.value() == "NOT_FOUND"));
+        assert_eq!(*value, DebugValue::Counter(1));
+    }
+}
@@ -0,0 +1,211 @@
+//! The audit log. Services record every change they make to an entity in
+//! the unit of work that makes it, so that a change is never kept without
+//! its event or the other way round.
+
+use crate::dto::audit::AuditEvent;
+use crate::dto::auth::Actor;
+use crate::metrics::metered;
+use repository::entity::audit_event::AuditEventEntity;
+use repository::repository::audit::{AuditQuery, AuditRepository};
+use repository::repository::unit_of_work::UnitOfWork;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use shared::query::Page;
+use shared::AppError;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    /// Permanently removed by the system.
+    Purge,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Restore => "restore",
+            AuditAction::Purge => "purge",
+        }
+    }
+}
+
+/// Records in `unit_of_work` that `actor`, or the system without one, did
+/// `action` to entity `entity_id` of `entity_type`, along with the request
+/// being served. Only the fields that differ between `before` and `after`
+/// are kept; either side is `None` where the entity did not exist.
+pub async fn record<T: Serialize>(
+    unit_of_work: &dyn UnitOfWork,
+    actor: Option<&Actor>,
+    action: AuditAction,
+    entity_type: &str,
+    entity_id: i32,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<(), AppError> {
+    let (old_values, new_values) = diff(snapshot(before)?, snapshot(after)?);
+    unit_of_work
+        .audit()
+        .record(AuditEventEntity {
+            id: 0,
+            actor_id: actor.map(|actor| actor.user_id),
+            action: action.as_str().to_string(),
+            entity_type: entity_type.to_string(),
+            entity_id,
+            old_values,
+            new_values,
+            request_id: shared::request_id::current(),
+            created_at: chrono::Utc::now().naive_utc(),
+        })
+        .await?;
+    Ok(())
+}
+
+fn snapshot<T: Serialize>(entity: Option<&T>) -> Result<Option<Value>, AppError> {
+    entity.map(serde_json::to_value).transpose().map_err(|err| {
+        tracing::error!(error = %err, "failed to serialize an audited entity");
+        AppError::InternalServerError
+    })
+}
+
+/// Leaves out the fields `before` and `after` agree on.
+fn diff(before: Option<Value>, after: Option<Value>) -> (Option<Value>, Option<Value>) {
+    let (Some(Value::Object(before)), Some(Value::Object(after))) = (&before, &after) else {
+        return (before, after);
+    };
+    let changed = |from: &Map<String, Value>, to: &Map<String, Value>| -> Map<String, Value> {
+        from.iter()
+            .filter(|(key, value)| to.get(*key) != Some(value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    };
+    (
+        Some(Value::Object(changed(before, after))),
+        Some(Value::Object(changed(after, before))),
+    )
+}
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait AuditService: Send + Sync {
+    /// Lists recorded changes, optionally of one type of entity or one
+    /// entity; an entity id alone is rejected as ambiguous.
+    async fn get_events(&self, query: AuditQuery) -> Result<Page<AuditEvent>, AppError>;
+}
+
+#[derive(Clone)]
+pub struct AuditServiceImpl {
+    audit_repository: Arc<dyn AuditRepository>,
+}
+
+impl AuditServiceImpl {
+    pub fn new(audit_repository: Arc<dyn AuditRepository>) -> Self {
+        Self { audit_repository }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditService for AuditServiceImpl {
+    async fn get_events(&self, query: AuditQuery) -> Result<Page<AuditEvent>, AppError> {
+        metered("audit", "get_events", async {
+            if query.filter.entity_id.is_some() && query.filter.entity_type.is_none() {
+                return Err(AppError::BadRequest(
+                    "id requires entity to be given".to_string(),
+                ));
+            }
+            self.audit_repository
+                .get_events(&query)
+                .await
+                .map(|page| page.map(AuditEvent::from))
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use repository::repository::audit::{AuditFilter, MockAuditRepository};
+    use repository::repository::unit_of_work::MockUnitOfWork;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_keeps_changed_fields() {
+        // given
+        let before = json!({ "id": 1, "name": "Bob", "version": 1 });
+        let after = json!({ "id": 1, "name": "Robert", "version": 2 });
+        // when
+        let (old, new) = diff(Some(before.clone()), Some(after.clone()));
+        let (created_old, created_new) = diff(None, Some(after.clone()));
+        // then
+        assert_eq!(old, Some(json!({ "name": "Bob", "version": 1 })));
+        assert_eq!(new, Some(json!({ "name": "Robert", "version": 2 })));
+        assert_eq!(created_old, None);
+        assert_eq!(created_new, Some(after));
+    }
+
+    #[tokio::test]
+    async fn test_record_stamps_actor_and_request() {
+        // given
+        let mut audit = MockAuditRepository::new();
+        audit
+            .expect_record()
+            .withf(|event| {
+                event.actor_id == Some(7)
+                    && event.action == "delete"
+                    && event.entity_type == "users"
+                    && event.entity_id == 2
+                    && event.old_values == Some(json!({ "name": "Bob" }))
+                    && event.new_values.is_none()
+                    && event.request_id.as_deref() == Some("abc-123")
+            })
+            .times(1)
+            .returning(Ok);
+        let audit = Arc::new(audit);
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work.expect_audit().returning(move || audit.clone());
+        let actor = Actor {
+            user_id: 7,
+            roles: vec![],
+            permissions: vec![],
+        };
+        // when
+        let result = shared::request_id::scope(
+            "abc-123".to_string(),
+            record(
+                &unit_of_work,
+                Some(&actor),
+                AuditAction::Delete,
+                "users",
+                2,
+                Some(&json!({ "name": "Bob" })),
+                None,
+            ),
+        )
+        .await;
+        // then
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_get_events_by_id_alone_is_rejected() {
+        // given
+        let service = AuditServiceImpl::new(Arc::new(MockAuditRepository::new()));
+        let filter = AuditFilter {
+            entity_type: None,
+            entity_id: Some(1),
+        };
+        // when
+        let result = service
+            .get_events(AuditQuery::new(filter, None, None, None).unwrap())
+            .await;
+        // then
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+}
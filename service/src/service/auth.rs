@@ -0,0 +1,727 @@
+use crate::dto::auth::{Actor, Login, Registration, TokenPair};
+use crate::dto::event::DomainEvent;
+use crate::dto::user::User;
+use crate::metrics::metered;
+use crate::service::audit::{self, AuditAction};
+use crate::service::event;
+use crate::service::token::{hash_refresh_token, TokenIssuer};
+use crate::service::transaction::transaction;
+use crate::service::user::ENTITY_TYPE;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand_core::OsRng;
+use repository::entity::credential::CredentialEntity;
+use repository::entity::refresh_token::RefreshTokenEntity;
+use repository::entity::user::UserEntity;
+use repository::repository::credential::CredentialRepository;
+use repository::repository::refresh_token::RefreshTokenRepository;
+use repository::repository::role::RoleRepository;
+use repository::repository::unit_of_work::TransactionManager;
+use shared::AppError;
+use std::sync::{Arc, OnceLock};
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait AuthService: Send + Sync {
+    async fn register(&self, registration: Registration) -> Result<User, AppError>;
+    /// Issues tokens for valid credentials, or fails with `AppError::Unauthorized`.
+    async fn login(&self, login: Login) -> Result<TokenPair, AppError>;
+    /// Exchanges a refresh token for a new pair, revoking the one presented.
+    async fn refresh(&self, refresh_token: String) -> Result<TokenPair, AppError>;
+    async fn logout(&self, refresh_token: String) -> Result<(), AppError>;
+    /// Resolves the caller behind an access token, with the roles and
+    /// permissions they hold now rather than those the token was issued with.
+    async fn authenticate(&self, access_token: &str) -> Result<Actor, AppError>;
+}
+
+#[derive(Clone)]
+pub struct AuthServiceImpl {
+    credential_repository: Arc<dyn CredentialRepository>,
+    refresh_token_repository: Arc<dyn RefreshTokenRepository>,
+    role_repository: Arc<dyn RoleRepository>,
+    transactions: Arc<dyn TransactionManager>,
+    token_issuer: TokenIssuer,
+}
+
+impl AuthServiceImpl {
+    pub fn new(
+        credential_repository: Arc<dyn CredentialRepository>,
+        refresh_token_repository: Arc<dyn RefreshTokenRepository>,
+        role_repository: Arc<dyn RoleRepository>,
+        transactions: Arc<dyn TransactionManager>,
+        token_issuer: TokenIssuer,
+    ) -> Self {
+        Self {
+            credential_repository,
+            refresh_token_repository,
+            role_repository,
+            transactions,
+            token_issuer,
+        }
+    }
+
+    /// Loads the roles and permissions the user holds right now.
+    async fn actor(&self, user_id: i32) -> Result<Actor, AppError> {
+        Ok(Actor {
+            user_id,
+            roles: self.role_repository.find_roles_for_user(user_id).await?,
+            permissions: self
+                .role_repository
+                .find_permissions_for_user(user_id)
+                .await?,
+        })
+    }
+
+    async fn issue_tokens(&self, actor: Actor) -> Result<TokenPair, AppError> {
+        let access_token = self.token_issuer.issue_access_token(&actor)?;
+        let (refresh_token, token_hash) = self.token_issuer.generate_refresh_token();
+        let now = chrono::Utc::now().naive_utc();
+        self.refresh_token_repository
+            .create(RefreshTokenEntity {
+                id: 0,
+                user_id: actor.user_id,
+                token_hash,
+                expires_at: now + self.token_issuer.refresh_token_ttl(),
+                revoked_at: None,
+                created_at: now,
+            })
+            .await?;
+        Ok(TokenPair {
+            access_token,
+            refresh_token,
+            expires_in: self.token_issuer.access_token_ttl().as_secs(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthService for AuthServiceImpl {
+    async fn register(&self, registration: Registration) -> Result<User, AppError> {
+        metered("auth", "register", async {
+            registration.validate()?;
+            let password_hash = hash_password(registration.password).await?;
+            let now = chrono::Utc::now().naive_utc();
+            transaction(&*self.transactions, |tx| async move {
+                let created = tx
+                    .credentials()
+                    .create_user(
+                        UserEntity {
+                            id: 0,
+                            name: registration.name,
+                            version: 1,
+                            deleted_at: None,
+                            created_at: now,
+                            updated_at: now,
+                        },
+                        CredentialEntity {
+                            user_id: 0,
+                            email: normalize_email(&registration.email),
+                            password_hash,
+                        },
+                    )
+                    .await?;
+                // the new user is the one acting
+                let registrant = Actor {
+                    user_id: created.id,
+                    roles: vec![],
+                    permissions: vec![],
+                };
+                audit::record(
+                    &*tx,
+                    Some(&registrant),
+                    AuditAction::Create,
+                    ENTITY_TYPE,
+                    created.id,
+                    None,
+                    Some(&created),
+                )
+                .await?;
+                let event = DomainEvent::UserCreated {
+                    user_id: created.id,
+                    name: created.name.clone(),
+                };
+                event::emit(&*tx, event).await?;
+                Ok(User::from(created))
+            })
+            .await
+        })
+        .await
+    }
+
+    async fn login(&self, login: Login) -> Result<TokenPair, AppError> {
+        metered("auth", "login", async {
+            let credential = self
+                .credential_repository
+                .find_by_email(&normalize_email(&login.email))
+                .await?;
+            // verify against a dummy hash for unknown emails so both paths take as long
+            let password_hash = credential
+                .as_ref()
+                .map(|credential| credential.password_hash.clone());
+            let verified = tokio::task::spawn_blocking(move || {
+                let password_hash = password_hash.as_deref().unwrap_or_else(|| dummy_hash());
+                verify_password_blocking(&login.password, password_hash)
+            })
+            .await
+            .map_err(|_| AppError::InternalServerError)??;
+            match credential {
+                Some(credential) if verified => {
+                    let actor = self.actor(credential.user_id).await?;
+                    self.issue_tokens(actor).await
+                }
+                _ => Err(AppError::Unauthorized),
+            }
+        })
+        .await
+    }
+
+    async fn refresh(&self, refresh_token: String) -> Result<TokenPair, AppError> {
+        metered("auth", "refresh", async {
+            let token = self
+                .refresh_token_repository
+                .find_by_hash(&hash_refresh_token(&refresh_token))
+                .await?
+                .ok_or(AppError::Unauthorized)?;
+            // a revoked token coming back means it leaked: end every session of the user
+            if token.revoked_at.is_some() || !self.refresh_token_repository.revoke(token.id).await?
+            {
+                self.refresh_token_repository
+                    .revoke_all_for_user(token.user_id)
+                    .await?;
+                return Err(AppError::Unauthorized);
+            }
+            if token.expires_at <= chrono::Utc::now().naive_utc() {
+                return Err(AppError::Unauthorized);
+            }
+            // roles are read again so that grants and revocations apply from the next refresh
+            let actor = self.actor(token.user_id).await?;
+            self.issue_tokens(actor).await
+        })
+        .await
+    }
+
+    async fn logout(&self, refresh_token: String) -> Result<(), AppError> {
+        metered("auth", "logout", async {
+            if let Some(token) = self
+                .refresh_token_repository
+                .find_by_hash(&hash_refresh_token(&refresh_token))
+                .await?
+            {
+                self.refresh_token_repository.revoke(token.id).await?;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn authenticate(&self, access_token: &str) -> Result<Actor, AppError> {
+        let claimed = self.token_issuer.verify_access_token(access_token)?;
+        // revoked roles must not outlive the token they were issued in
+        self.actor(claimed.user_id).await
+    }
+}
+
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+fn dummy_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| hash_password_blocking("dummy-password").unwrap())
+}
+
+fn hash_password_blocking(password: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|_| AppError::InternalServerError)
+}
+
+/// Hashes `password` with Argon2id off the async runtime.
+pub async fn hash_password(password: String) -> Result<String, AppError> {
+    tokio::task::spawn_blocking(move || hash_password_blocking(&password))
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+}
+
+fn verify_password_blocking(password: &str, password_hash: &str) -> Result<bool, AppError> {
+    let parsed = PasswordHash::new(password_hash).map_err(|_| AppError::InternalServerError)?;
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::transaction::mock_transactions;
+    use repository::repository::{
+        audit::MockAuditRepository,
+        credential::MockCredentialRepository,
+        outbox::MockOutboxRepository,
+        refresh_token::MockRefreshTokenRepository,
+        role::MockRoleRepository,
+        unit_of_work::{MockTransactionManager, MockUnitOfWork},
+    };
+    use shared::settings::AuthSettings;
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn timestamp() -> chrono::NaiveDateTime {
+        chrono::NaiveDateTime::parse_from_str("2021-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn token_issuer() -> TokenIssuer {
+        TokenIssuer::new(&AuthSettings {
+            keys: HashMap::from([(
+                "test".to_string(),
+                "a-test-secret-that-is-long-enough-0000".to_string(),
+            )]),
+            active_kid: "test".to_string(),
+            ..AuthSettings::default()
+        })
+    }
+
+    /// Every user is a member who may write memos.
+    fn member_role_repository() -> MockRoleRepository {
+        let mut mock_role_repository = MockRoleRepository::new();
+        mock_role_repository
+            .expect_find_roles_for_user()
+            .returning(|_| Ok(vec!["member".to_string()]));
+        mock_role_repository
+            .expect_find_permissions_for_user()
+            .returning(|_| Ok(vec!["memos:write".to_string()]));
+        mock_role_repository
+    }
+
+    fn member(user_id: i32) -> Actor {
+        Actor {
+            user_id,
+            roles: vec!["member".to_string()],
+            permissions: vec!["memos:write".to_string()],
+        }
+    }
+
+    fn auth_service(
+        credential_repository: MockCredentialRepository,
+        refresh_token_repository: MockRefreshTokenRepository,
+    ) -> AuthServiceImpl {
+        AuthServiceImpl::new(
+            Arc::new(credential_repository),
+            Arc::new(refresh_token_repository),
+            Arc::new(member_role_repository()),
+            Arc::new(MockTransactionManager::new()),
+            token_issuer(),
+        )
+    }
+
+    /// A service registering through a unit of work on `credentials`,
+    /// `audit` and `outbox`, which is committed once when `committed`, else
+    /// rolled back.
+    fn registering_auth_service(
+        credentials: MockCredentialRepository,
+        audit: MockAuditRepository,
+        outbox: MockOutboxRepository,
+        committed: bool,
+    ) -> AuthServiceImpl {
+        let (credentials, audit, outbox) =
+            (Arc::new(credentials), Arc::new(audit), Arc::new(outbox));
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work
+            .expect_credentials()
+            .returning(move || credentials.clone());
+        unit_of_work.expect_audit().returning(move || audit.clone());
+        unit_of_work
+            .expect_outbox()
+            .returning(move || outbox.clone());
+        unit_of_work
+            .expect_commit()
+            .times(committed as usize)
+            .returning(|| Ok(()));
+        unit_of_work
+            .expect_rollback()
+            .times(!committed as usize)
+            .returning(|| Ok(()));
+        AuthServiceImpl::new(
+            Arc::new(MockCredentialRepository::new()),
+            Arc::new(MockRefreshTokenRepository::new()),
+            Arc::new(member_role_repository()),
+            Arc::new(mock_transactions(unit_of_work)),
+            token_issuer(),
+        )
+    }
+
+    fn refresh_token_entity(
+        token: &str,
+        expires_at: chrono::NaiveDateTime,
+        revoked_at: Option<chrono::NaiveDateTime>,
+    ) -> RefreshTokenEntity {
+        RefreshTokenEntity {
+            id: 10,
+            user_id: 1,
+            token_hash: hash_refresh_token(token),
+            expires_at,
+            revoked_at,
+            created_at: timestamp(),
+        }
+    }
+
+    fn expect_token_created(mock_refresh_token_repository: &mut MockRefreshTokenRepository) {
+        mock_refresh_token_repository
+            .expect_create()
+            .withf(|token| token.user_id == 1 && token.token_hash.len() == 64)
+            .times(1)
+            .returning(|token| Ok(RefreshTokenEntity { id: 11, ..token }));
+    }
+
+    #[tokio::test]
+    async fn test_hash_and_verify_password() {
+        // when
+        let hash = hash_password("secret-password".to_string()).await.unwrap();
+        // then
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_password_blocking("secret-password", &hash).unwrap());
+        assert!(!verify_password_blocking("wrong-password", &hash).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_register() {
+        // given
+        let mut mock_credential_repository = MockCredentialRepository::new();
+        mock_credential_repository
+            .expect_create_user()
+            .withf(|user, credential| {
+                user.name == "Alice"
+                    && credential.email == "alice@example.com"
+                    && credential.password_hash.starts_with("$argon2id$")
+            })
+            .returning(|user, _| {
+                Ok(UserEntity {
+                    id: 1,
+                    created_at: timestamp(),
+                    updated_at: timestamp(),
+                    ..user
+                })
+            });
+        let mut mock_audit_repository = MockAuditRepository::new();
+        mock_audit_repository
+            .expect_record()
+            .withf(|event| {
+                event.action == "create"
+                    && event.entity_type == "users"
+                    && event.entity_id == 1
+                    && event.actor_id == Some(1)
+            })
+            .times(1)
+            .returning(Ok);
+        let mut mock_outbox_repository = MockOutboxRepository::new();
+        mock_outbox_repository
+            .expect_append()
+            .withf(|event| {
+                event.event_type == "UserCreated"
+                    && event.payload
+                        == serde_json::json!({"type": "UserCreated", "userId": 1, "name": "Alice"})
+            })
+            .times(1)
+            .returning(Ok);
+        let auth_service = registering_auth_service(
+            mock_credential_repository,
+            mock_audit_repository,
+            mock_outbox_repository,
+            true,
+        );
+        // when
+        let user = auth_service
+            .register(Registration {
+                name: "Alice".to_string(),
+                email: " Alice@Example.com ".to_string(),
+                password: "secret-password".to_string(),
+            })
+            .await
+            .unwrap();
+        // then
+        assert_eq!(user.id, 1);
+        assert_eq!(user.name, "Alice");
+    }
+
+    #[tokio::test]
+    async fn test_register_short_password_is_rejected() {
+        // given
+        let auth_service = auth_service(
+            MockCredentialRepository::new(),
+            MockRefreshTokenRepository::new(),
+        );
+        // when
+        let result = auth_service
+            .register(Registration {
+                name: "Alice".to_string(),
+                email: "alice@example.com".to_string(),
+                password: "short".to_string(),
+            })
+            .await;
+        // then
+        let Err(AppError::Validation(violations)) = result else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].field, "password");
+    }
+
+    #[tokio::test]
+    async fn test_register_duplicate_email() {
+        // given
+        let mut mock_credential_repository = MockCredentialRepository::new();
+        mock_credential_repository
+            .expect_create_user()
+            .returning(|_, _| Err(AppError::Conflict));
+        let mut mock_audit_repository = MockAuditRepository::new();
+        mock_audit_repository.expect_record().never();
+        let mut mock_outbox_repository = MockOutboxRepository::new();
+        mock_outbox_repository.expect_append().never();
+        let auth_service = registering_auth_service(
+            mock_credential_repository,
+            mock_audit_repository,
+            mock_outbox_repository,
+            false,
+        );
+        // when
+        let result = auth_service
+            .register(Registration {
+                name: "Alice".to_string(),
+                email: "alice@example.com".to_string(),
+                password: "secret-password".to_string(),
+            })
+            .await;
+        // then
+        assert!(matches!(result, Err(AppError::Conflict)));
+    }
+
+    #[tokio::test]
+    async fn test_login() {
+        // given
+        let password_hash = hash_password("secret-password".to_string()).await.unwrap();
+        let mut mock_credential_repository = MockCredentialRepository::new();
+        mock_credential_repository
+            .expect_find_by_email()
+            .withf(|email| email == "alice@example.com")
+            .returning(move |email| {
+                Ok(Some(CredentialEntity {
+                    user_id: 1,
+                    email: email.to_string(),
+                    password_hash: password_hash.clone(),
+                }))
+            });
+        let mut mock_refresh_token_repository = MockRefreshTokenRepository::new();
+        expect_token_created(&mut mock_refresh_token_repository);
+        let auth_service = auth_service(mock_credential_repository, mock_refresh_token_repository);
+        // when
+        let tokens = auth_service
+            .login(Login {
+                email: "alice@example.com".to_string(),
+                password: "secret-password".to_string(),
+            })
+            .await
+            .unwrap();
+        // then
+        assert_eq!(tokens.expires_in, 900);
+        assert_eq!(
+            auth_service
+                .authenticate(&tokens.access_token)
+                .await
+                .unwrap(),
+            member(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_login_wrong_password() {
+        // given
+        let password_hash = hash_password("secret-password".to_string()).await.unwrap();
+        let mut mock_credential_repository = MockCredentialRepository::new();
+        mock_credential_repository
+            .expect_find_by_email()
+            .returning(move |email| {
+                Ok(Some(CredentialEntity {
+                    user_id: 1,
+                    email: email.to_string(),
+                    password_hash: password_hash.clone(),
+                }))
+            });
+        let auth_service = auth_service(
+            mock_credential_repository,
+            MockRefreshTokenRepository::new(),
+        );
+        // when
+        let result = auth_service
+            .login(Login {
+                email: "alice@example.com".to_string(),
+                password: "wrong-password".to_string(),
+            })
+            .await;
+        // then
+        assert!(matches!(result, Err(AppError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_login_unknown_email() {
+        // given
+        let mut mock_credential_repository = MockCredentialRepository::new();
+        mock_credential_repository
+            .expect_find_by_email()
+            .returning(|_| Ok(None));
+        let auth_service = auth_service(
+            mock_credential_repository,
+            MockRefreshTokenRepository::new(),
+        );
+        // when
+        let result = auth_service
+            .login(Login {
+                email: "nobody@example.com".to_string(),
+                password: "secret-password".to_string(),
+            })
+            .await;
+        // then
+        assert!(matches!(result, Err(AppError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_rotates_token() {
+        // given
+        let expires_at = chrono::Utc::now().naive_utc() + chrono::Duration::days(1);
+        let mut mock_refresh_token_repository = MockRefreshTokenRepository::new();
+        mock_refresh_token_repository
+            .expect_find_by_hash()
+            .withf(|hash| hash == hash_refresh_token("old-token"))
+            .returning(move |_| Ok(Some(refresh_token_entity("old-token", expires_at, None))));
+        mock_refresh_token_repository
+            .expect_revoke()
+            .withf(|id| *id == 10)
+            .times(1)
+            .returning(|_| Ok(true));
+        expect_token_created(&mut mock_refresh_token_repository);
+        let auth_service = auth_service(
+            MockCredentialRepository::new(),
+            mock_refresh_token_repository,
+        );
+        // when
+        let tokens = auth_service.refresh("old-token".to_string()).await.unwrap();
+        // then
+        assert_ne!(tokens.refresh_token, "old-token");
+        assert_eq!(
+            auth_service
+                .authenticate(&tokens.access_token)
+                .await
+                .unwrap(),
+            member(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_reads_current_roles() {
+        // given
+        let auth_service = auth_service(
+            MockCredentialRepository::new(),
+            MockRefreshTokenRepository::new(),
+        );
+        let access_token = token_issuer()
+            .issue_access_token(&Actor {
+                user_id: 1,
+                roles: vec!["admin".to_string()],
+                permissions: vec!["users:delete".to_string()],
+            })
+            .unwrap();
+        // when
+        let actor = auth_service.authenticate(&access_token).await.unwrap();
+        // then
+        assert_eq!(actor, member(1));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_with_revoked_token_revokes_all() {
+        // given
+        let expires_at = chrono::Utc::now().naive_utc() + chrono::Duration::days(1);
+        let mut mock_refresh_token_repository = MockRefreshTokenRepository::new();
+        mock_refresh_token_repository
+            .expect_find_by_hash()
+            .returning(move |_| {
+                Ok(Some(refresh_token_entity(
+                    "old-token",
+                    expires_at,
+                    Some(timestamp()),
+                )))
+            });
+        mock_refresh_token_repository
+            .expect_revoke_all_for_user()
+            .withf(|user_id| *user_id == 1)
+            .times(1)
+            .returning(|_| Ok(()));
+        let auth_service = auth_service(
+            MockCredentialRepository::new(),
+            mock_refresh_token_repository,
+        );
+        // when
+        let result = auth_service.refresh("old-token".to_string()).await;
+        // then
+        assert!(matches!(result, Err(AppError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_with_expired_token() {
+        // given
+        let mut mock_refresh_token_repository = MockRefreshTokenRepository::new();
+        mock_refresh_token_repository
+            .expect_find_by_hash()
+            .returning(|_| Ok(Some(refresh_token_entity("old-token", timestamp(), None))));
+        mock_refresh_token_repository
+            .expect_revoke()
+            .returning(|_| Ok(true));
+        let auth_service = auth_service(
+            MockCredentialRepository::new(),
+            mock_refresh_token_repository,
+        );
+        // when
+        let result = auth_service.refresh("old-token".to_string()).await;
+        // then
+        assert!(matches!(result, Err(AppError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_with_unknown_token() {
+        // given
+        let mut mock_refresh_token_repository = MockRefreshTokenRepository::new();
+        mock_refresh_token_repository
+            .expect_find_by_hash()
+            .returning(|_| Ok(None));
+        let auth_service = auth_service(
+            MockCredentialRepository::new(),
+            mock_refresh_token_repository,
+        );
+        // when
+        let result = auth_service.refresh("unknown".to_string()).await;
+        // then
+        assert!(matches!(result, Err(AppError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_logout() {
+        // given
+        let mut mock_refresh_token_repository = MockRefreshTokenRepository::new();
+        mock_refresh_token_repository
+            .expect_find_by_hash()
+            .returning(|_| Ok(Some(refresh_token_entity("token", timestamp(), None))));
+        mock_refresh_token_repository
+            .expect_revoke()
+            .withf(|id| *id == 10)
+            .times(1)
+            .returning(|_| Ok(true));
+        let auth_service = auth_service(
+            MockCredentialRepository::new(),
+            mock_refresh_token_repository,
+        );
+        // when
+        let result = auth_service.logout("token".to_string()).await;
+        // then
+        assert!(result.is_ok());
+    }
+}
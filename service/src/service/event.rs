@@ -0,0 +1,313 @@
+//! Domain events. Services emit them into the outbox in the unit of work of
+//! the change they describe, so that an event is kept exactly when its
+//! change is. [`EventServiceImpl`] then delivers them to every
+//! [`EventSink`] at least once: a failed delivery is retried, with every
+//! sink, until it succeeds.
+
+use crate::dto::event::{DomainEvent, Envelope};
+use crate::metrics::metered;
+use crate::service::sink::EventSink;
+use repository::entity::outbox_event::OutboxEventEntity;
+use repository::repository::outbox::OutboxRepository;
+use repository::repository::unit_of_work::UnitOfWork;
+use shared::settings::EventSettings;
+use shared::AppError;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Adds `event` to the outbox of `unit_of_work`.
+pub async fn emit(unit_of_work: &dyn UnitOfWork, event: DomainEvent) -> Result<(), AppError> {
+    let payload = serde_json::to_value(&event).map_err(|err| {
+        tracing::error!(error = %err, "failed to serialize a domain event");
+        AppError::InternalServerError
+    })?;
+    let now = chrono::Utc::now().naive_utc();
+    unit_of_work
+        .outbox()
+        .append(OutboxEventEntity {
+            id: 0,
+            event_type: event.event_type().to_string(),
+            payload,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+        })
+        .await?;
+    Ok(())
+}
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait EventService: Send + Sync {
+    /// Delivers the events that are due, returning how many got through.
+    async fn dispatch(&self) -> Result<usize, AppError>;
+}
+
+#[derive(Clone)]
+pub struct EventServiceImpl {
+    outbox: Arc<dyn OutboxRepository>,
+    sinks: Vec<Arc<dyn EventSink>>,
+    batch_size: i64,
+    lease: Duration,
+    retry_base: Duration,
+    retry_max: Duration,
+}
+
+impl EventServiceImpl {
+    pub fn new(
+        outbox: Arc<dyn OutboxRepository>,
+        sinks: Vec<Arc<dyn EventSink>>,
+        settings: &EventSettings,
+    ) -> Self {
+        Self {
+            outbox,
+            sinks,
+            batch_size: settings.batch_size.into(),
+            lease: settings.lease(),
+            retry_base: settings.retry_base(),
+            retry_max: settings.retry_max(),
+        }
+    }
+
+    /// Hands `event` to each sink in turn, stopping at the first failure.
+    async fn deliver(&self, event: OutboxEventEntity) -> Result<(), String> {
+        let envelope = Envelope::try_from(event).map_err(|err| err.to_string())?;
+        for sink in &self.sinks {
+            sink.publish(&envelope)
+                .await
+                .map_err(|err| format!("{}: {err}", sink.name()))?;
+        }
+        Ok(())
+    }
+
+    /// How long to wait after the `attempts`th failure: the base delay,
+    /// doubled for every earlier failure, up to the maximum.
+    fn backoff(&self, attempts: i32) -> Duration {
+        let doublings = attempts.saturating_sub(1).clamp(0, 30) as u32;
+        self.retry_base
+            .saturating_mul(1 << doublings)
+            .min(self.retry_max)
+    }
+}
+
+#[async_trait::async_trait]
+impl EventService for EventServiceImpl {
+    async fn dispatch(&self) -> Result<usize, AppError> {
+        metered("event", "dispatch", async {
+            let now = chrono::Utc::now().naive_utc();
+            let lease = chrono::Duration::from_std(self.lease)
+                .map_err(|_| AppError::InternalServerError)?;
+            let events = self.outbox.claim(now, now + lease, self.batch_size).await?;
+            let mut delivered = 0;
+            for event in events {
+                let (id, event_type, attempts) =
+                    (event.id, event.event_type.clone(), event.attempts);
+                match self.deliver(event).await {
+                    Ok(()) => {
+                        self.outbox.complete(id).await?;
+                        delivered += 1;
+                        metrics::counter!("events_delivered_total", "type" => event_type)
+                            .increment(1);
+                    }
+                    Err(err) => {
+                        let delay = self.backoff(attempts);
+                        tracing::warn!(
+                            event_id = id,
+                            event_type,
+                            attempts,
+                            retry_in = ?delay,
+                            error = %err,
+                            "delivering a domain event failed"
+                        );
+                        metrics::counter!("events_failed_total", "type" => event_type).increment(1);
+                        let retry_at = chrono::Utc::now().naive_utc()
+                            + chrono::Duration::from_std(delay)
+                                .map_err(|_| AppError::InternalServerError)?;
+                        self.outbox.retry(id, retry_at, &err).await?;
+                    }
+                }
+            }
+            Ok(delivered)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::sink::MockEventSink;
+    use repository::repository::outbox::MockOutboxRepository;
+    use repository::repository::unit_of_work::MockUnitOfWork;
+    use serde_json::json;
+
+    fn settings() -> EventSettings {
+        EventSettings {
+            retry_base_ms: 1000,
+            retry_max_secs: 5,
+            ..EventSettings::default()
+        }
+    }
+
+    fn outbox_event(id: i64, attempts: i32, event: DomainEvent) -> OutboxEventEntity {
+        let now = chrono::Utc::now().naive_utc();
+        OutboxEventEntity {
+            id,
+            event_type: event.event_type().to_string(),
+            payload: serde_json::to_value(&event).unwrap(),
+            attempts,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_emit_appends_to_the_outbox() {
+        // given
+        let mut outbox = MockOutboxRepository::new();
+        outbox
+            .expect_append()
+            .withf(|event| {
+                event.event_type == "UserRenamed"
+                    && event.payload
+                        == json!({
+                            "type": "UserRenamed",
+                            "userId": 1,
+                            "oldName": "Alice",
+                            "newName": "Alicia",
+                        })
+            })
+            .times(1)
+            .returning(Ok);
+        let outbox = Arc::new(outbox);
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work
+            .expect_outbox()
+            .returning(move || outbox.clone());
+        // when
+        let result = emit(
+            &unit_of_work,
+            DomainEvent::UserRenamed {
+                user_id: 1,
+                old_name: "Alice".to_string(),
+                new_name: "Alicia".to_string(),
+            },
+        )
+        .await;
+        // then
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_emit_memo_events() {
+        // given
+        let mut outbox = MockOutboxRepository::new();
+        for (event_type, payload) in [
+            (
+                "MemoCreated",
+                json!({"type": "MemoCreated", "memoId": 3, "userId": 1, "title": "Todo"}),
+            ),
+            (
+                "MemoUpdated",
+                json!({"type": "MemoUpdated", "memoId": 3, "userId": 1, "title": "Done"}),
+            ),
+            (
+                "MemoDeleted",
+                json!({"type": "MemoDeleted", "memoId": 3, "userId": 1}),
+            ),
+        ] {
+            outbox
+                .expect_append()
+                .withf(move |event| event.event_type == event_type && event.payload == payload)
+                .times(1)
+                .returning(Ok);
+        }
+        let outbox = Arc::new(outbox);
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work
+            .expect_outbox()
+            .returning(move || outbox.clone());
+        let events = [
+            DomainEvent::MemoCreated {
+                memo_id: 3,
+                user_id: 1,
+                title: "Todo".to_string(),
+            },
+            DomainEvent::MemoUpdated {
+                memo_id: 3,
+                user_id: 1,
+                title: "Done".to_string(),
+            },
+            DomainEvent::MemoDeleted {
+                memo_id: 3,
+                user_id: 1,
+            },
+        ];
+        // when
+        let mut results = vec![];
+        for event in events {
+            results.push(emit(&unit_of_work, event).await);
+        }
+        // then
+        assert!(results.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_completes_delivered_and_retries_failed_events() {
+        // given
+        let mut outbox = MockOutboxRepository::new();
+        outbox.expect_claim().times(1).returning(|_, _, _| {
+            Ok(vec![
+                outbox_event(1, 1, DomainEvent::UserDeleted { user_id: 2 }),
+                outbox_event(2, 3, DomainEvent::UserDeleted { user_id: 3 }),
+            ])
+        });
+        outbox
+            .expect_complete()
+            .withf(|id| *id == 1)
+            .times(1)
+            .returning(|_| Ok(()));
+        outbox
+            .expect_retry()
+            .withf(|id, retry_at, error| {
+                // the third failure waits four times the base delay
+                let delay = *retry_at - chrono::Utc::now().naive_utc();
+                *id == 2
+                    && (3..=4).contains(&delay.num_seconds())
+                    && error == "recorder: unavailable"
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let mut sink = MockEventSink::new();
+        sink.expect_name().return_const("recorder");
+        sink.expect_publish()
+            .withf(|envelope| envelope.id == 1 && envelope.attempt == 1)
+            .times(1)
+            .returning(|_| Ok(()));
+        sink.expect_publish()
+            .withf(|envelope| envelope.id == 2)
+            .times(1)
+            .returning(|_| Err("unavailable".to_string()));
+        let service = EventServiceImpl::new(Arc::new(outbox), vec![Arc::new(sink)], &settings());
+        // when
+        let delivered = service.dispatch().await.unwrap();
+        // then
+        assert_eq!(delivered, 1);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_maximum() {
+        // given
+        let service =
+            EventServiceImpl::new(Arc::new(MockOutboxRepository::new()), vec![], &settings());
+        // when
+        let delays: Vec<u64> = [1, 2, 3, 4, 100]
+            .into_iter()
+            .map(|attempts| service.backoff(attempts).as_secs())
+            .collect();
+        // then
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+    }
+}
@@ -0,0 +1,549 @@
+use crate::dto::auth::Actor;
+use crate::dto::event::DomainEvent;
+use crate::dto::memo::Memo;
+use crate::metrics::metered;
+use crate::service::audit::{self, AuditAction};
+use crate::service::event;
+use crate::service::policy::Policy;
+use crate::service::transaction::transaction;
+use repository::entity::memo::MemoEntity;
+use repository::repository::memo::MemoRepository;
+use repository::repository::unit_of_work::{TransactionManager, UnitOfWork};
+use shared::AppError;
+use std::sync::Arc;
+
+/// How memos appear in the audit log.
+const ENTITY_TYPE: &str = "memos";
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait MemoService: Send + Sync {
+    async fn get_memos(&self) -> Result<Vec<Memo>, AppError>;
+    async fn find_by_id(&self, id: i32) -> Result<Option<Memo>, AppError>;
+    /// Creates `memo` owned by `actor`, whatever its `user_id` says.
+    async fn create_memo(&self, actor: &Actor, memo: Memo) -> Result<Memo, AppError>;
+    /// Fails with `AppError::Forbidden` unless `actor` may modify the memo.
+    /// The memo keeps its owner.
+    async fn update_memo(&self, actor: &Actor, memo: Memo) -> Result<Memo, AppError>;
+    /// Fails with `AppError::Forbidden` unless `actor` may modify the memo.
+    async fn delete_memo(&self, actor: &Actor, id: i32) -> Result<(), AppError>;
+}
+
+#[derive(Clone)]
+pub struct MemoServiceImpl {
+    memo_repository: Arc<dyn MemoRepository>,
+    transactions: Arc<dyn TransactionManager>,
+    policy: Arc<dyn Policy>,
+}
+
+impl MemoServiceImpl {
+    pub fn new(
+        memo_repository: Arc<dyn MemoRepository>,
+        transactions: Arc<dyn TransactionManager>,
+        policy: Arc<dyn Policy>,
+    ) -> Self {
+        Self {
+            memo_repository,
+            transactions,
+            policy,
+        }
+    }
+
+    /// Loads memo `id` in `unit_of_work`, failing unless `actor` may modify
+    /// it.
+    async fn authorized(
+        &self,
+        unit_of_work: &dyn UnitOfWork,
+        actor: &Actor,
+        id: i32,
+    ) -> Result<MemoEntity, AppError> {
+        let memo = unit_of_work
+            .memos()
+            .find_by_id(id)
+            .await?
+            .ok_or(AppError::NotFound)?;
+        self.policy.authorize(actor, memo.user_id)?;
+        Ok(memo)
+    }
+}
+
+#[async_trait::async_trait]
+impl MemoService for MemoServiceImpl {
+    async fn get_memos(&self) -> Result<Vec<Memo>, AppError> {
+        metered("memo", "get_memos", async {
+            self.memo_repository
+                .get_memos()
+                .await
+                .map(|entities| entities.into_iter().map(Memo::from).collect())
+        })
+        .await
+    }
+
+    async fn find_by_id(&self, id: i32) -> Result<Option<Memo>, AppError> {
+        metered("memo", "find_by_id", async {
+            self.memo_repository
+                .find_by_id(id)
+                .await
+                .map(|entity| entity.map(Memo::from))
+        })
+        .await
+    }
+
+    async fn create_memo(&self, actor: &Actor, mut memo: Memo) -> Result<Memo, AppError> {
+        metered("memo", "create_memo", async {
+            memo.user_id = actor.user_id;
+            memo.validate()?;
+            transaction(&*self.transactions, |tx| async move {
+                let created = tx.memos().create_memo(Memo::into(memo)).await?;
+                audit::record(
+                    &*tx,
+                    Some(actor),
+                    AuditAction::Create,
+                    ENTITY_TYPE,
+                    created.id,
+                    None,
+                    Some(&created),
+                )
+                .await?;
+                let event = DomainEvent::MemoCreated {
+                    memo_id: created.id,
+                    user_id: created.user_id,
+                    title: created.title.clone(),
+                };
+                event::emit(&*tx, event).await?;
+                Ok(Memo::from(created))
+            })
+            .await
+        })
+        .await
+    }
+
+    async fn update_memo(&self, actor: &Actor, mut memo: Memo) -> Result<Memo, AppError> {
+        metered("memo", "update_memo", async {
+            transaction(&*self.transactions, |tx| async move {
+                let before = self.authorized(&*tx, actor, memo.id).await?;
+                memo.user_id = before.user_id;
+                memo.validate()?;
+                let updated = tx.memos().update_memo(Memo::into(memo)).await?;
+                audit::record(
+                    &*tx,
+                    Some(actor),
+                    AuditAction::Update,
+                    ENTITY_TYPE,
+                    updated.id,
+                    Some(&before),
+                    Some(&updated),
+                )
+                .await?;
+                let event = DomainEvent::MemoUpdated {
+                    memo_id: updated.id,
+                    user_id: updated.user_id,
+                    title: updated.title.clone(),
+                };
+                event::emit(&*tx, event).await?;
+                Ok(Memo::from(updated))
+            })
+            .await
+        })
+        .await
+    }
+
+    async fn delete_memo(&self, actor: &Actor, id: i32) -> Result<(), AppError> {
+        metered("memo", "delete_memo", async {
+            transaction(&*self.transactions, |tx| async move {
+                let before = self.authorized(&*tx, actor, id).await?;
+                tx.memos().delete_memo(id).await?;
+                audit::record(
+                    &*tx,
+                    Some(actor),
+                    AuditAction::Delete,
+                    ENTITY_TYPE,
+                    id,
+                    Some(&before),
+                    None,
+                )
+                .await?;
+                let event = DomainEvent::MemoDeleted {
+                    memo_id: id,
+                    user_id: before.user_id,
+                };
+                event::emit(&*tx, event).await
+            })
+            .await
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use repository::repository::audit::MockAuditRepository;
+    use repository::repository::memo::MockMemoRepository;
+    use repository::repository::outbox::MockOutboxRepository;
+    use repository::repository::unit_of_work::{MockTransactionManager, MockUnitOfWork};
+
+    use super::*;
+    use crate::dto::auth::ADMIN_ROLE;
+    use crate::service::policy::OwnershipPolicy;
+    use crate::service::transaction::mock_transactions;
+
+    fn timestamp() -> chrono::NaiveDateTime {
+        chrono::NaiveDateTime::parse_from_str("2021-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn memo_service(mock_memo_repository: MockMemoRepository) -> MemoServiceImpl {
+        MemoServiceImpl::new(
+            Arc::new(mock_memo_repository),
+            Arc::new(MockTransactionManager::new()),
+            Arc::new(OwnershipPolicy),
+        )
+    }
+
+    /// An audit log expecting `times` events of `action` on memo `id`.
+    fn audit_log(action: &'static str, id: i32, times: usize) -> MockAuditRepository {
+        let mut audit = MockAuditRepository::new();
+        audit
+            .expect_record()
+            .withf(move |event| {
+                event.action == action && event.entity_type == "memos" && event.entity_id == id
+            })
+            .times(times)
+            .returning(Ok);
+        audit
+    }
+
+    /// An outbox expecting `times` events of `event_type`.
+    fn outbox(event_type: &'static str, times: usize) -> MockOutboxRepository {
+        let mut outbox = MockOutboxRepository::new();
+        outbox
+            .expect_append()
+            .withf(move |event| event.event_type == event_type)
+            .times(times)
+            .returning(Ok);
+        outbox
+    }
+
+    /// A service writing through a unit of work on `memos`, `audit` and
+    /// `outbox`, which is committed once when `committed`, else rolled back.
+    fn transactional_memo_service(
+        memos: MockMemoRepository,
+        audit: MockAuditRepository,
+        outbox: MockOutboxRepository,
+        committed: bool,
+    ) -> MemoServiceImpl {
+        let (memos, audit, outbox) = (Arc::new(memos), Arc::new(audit), Arc::new(outbox));
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work.expect_memos().returning(move || memos.clone());
+        unit_of_work.expect_audit().returning(move || audit.clone());
+        unit_of_work
+            .expect_outbox()
+            .returning(move || outbox.clone());
+        unit_of_work
+            .expect_commit()
+            .times(committed as usize)
+            .returning(|| Ok(()));
+        unit_of_work
+            .expect_rollback()
+            .times(!committed as usize)
+            .returning(|| Ok(()));
+        MemoServiceImpl::new(
+            Arc::new(MockMemoRepository::new()),
+            Arc::new(mock_transactions(unit_of_work)),
+            Arc::new(OwnershipPolicy),
+        )
+    }
+
+    fn actor(user_id: i32) -> Actor {
+        Actor {
+            user_id,
+            roles: vec![],
+            permissions: vec![],
+        }
+    }
+
+    /// Expects memo `id`, owned by `user_id`, to be looked up.
+    fn expect_stored_memo(mock_memo_repository: &mut MockMemoRepository, id: i32, user_id: i32) {
+        mock_memo_repository
+            .expect_find_by_id()
+            .withf(move |memo_id| *memo_id == id)
+            .returning(move |id| {
+                Ok(Some(MemoEntity {
+                    id,
+                    user_id,
+                    title: "Groceries".to_string(),
+                    content: "Milk, eggs and bread".to_string(),
+                    created_at: timestamp(),
+                    updated_at: timestamp(),
+                }))
+            });
+    }
+
+    #[tokio::test]
+    async fn test_get_memos() {
+        // given
+        let mut mock_memo_repository = MockMemoRepository::new();
+        mock_memo_repository.expect_get_memos().returning(|| {
+            Ok(vec![
+                MemoEntity {
+                    id: 1,
+                    user_id: 1,
+                    title: "Groceries".to_string(),
+                    content: "Milk, eggs and bread".to_string(),
+                    created_at: timestamp(),
+                    updated_at: timestamp(),
+                },
+                MemoEntity {
+                    id: 2,
+                    user_id: 2,
+                    title: "Meeting".to_string(),
+                    content: "Prepare slides for Monday".to_string(),
+                    created_at: timestamp(),
+                    updated_at: timestamp(),
+                },
+            ])
+        });
+        let memo_service = memo_service(mock_memo_repository);
+        // when
+        let memos = memo_service.get_memos().await.unwrap();
+        // then
+        assert_eq!(memos.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_find_by_id() {
+        // given
+        let mut mock_memo_repository = MockMemoRepository::new();
+        mock_memo_repository.expect_find_by_id().returning(|id| {
+            Ok(Some(MemoEntity {
+                id,
+                user_id: 1,
+                title: "Groceries".to_string(),
+                content: "Milk, eggs and bread".to_string(),
+                created_at: timestamp(),
+                updated_at: timestamp(),
+            }))
+        });
+        let memo_service = memo_service(mock_memo_repository);
+        let id = 1;
+        // when
+        let memo = memo_service.find_by_id(id).await.unwrap().unwrap();
+        // then
+        assert_eq!(memo.id, id);
+    }
+
+    #[tokio::test]
+    async fn test_create_memo() {
+        // given
+        let mut mock_memo_repository = MockMemoRepository::new();
+        mock_memo_repository.expect_create_memo().returning(|memo| {
+            Ok(MemoEntity {
+                id: 3,
+                user_id: memo.user_id,
+                title: memo.title.clone(),
+                content: memo.content.clone(),
+                created_at: timestamp(),
+                updated_at: timestamp(),
+            })
+        });
+        let memo_service = transactional_memo_service(
+            mock_memo_repository,
+            audit_log("create", 3, 1),
+            outbox("MemoCreated", 1),
+            true,
+        );
+        let memo = Memo {
+            id: 0,
+            user_id: 2,
+            title: "Todo".to_string(),
+            content: "Water the plants".to_string(),
+            created_at: timestamp(),
+            updated_at: timestamp(),
+        };
+        // when
+        let memo = memo_service.create_memo(&actor(1), memo).await.unwrap();
+        // then
+        assert_eq!(memo.id, 3);
+        assert_eq!(memo.user_id, 1);
+        assert_eq!(memo.title, "Todo");
+        assert_eq!(memo.content, "Water the plants");
+    }
+
+    #[tokio::test]
+    async fn test_update_memo() {
+        // given
+        let mut mock_memo_repository = MockMemoRepository::new();
+        expect_stored_memo(&mut mock_memo_repository, 1, 1);
+        mock_memo_repository.expect_update_memo().returning(|memo| {
+            Ok(MemoEntity {
+                id: memo.id,
+                user_id: memo.user_id,
+                title: memo.title.clone(),
+                content: memo.content.clone(),
+                created_at: timestamp(),
+                updated_at: timestamp(),
+            })
+        });
+        let memo_service = transactional_memo_service(
+            mock_memo_repository,
+            audit_log("update", 1, 1),
+            outbox("MemoUpdated", 1),
+            true,
+        );
+        let memo = Memo {
+            id: 1,
+            user_id: 2,
+            title: "Shopping".to_string(),
+            content: "Milk and cheese".to_string(),
+            created_at: timestamp(),
+            updated_at: timestamp(),
+        };
+        // when
+        let memo = memo_service.update_memo(&actor(1), memo).await.unwrap();
+        // then
+        assert_eq!(memo.id, 1);
+        assert_eq!(memo.user_id, 1);
+        assert_eq!(memo.title, "Shopping");
+        assert_eq!(memo.content, "Milk and cheese");
+    }
+
+    #[tokio::test]
+    async fn test_delete_memo() {
+        // given
+        let mut mock_memo_repository = MockMemoRepository::new();
+        expect_stored_memo(&mut mock_memo_repository, 1, 1);
+        mock_memo_repository
+            .expect_delete_memo()
+            .returning(|_| Ok(()));
+        let memo_service = transactional_memo_service(
+            mock_memo_repository,
+            audit_log("delete", 1, 1),
+            outbox("MemoDeleted", 1),
+            true,
+        );
+        // when
+        let result = memo_service.delete_memo(&actor(1), 1).await;
+        // then
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_update_memo_records_the_change() {
+        // given
+        let mut mock_memo_repository = MockMemoRepository::new();
+        expect_stored_memo(&mut mock_memo_repository, 1, 1);
+        mock_memo_repository.expect_update_memo().returning(Ok);
+        let mut audit = MockAuditRepository::new();
+        audit
+            .expect_record()
+            .withf(|event| {
+                event.actor_id == Some(1)
+                    && event.old_values == Some(serde_json::json!({ "title": "Groceries" }))
+                    && event.new_values == Some(serde_json::json!({ "title": "Shopping" }))
+            })
+            .times(1)
+            .returning(Ok);
+        let memo_service =
+            transactional_memo_service(mock_memo_repository, audit, outbox("MemoUpdated", 1), true);
+        let memo = Memo {
+            id: 1,
+            user_id: 1,
+            title: "Shopping".to_string(),
+            content: "Milk, eggs and bread".to_string(),
+            created_at: timestamp(),
+            updated_at: timestamp(),
+        };
+        // when
+        let result = memo_service.update_memo(&actor(1), memo).await;
+        // then
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_update_memo_of_another_user_is_forbidden() {
+        // given
+        let mut mock_memo_repository = MockMemoRepository::new();
+        expect_stored_memo(&mut mock_memo_repository, 1, 2);
+        mock_memo_repository.expect_update_memo().never();
+        let memo_service = transactional_memo_service(
+            mock_memo_repository,
+            audit_log("update", 1, 0),
+            outbox("MemoUpdated", 0),
+            false,
+        );
+        let memo = Memo {
+            id: 1,
+            user_id: 1,
+            title: "Shopping".to_string(),
+            content: "Milk and cheese".to_string(),
+            created_at: timestamp(),
+            updated_at: timestamp(),
+        };
+        // when
+        let result = memo_service.update_memo(&actor(1), memo).await;
+        // then
+        assert!(matches!(result, Err(AppError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_delete_memo_of_another_user_is_forbidden() {
+        // given
+        let mut mock_memo_repository = MockMemoRepository::new();
+        expect_stored_memo(&mut mock_memo_repository, 1, 2);
+        mock_memo_repository.expect_delete_memo().never();
+        let memo_service = transactional_memo_service(
+            mock_memo_repository,
+            audit_log("delete", 1, 0),
+            outbox("MemoDeleted", 0),
+            false,
+        );
+        // when
+        let result = memo_service.delete_memo(&actor(1), 1).await;
+        // then
+        assert!(matches!(result, Err(AppError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_admin_may_delete_memo_of_another_user() {
+        // given
+        let mut mock_memo_repository = MockMemoRepository::new();
+        expect_stored_memo(&mut mock_memo_repository, 1, 2);
+        mock_memo_repository
+            .expect_delete_memo()
+            .times(1)
+            .returning(|_| Ok(()));
+        let memo_service = transactional_memo_service(
+            mock_memo_repository,
+            audit_log("delete", 1, 1),
+            outbox("MemoDeleted", 1),
+            true,
+        );
+        let admin = Actor {
+            roles: vec![ADMIN_ROLE.to_string()],
+            ..actor(1)
+        };
+        // when
+        let result = memo_service.delete_memo(&admin, 1).await;
+        // then
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_missing_memo() {
+        // given
+        let mut mock_memo_repository = MockMemoRepository::new();
+        mock_memo_repository
+            .expect_find_by_id()
+            .returning(|_| Ok(None));
+        mock_memo_repository.expect_delete_memo().never();
+        let memo_service = transactional_memo_service(
+            mock_memo_repository,
+            audit_log("delete", 99, 0),
+            outbox("MemoDeleted", 0),
+            false,
+        );
+        // when
+        let result = memo_service.delete_memo(&actor(1), 99).await;
+        // then
+        assert!(matches!(result, Err(AppError::NotFound)));
+    }
+}
@@ -0,0 +1,52 @@
+use crate::dto::auth::Actor;
+use shared::AppError;
+
+/// Decides whether an actor may modify a resource, given who owns it.
+pub trait Policy: Send + Sync {
+    /// Fails with `AppError::Forbidden` when `actor` may not modify a
+    /// resource owned by the user `owner_id`.
+    fn authorize(&self, actor: &Actor, owner_id: i32) -> Result<(), AppError>;
+}
+
+/// Users may modify only what they own; admins may modify anything.
+#[derive(Debug, Clone, Default)]
+pub struct OwnershipPolicy;
+
+impl Policy for OwnershipPolicy {
+    fn authorize(&self, actor: &Actor, owner_id: i32) -> Result<(), AppError> {
+        if actor.is_admin() || actor.user_id == owner_id {
+            Ok(())
+        } else {
+            Err(AppError::Forbidden)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::auth::ADMIN_ROLE;
+
+    #[test]
+    fn test_ownership_policy() {
+        // given
+        let policy = OwnershipPolicy;
+        let user = Actor {
+            user_id: 1,
+            roles: vec![],
+            permissions: vec![],
+        };
+        let admin = Actor {
+            user_id: 2,
+            roles: vec![ADMIN_ROLE.to_string()],
+            permissions: vec![],
+        };
+        // then
+        assert!(policy.authorize(&user, 1).is_ok());
+        assert!(matches!(
+            policy.authorize(&user, 2),
+            Err(AppError::Forbidden)
+        ));
+        assert!(policy.authorize(&admin, 1).is_ok());
+    }
+}
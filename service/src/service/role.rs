@@ -0,0 +1,284 @@
+use crate::dto::auth::Actor;
+use crate::metrics::metered;
+use crate::service::audit::{self, AuditAction};
+use crate::service::transaction::transaction;
+use repository::repository::role::RoleRepository;
+use repository::repository::unit_of_work::TransactionManager;
+use serde_json::json;
+use shared::AppError;
+use std::sync::Arc;
+
+/// How role assignments appear in the audit log, under the user's id.
+const ENTITY_TYPE: &str = "user_roles";
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait RoleService: Send + Sync {
+    async fn get_user_roles(&self, user_id: i32) -> Result<Vec<String>, AppError>;
+    /// Fails with `AppError::NotFound` if the user or the role does not exist.
+    async fn grant_role(&self, actor: &Actor, user_id: i32, role: String) -> Result<(), AppError>;
+    /// Fails with `AppError::NotFound` if the user does not hold the role.
+    /// Ends every session of the user, so tokens minted with the role cannot
+    /// be refreshed.
+    async fn revoke_role(&self, actor: &Actor, user_id: i32, role: String) -> Result<(), AppError>;
+}
+
+#[derive(Clone)]
+pub struct RoleServiceImpl {
+    role_repository: Arc<dyn RoleRepository>,
+    transactions: Arc<dyn TransactionManager>,
+}
+
+impl RoleServiceImpl {
+    pub fn new(
+        role_repository: Arc<dyn RoleRepository>,
+        transactions: Arc<dyn TransactionManager>,
+    ) -> Self {
+        Self {
+            role_repository,
+            transactions,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleService for RoleServiceImpl {
+    async fn get_user_roles(&self, user_id: i32) -> Result<Vec<String>, AppError> {
+        metered("role", "get_user_roles", async {
+            self.role_repository.find_roles_for_user(user_id).await
+        })
+        .await
+    }
+
+    async fn grant_role(&self, actor: &Actor, user_id: i32, role: String) -> Result<(), AppError> {
+        metered("role", "grant_role", async {
+            transaction(&*self.transactions, |tx| async move {
+                if !tx.roles().grant(user_id, &role).await? {
+                    return Err(AppError::NotFound);
+                }
+                audit::record(
+                    &*tx,
+                    Some(actor),
+                    AuditAction::Create,
+                    ENTITY_TYPE,
+                    user_id,
+                    None,
+                    Some(&json!({ "role": role })),
+                )
+                .await
+            })
+            .await
+        })
+        .await
+    }
+
+    async fn revoke_role(&self, actor: &Actor, user_id: i32, role: String) -> Result<(), AppError> {
+        metered("role", "revoke_role", async {
+            transaction(&*self.transactions, |tx| async move {
+                if !tx.roles().revoke(user_id, &role).await? {
+                    return Err(AppError::NotFound);
+                }
+                tx.refresh_tokens().revoke_all_for_user(user_id).await?;
+                audit::record(
+                    &*tx,
+                    Some(actor),
+                    AuditAction::Delete,
+                    ENTITY_TYPE,
+                    user_id,
+                    Some(&json!({ "role": role })),
+                    None,
+                )
+                .await
+            })
+            .await
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::transaction::mock_transactions;
+    use repository::repository::audit::MockAuditRepository;
+    use repository::repository::refresh_token::MockRefreshTokenRepository;
+    use repository::repository::role::MockRoleRepository;
+    use repository::repository::unit_of_work::{MockTransactionManager, MockUnitOfWork};
+
+    use super::*;
+
+    fn admin() -> Actor {
+        Actor {
+            user_id: 1,
+            roles: vec!["admin".to_string()],
+            permissions: vec![],
+        }
+    }
+
+    /// An audit log expecting `times` records of `action` on user 2's
+    /// admin role by the admin.
+    fn audit_log(action: &'static str, times: usize) -> MockAuditRepository {
+        let mut audit = MockAuditRepository::new();
+        audit
+            .expect_record()
+            .withf(move |event| {
+                event.action == action
+                    && event.entity_type == "user_roles"
+                    && event.entity_id == 2
+                    && event.actor_id == Some(1)
+                    && event.new_values.as_ref().or(event.old_values.as_ref())
+                        == Some(&json!({ "role": "admin" }))
+            })
+            .times(times)
+            .returning(Ok);
+        audit
+    }
+
+    /// Refresh tokens expecting every session of user 2 to be ended
+    /// `times` times.
+    fn sessions_ended(times: usize) -> MockRefreshTokenRepository {
+        let mut refresh_tokens = MockRefreshTokenRepository::new();
+        refresh_tokens
+            .expect_revoke_all_for_user()
+            .withf(|user_id| *user_id == 2)
+            .times(times)
+            .returning(|_| Ok(()));
+        refresh_tokens
+    }
+
+    /// A service changing roles through a unit of work on `roles`,
+    /// `refresh_tokens` and `audit`, which is committed once when
+    /// `committed`, else rolled back.
+    fn role_service(
+        roles: MockRoleRepository,
+        refresh_tokens: MockRefreshTokenRepository,
+        audit: MockAuditRepository,
+        committed: bool,
+    ) -> RoleServiceImpl {
+        let (roles, refresh_tokens, audit) =
+            (Arc::new(roles), Arc::new(refresh_tokens), Arc::new(audit));
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work.expect_roles().returning(move || roles.clone());
+        unit_of_work
+            .expect_refresh_tokens()
+            .returning(move || refresh_tokens.clone());
+        unit_of_work.expect_audit().returning(move || audit.clone());
+        unit_of_work
+            .expect_commit()
+            .times(committed as usize)
+            .returning(|| Ok(()));
+        unit_of_work
+            .expect_rollback()
+            .times(!committed as usize)
+            .returning(|| Ok(()));
+        RoleServiceImpl::new(
+            Arc::new(MockRoleRepository::new()),
+            Arc::new(mock_transactions(unit_of_work)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_get_user_roles() {
+        // given
+        let mut mock_role_repository = MockRoleRepository::new();
+        mock_role_repository
+            .expect_find_roles_for_user()
+            .withf(|user_id| *user_id == 2)
+            .returning(|_| Ok(vec!["member".to_string()]));
+        let role_service = RoleServiceImpl::new(
+            Arc::new(mock_role_repository),
+            Arc::new(MockTransactionManager::new()),
+        );
+        // when
+        let roles = role_service.get_user_roles(2).await.unwrap();
+        // then
+        assert_eq!(roles, vec!["member"]);
+    }
+
+    #[tokio::test]
+    async fn test_grant_role() {
+        // given
+        let mut mock_role_repository = MockRoleRepository::new();
+        mock_role_repository
+            .expect_grant()
+            .withf(|user_id, role| *user_id == 2 && role == "admin")
+            .times(1)
+            .returning(|_, _| Ok(true));
+        let role_service = role_service(
+            mock_role_repository,
+            sessions_ended(0),
+            audit_log("create", 1),
+            true,
+        );
+        // when
+        let result = role_service
+            .grant_role(&admin(), 2, "admin".to_string())
+            .await;
+        // then
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_grant_unknown_role() {
+        // given
+        let mut mock_role_repository = MockRoleRepository::new();
+        mock_role_repository
+            .expect_grant()
+            .returning(|_, _| Ok(false));
+        let role_service = role_service(
+            mock_role_repository,
+            sessions_ended(0),
+            audit_log("create", 0),
+            false,
+        );
+        // when
+        let result = role_service
+            .grant_role(&admin(), 2, "owner".to_string())
+            .await;
+        // then
+        assert!(matches!(result, Err(AppError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_role() {
+        // given
+        let mut mock_role_repository = MockRoleRepository::new();
+        mock_role_repository
+            .expect_revoke()
+            .withf(|user_id, role| *user_id == 2 && role == "admin")
+            .times(1)
+            .returning(|_, _| Ok(true));
+        let role_service = role_service(
+            mock_role_repository,
+            sessions_ended(1),
+            audit_log("delete", 1),
+            true,
+        );
+        // when
+        let result = role_service
+            .revoke_role(&admin(), 2, "admin".to_string())
+            .await;
+        // then
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_role_not_held() {
+        // given
+        let mut mock_role_repository = MockRoleRepository::new();
+        mock_role_repository
+            .expect_revoke()
+            .returning(|_, _| Ok(false));
+        let role_service = role_service(
+            mock_role_repository,
+            sessions_ended(0),
+            audit_log("delete", 0),
+            false,
+        );
+        // when
+        let result = role_service
+            .revoke_role(&admin(), 2, "admin".to_string())
+            .await;
+        // then
+        assert!(matches!(result, Err(AppError::NotFound)));
+    }
+}
@@ -0,0 +1,204 @@
+//! Where domain events are delivered. Code in process subscribes to them by
+//! implementing [`EventSink`] as well.
+
+use crate::dto::event::Envelope;
+use shared::settings::EventSettings;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[mockall::automock]
+#[async_trait::async_trait]
+pub trait EventSink: Send + Sync {
+    /// Names the sink in logs, e.g. `webhook`.
+    fn name(&self) -> &'static str;
+    /// Takes `event` over. On failure, the event is later handed to every
+    /// sink again, so sinks may see it more than once.
+    async fn publish(&self, event: &Envelope) -> Result<(), String>;
+}
+
+/// The sinks `settings` name, in order.
+pub fn from_settings(settings: &EventSettings) -> Vec<Arc<dyn EventSink>> {
+    settings
+        .sinks
+        .iter()
+        .filter_map(|name| -> Option<Arc<dyn EventSink>> {
+            match name.as_str() {
+                "log" => Some(Arc::new(LogSink)),
+                "webhook" => Some(Arc::new(WebhookSink::new(
+                    settings.webhook_url.as_deref()?,
+                    settings.webhook_timeout(),
+                ))),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Writes every event to the application log.
+#[derive(Debug, Clone, Default)]
+pub struct LogSink;
+
+#[async_trait::async_trait]
+impl EventSink for LogSink {
+    fn name(&self) -> &'static str {
+        "log"
+    }
+
+    async fn publish(&self, event: &Envelope) -> Result<(), String> {
+        let payload = serde_json::to_string(event).map_err(|err| err.to_string())?;
+        tracing::info!(
+            event_id = event.id,
+            event_type = event.event.event_type(),
+            payload,
+            "domain event"
+        );
+        Ok(())
+    }
+}
+
+/// POSTs every event as JSON to a URL, which has to answer with a 2xx
+/// status within the timeout.
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str, timeout: Duration) -> Self {
+        Self {
+            url: url.to_string(),
+            client: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .expect("Failed to set up the webhook client"),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EventSink for WebhookSink {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn publish(&self, event: &Envelope) -> Result<(), String> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(event)
+            .send()
+            .await
+            .map_err(|err| err.to_string())?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("{} answered {}", self.url, response.status()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::event::DomainEvent;
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    fn envelope() -> Envelope {
+        Envelope {
+            id: 7,
+            occurred_at: chrono::NaiveDateTime::parse_from_str(
+                "2025-03-01 12:00:00",
+                "%Y-%m-%d %H:%M:%S",
+            )
+            .unwrap(),
+            attempt: 1,
+            event: DomainEvent::UserCreated {
+                user_id: 3,
+                name: "Charlie".to_string(),
+            },
+        }
+    }
+
+    /// Answers one request with `status` and returns its JSON body.
+    async fn receive_once(status: &'static str) -> (String, tokio::task::JoinHandle<Value>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/events", listener.local_addr().unwrap());
+        let received = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                stream.read_line(&mut line).await.unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; length];
+            stream.read_exact(&mut body).await.unwrap();
+            let response = format!("HTTP/1.1 {status}\r\ncontent-length: 0\r\n\r\n");
+            stream.write_all(response.as_bytes()).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        });
+        (url, received)
+    }
+
+    #[test]
+    fn test_from_settings() {
+        // given
+        let settings = EventSettings {
+            sinks: vec!["webhook".to_string(), "log".to_string()],
+            webhook_url: Some("http://localhost:8080/events".to_string()),
+            ..EventSettings::default()
+        };
+        // when
+        let sinks = from_settings(&settings);
+        // then
+        let names: Vec<&str> = sinks.iter().map(|sink| sink.name()).collect();
+        assert_eq!(names, vec!["webhook", "log"]);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_posts_the_event() {
+        // given
+        let (url, received) = receive_once("204 No Content").await;
+        let sink = WebhookSink::new(&url, Duration::from_secs(1));
+        // when
+        let result = sink.publish(&envelope()).await;
+        // then
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            received.await.unwrap(),
+            json!({
+                "id": 7,
+                "occurredAt": "2025-03-01T12:00:00",
+                "attempt": 1,
+                "type": "UserCreated",
+                "userId": 3,
+                "name": "Charlie",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_webhook_error_status_fails_the_delivery() {
+        // given
+        let (url, _received) = receive_once("503 Service Unavailable").await;
+        let sink = WebhookSink::new(&url, Duration::from_secs(1));
+        // when
+        let result = sink.publish(&envelope()).await;
+        // then
+        assert_eq!(
+            result,
+            Err(format!("{url} answered 503 Service Unavailable"))
+        );
+    }
+}
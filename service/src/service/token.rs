@@ -0,0 +1,220 @@
+use crate::dto::auth::Actor;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use shared::settings::AuthSettings;
+use shared::AppError;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims {
+    sub: String,
+    iss: String,
+    iat: u64,
+    exp: u64,
+    #[serde(default)]
+    roles: Vec<String>,
+    #[serde(default)]
+    permissions: Vec<String>,
+}
+
+/// Signs and verifies HS256 access tokens and mints opaque refresh tokens.
+///
+/// Every configured key can verify; only `active_kid` signs, so keys can be
+/// rotated without invalidating tokens that are still in flight.
+#[derive(Clone)]
+pub struct TokenIssuer {
+    issuer: String,
+    active_kid: String,
+    encoding_key: EncodingKey,
+    decoding_keys: HashMap<String, DecodingKey>,
+    access_token_ttl: Duration,
+    refresh_token_ttl: Duration,
+}
+
+impl TokenIssuer {
+    pub fn new(settings: &AuthSettings) -> Self {
+        let secret = settings
+            .keys
+            .get(&settings.active_kid)
+            .expect("auth.active_kid must name a configured key");
+        Self {
+            issuer: settings.issuer.clone(),
+            active_kid: settings.active_kid.clone(),
+            encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+            decoding_keys: settings
+                .keys
+                .iter()
+                .map(|(kid, secret)| (kid.clone(), DecodingKey::from_secret(secret.as_bytes())))
+                .collect(),
+            access_token_ttl: Duration::from_secs(settings.access_token_ttl_secs),
+            refresh_token_ttl: Duration::from_secs(settings.refresh_token_ttl_secs),
+        }
+    }
+
+    pub fn access_token_ttl(&self) -> Duration {
+        self.access_token_ttl
+    }
+
+    pub fn refresh_token_ttl(&self) -> Duration {
+        self.refresh_token_ttl
+    }
+
+    pub fn issue_access_token(&self, actor: &Actor) -> Result<String, AppError> {
+        let now = jsonwebtoken::get_current_timestamp();
+        let claims = Claims {
+            sub: actor.user_id.to_string(),
+            iss: self.issuer.clone(),
+            iat: now,
+            exp: now + self.access_token_ttl.as_secs(),
+            roles: actor.roles.clone(),
+            permissions: actor.permissions.clone(),
+        };
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some(self.active_kid.clone());
+        jsonwebtoken::encode(&header, &claims, &self.encoding_key)
+            .map_err(|_| AppError::InternalServerError)
+    }
+
+    /// Returns the actor an access token was issued to, or `AppError::Unauthorized`
+    /// if it is malformed, expired, or signed with an unknown key.
+    pub fn verify_access_token(&self, token: &str) -> Result<Actor, AppError> {
+        let header = jsonwebtoken::decode_header(token).map_err(|_| AppError::Unauthorized)?;
+        let key = header
+            .kid
+            .and_then(|kid| self.decoding_keys.get(&kid))
+            .ok_or(AppError::Unauthorized)?;
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.set_issuer(&[&self.issuer]);
+        validation.leeway = 0;
+        let data = jsonwebtoken::decode::<Claims>(token, key, &validation)
+            .map_err(|_| AppError::Unauthorized)?;
+        let user_id = data
+            .claims
+            .sub
+            .parse()
+            .map_err(|_| AppError::Unauthorized)?;
+        Ok(Actor {
+            user_id,
+            roles: data.claims.roles,
+            permissions: data.claims.permissions,
+        })
+    }
+
+    /// Returns a fresh random refresh token together with its storage hash.
+    pub fn generate_refresh_token(&self) -> (String, String) {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let token = URL_SAFE_NO_PAD.encode(bytes);
+        let hash = hash_refresh_token(&token);
+        (token, hash)
+    }
+}
+
+pub fn hash_refresh_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(keys: &[(&str, &str)], active_kid: &str) -> AuthSettings {
+        AuthSettings {
+            issuer: "memo-app".to_string(),
+            access_token_ttl_secs: 900,
+            refresh_token_ttl_secs: 3600,
+            keys: keys
+                .iter()
+                .map(|(kid, secret)| (kid.to_string(), secret.to_string()))
+                .collect(),
+            active_kid: active_kid.to_string(),
+        }
+    }
+
+    fn actor() -> Actor {
+        Actor {
+            user_id: 7,
+            roles: vec![],
+            permissions: vec![],
+        }
+    }
+
+    const OLD: (&str, &str) = ("old", "an-old-secret-that-is-long-enough-0001");
+    const NEW: (&str, &str) = ("new", "a-new-secret-that-is-long-enough-00002");
+
+    #[test]
+    fn test_issue_and_verify_access_token() {
+        // given
+        let issuer = TokenIssuer::new(&settings(&[NEW], "new"));
+        // when
+        let actor = Actor {
+            user_id: 7,
+            roles: vec!["admin".to_string()],
+            permissions: vec!["roles:manage".to_string()],
+        };
+        let token = issuer.issue_access_token(&actor).unwrap();
+        // then
+        let header = jsonwebtoken::decode_header(&token).unwrap();
+        assert_eq!(header.kid.as_deref(), Some("new"));
+        assert_eq!(issuer.verify_access_token(&token).unwrap(), actor);
+    }
+
+    #[test]
+    fn test_verify_after_key_rotation() {
+        // given
+        let before = TokenIssuer::new(&settings(&[OLD], "old"));
+        let token = before.issue_access_token(&actor()).unwrap();
+        // when
+        let rotated = TokenIssuer::new(&settings(&[OLD, NEW], "new"));
+        let retired = TokenIssuer::new(&settings(&[NEW], "new"));
+        // then
+        assert!(rotated.verify_access_token(&token).is_ok());
+        assert!(matches!(
+            retired.verify_access_token(&token),
+            Err(AppError::Unauthorized)
+        ));
+    }
+
+    #[test]
+    fn test_verify_rejects_tampered_and_expired_tokens() {
+        // given
+        let issuer = TokenIssuer::new(&settings(&[NEW], "new"));
+        let token = issuer.issue_access_token(&actor()).unwrap();
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = Some("new".to_string());
+        let expired = jsonwebtoken::encode(
+            &header,
+            &Claims {
+                sub: "7".to_string(),
+                iss: "memo-app".to_string(),
+                iat: 0,
+                exp: 1,
+                roles: vec![],
+                permissions: vec![],
+            },
+            &EncodingKey::from_secret(NEW.1.as_bytes()),
+        )
+        .unwrap();
+        // then
+        assert!(issuer.verify_access_token(&format!("{token}x")).is_err());
+        assert!(issuer.verify_access_token(&expired).is_err());
+        assert!(issuer.verify_access_token("not-a-jwt").is_err());
+    }
+
+    #[test]
+    fn test_generate_refresh_token() {
+        // given
+        let issuer = TokenIssuer::new(&settings(&[NEW], "new"));
+        // when
+        let (token, hash) = issuer.generate_refresh_token();
+        let (other, _) = issuer.generate_refresh_token();
+        // then
+        assert_ne!(token, other);
+        assert_eq!(hash, hash_refresh_token(&token));
+        assert_eq!(hash.len(), 64);
+    }
+}
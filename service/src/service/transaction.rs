@@ -0,0 +1,84 @@
+use repository::repository::unit_of_work::{TransactionManager, UnitOfWork};
+use shared::AppError;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Runs `work` in a new unit of work, committing it when `work` succeeds
+/// and rolling it back when it fails.
+///
+/// ```text
+/// transaction(&*self.transactions, |tx| async move {
+///     tx.users().delete_user(id, None).await?;
+///     tx.refresh_tokens().revoke_all_for_user(id).await
+/// })
+/// .await
+/// ```
+pub async fn transaction<T, F, Fut>(
+    transactions: &dyn TransactionManager,
+    work: F,
+) -> Result<T, AppError>
+where
+    F: FnOnce(Arc<dyn UnitOfWork>) -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    let unit_of_work = transactions.begin().await?;
+    match work(unit_of_work.clone()).await {
+        Ok(value) => {
+            unit_of_work.commit().await?;
+            Ok(value)
+        }
+        Err(err) => {
+            if let Err(rollback) = unit_of_work.rollback().await {
+                tracing::warn!(error = %rollback, "rolling back a unit of work failed");
+            }
+            Err(err)
+        }
+    }
+}
+
+/// Transactions that start `unit_of_work` exactly once.
+#[cfg(test)]
+pub fn mock_transactions(
+    unit_of_work: repository::repository::unit_of_work::MockUnitOfWork,
+) -> repository::repository::unit_of_work::MockTransactionManager {
+    let unit_of_work: Arc<dyn UnitOfWork> = Arc::new(unit_of_work);
+    let mut transactions = repository::repository::unit_of_work::MockTransactionManager::new();
+    transactions
+        .expect_begin()
+        .times(1)
+        .returning(move || Ok(unit_of_work.clone()));
+    transactions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use repository::repository::unit_of_work::MockUnitOfWork;
+
+    #[tokio::test]
+    async fn test_commits_on_success() {
+        // given
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work.expect_commit().times(1).returning(|| Ok(()));
+        unit_of_work.expect_rollback().never();
+        let transactions = mock_transactions(unit_of_work);
+        // when
+        let result = transaction(&transactions, |_| async { Ok(7) }).await;
+        // then
+        assert_eq!(result.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_rolls_back_on_failure() {
+        // given
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work.expect_commit().never();
+        unit_of_work.expect_rollback().times(1).returning(|| Ok(()));
+        let transactions = mock_transactions(unit_of_work);
+        // when
+        let result: Result<(), _> =
+            transaction(&transactions, |_| async { Err(AppError::Conflict) }).await;
+        // then
+        assert!(matches!(result, Err(AppError::Conflict)));
+    }
+}
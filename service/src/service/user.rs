@@ -1,79 +1,497 @@
-use crate::dto::user::User;
-use repository::repository::user::UserRepository;
+use crate::dto::auth::Actor;
+use crate::dto::event::DomainEvent;
+use crate::dto::patch::Patch;
+use crate::dto::user::{User, UserChanges};
+use crate::metrics::metered;
+use crate::service::audit::{self, AuditAction};
+use crate::service::event;
+use crate::service::policy::Policy;
+use crate::service::transaction::transaction;
+use repository::entity::user::UserEntity;
+use repository::repository::unit_of_work::{TransactionManager, UnitOfWork};
+use repository::repository::user::{UserQuery, UserRepository};
+use shared::query::Page;
 use shared::AppError;
 use std::sync::Arc;
+use std::time::Duration;
+use tracing::instrument;
+
+/// How users appear in the audit log.
+pub(crate) const ENTITY_TYPE: &str = "users";
 
 #[mockall::automock]
 #[async_trait::async_trait]
 pub trait UserService: Send + Sync {
-    async fn get_users(&self) -> Result<Vec<User>, AppError>;
+    /// Listing deleted users is reserved to admins.
+    async fn get_users(
+        &self,
+        actor: Option<Actor>,
+        query: UserQuery,
+    ) -> Result<Page<User>, AppError>;
     async fn find_by_id(&self, id: i32) -> Result<Option<User>, AppError>;
-    async fn create_user(&self, user: User) -> Result<User, AppError>;
-    async fn update_user(&self, user: User) -> Result<User, AppError>;
-    async fn delete_user(&self, id: i32) -> Result<(), AppError>;
+    async fn create_user(&self, actor: &Actor, user: User) -> Result<User, AppError>;
+    /// Fails with `AppError::Forbidden` unless `actor` may modify the user, and
+    /// with `AppError::PreconditionFailed` unless the user is at one of
+    /// `expected_versions`.
+    async fn update_user(
+        &self,
+        actor: &Actor,
+        user: User,
+        expected_versions: Option<Vec<i32>>,
+    ) -> Result<User, AppError>;
+    /// Changes only the fields `patch` touches; unknown fields are rejected.
+    async fn patch_user(
+        &self,
+        actor: &Actor,
+        id: i32,
+        patch: Patch,
+        expected_versions: Option<Vec<i32>>,
+    ) -> Result<User, AppError>;
+    /// Moves the user to the trash, from where `restore_user` brings it back
+    /// until the retention period has passed.
+    ///
+    /// Fails with `AppError::Forbidden` unless `actor` may modify the user, and
+    /// with `AppError::PreconditionFailed` unless the user is at one of
+    /// `expected_versions`.
+    async fn delete_user(
+        &self,
+        actor: &Actor,
+        id: i32,
+        expected_versions: Option<Vec<i32>>,
+    ) -> Result<(), AppError>;
+    /// Fails with `AppError::Conflict` if the user's email has been
+    /// registered again while they were in the trash.
+    async fn restore_user(&self, actor: &Actor, id: i32) -> Result<User, AppError>;
+    /// Permanently removes users that have been in the trash for longer
+    /// than `retention`, returning how many there were.
+    async fn purge_deleted(&self, retention: Duration) -> Result<u64, AppError>;
 }
 
 #[derive(Clone)]
 pub struct UserServiceImpl {
     user_repository: Arc<dyn UserRepository>,
+    transactions: Arc<dyn TransactionManager>,
+    policy: Arc<dyn Policy>,
 }
 
 impl UserServiceImpl {
-    pub fn new(user_repository: Arc<dyn UserRepository>) -> Self {
-        Self { user_repository }
+    pub fn new(
+        user_repository: Arc<dyn UserRepository>,
+        transactions: Arc<dyn TransactionManager>,
+        policy: Arc<dyn Policy>,
+    ) -> Self {
+        Self {
+            user_repository,
+            transactions,
+            policy,
+        }
     }
 }
 
 #[async_trait::async_trait]
 impl UserService for UserServiceImpl {
-    async fn get_users(&self) -> Result<Vec<User>, AppError> {
-        self.user_repository
-            .get_users()
-            .await
-            .map(|entities| entities.into_iter().map(User::from).collect())
+    #[instrument(skip(self, actor), fields(actor = actor.as_ref().map(|actor| actor.user_id)))]
+    async fn get_users(
+        &self,
+        actor: Option<Actor>,
+        query: UserQuery,
+    ) -> Result<Page<User>, AppError> {
+        metered("user", "get_users", async {
+            if query.filter.include_deleted {
+                let actor = actor.ok_or(AppError::Unauthorized)?;
+                if !actor.is_admin() {
+                    return Err(AppError::Forbidden);
+                }
+            }
+            self.user_repository
+                .get_users(&query)
+                .await
+                .map(|page| page.map(User::from))
+        })
+        .await
     }
 
+    #[instrument(skip(self))]
     async fn find_by_id(&self, id: i32) -> Result<Option<User>, AppError> {
-        self.user_repository
-            .find_by_id(id)
+        metered("user", "find_by_id", async {
+            self.user_repository
+                .find_by_id(id)
+                .await
+                .map(|entity| entity.map(User::from))
+        })
+        .await
+    }
+
+    #[instrument(skip(self, actor, user), fields(actor = actor.user_id))]
+    async fn create_user(&self, actor: &Actor, user: User) -> Result<User, AppError> {
+        metered("user", "create_user", async {
+            user.validate()?;
+            transaction(&*self.transactions, |tx| async move {
+                let created = tx.users().create_user(User::into(user)).await?;
+                audit::record(
+                    &*tx,
+                    Some(actor),
+                    AuditAction::Create,
+                    ENTITY_TYPE,
+                    created.id,
+                    None,
+                    Some(&created),
+                )
+                .await?;
+                let event = DomainEvent::UserCreated {
+                    user_id: created.id,
+                    name: created.name.clone(),
+                };
+                event::emit(&*tx, event).await?;
+                Ok(User::from(created))
+            })
+            .await
+        })
+        .await
+    }
+
+    #[instrument(skip(self, actor, user), fields(actor = actor.user_id, id = user.id))]
+    async fn update_user(
+        &self,
+        actor: &Actor,
+        user: User,
+        expected_versions: Option<Vec<i32>>,
+    ) -> Result<User, AppError> {
+        metered("user", "update_user", async {
+            // a user owns its own record
+            self.policy.authorize(actor, user.id)?;
+            user.validate()?;
+            transaction(&*self.transactions, |tx| async move {
+                update(&*tx, actor, user, expected_versions).await
+            })
+            .await
+        })
+        .await
+    }
+
+    #[instrument(skip(self, actor, patch), fields(actor = actor.user_id))]
+    async fn patch_user(
+        &self,
+        actor: &Actor,
+        id: i32,
+        patch: Patch,
+        expected_versions: Option<Vec<i32>>,
+    ) -> Result<User, AppError> {
+        metered("user", "patch_user", async {
+            self.policy.authorize(actor, id)?;
+            transaction(&*self.transactions, |tx| async move {
+                // read in the transaction, as replicas and the cache may lag
+                let user: User = tx
+                    .users()
+                    .find_by_id(id)
+                    .await?
+                    .ok_or(AppError::NotFound)?
+                    .into();
+                if expected_versions.is_some_and(|versions| !versions.contains(&user.version)) {
+                    return Err(AppError::PreconditionFailed);
+                }
+                // guard the write with the version the patch was applied to
+                let version = user.version;
+                let changes = patch.apply(&UserChanges::from(&user))?;
+                let user = User {
+                    name: changes.name,
+                    ..user
+                };
+                user.validate()?;
+                update(&*tx, actor, user, Some(vec![version])).await
+            })
             .await
-            .map(|entity| entity.map(User::from))
+        })
+        .await
     }
 
-    async fn create_user(&self, user: User) -> Result<User, AppError> {
-        self.user_repository
-            .create_user(User::into(user))
+    #[instrument(skip(self, actor), fields(actor = actor.user_id))]
+    async fn delete_user(
+        &self,
+        actor: &Actor,
+        id: i32,
+        expected_versions: Option<Vec<i32>>,
+    ) -> Result<(), AppError> {
+        metered("user", "delete_user", async {
+            self.policy.authorize(actor, id)?;
+            transaction(&*self.transactions, |tx| async move {
+                let before = tx.users().find_by_id(id).await?;
+                let expected_version = guard(before.as_ref(), expected_versions.as_deref())?;
+                tx.users().delete_user(id, expected_version).await?;
+                // a deleted user must not be able to mint new access tokens
+                tx.refresh_tokens().revoke_all_for_user(id).await?;
+                audit::record(
+                    &*tx,
+                    Some(actor),
+                    AuditAction::Delete,
+                    ENTITY_TYPE,
+                    id,
+                    before.as_ref(),
+                    None,
+                )
+                .await?;
+                event::emit(&*tx, DomainEvent::UserDeleted { user_id: id }).await
+            })
             .await
-            .map(User::from)
+        })
+        .await
     }
 
-    async fn update_user(&self, user: User) -> Result<User, AppError> {
-        self.user_repository
-            .update_user(User::into(user))
+    #[instrument(skip(self, actor), fields(actor = actor.user_id))]
+    async fn restore_user(&self, actor: &Actor, id: i32) -> Result<User, AppError> {
+        metered("user", "restore_user", async {
+            self.policy.authorize(actor, id)?;
+            transaction(&*self.transactions, |tx| async move {
+                let restored = tx.users().restore_user(id).await?;
+                audit::record(
+                    &*tx,
+                    Some(actor),
+                    AuditAction::Restore,
+                    ENTITY_TYPE,
+                    id,
+                    None,
+                    Some(&restored),
+                )
+                .await?;
+                event::emit(&*tx, DomainEvent::UserRestored { user_id: id }).await?;
+                Ok(User::from(restored))
+            })
             .await
-            .map(User::from)
+        })
+        .await
     }
 
-    async fn delete_user(&self, id: i32) -> Result<(), AppError> {
-        self.user_repository.delete_user(id).await
+    #[instrument(skip(self))]
+    async fn purge_deleted(&self, retention: Duration) -> Result<u64, AppError> {
+        metered("user", "purge_deleted", async {
+            let retention =
+                chrono::Duration::from_std(retention).map_err(|_| AppError::InternalServerError)?;
+            let deleted_before = chrono::Utc::now().naive_utc() - retention;
+            transaction(&*self.transactions, |tx| async move {
+                let purged = tx.users().purge_deleted(deleted_before).await?;
+                for id in &purged {
+                    audit::record::<UserEntity>(
+                        &*tx,
+                        None,
+                        AuditAction::Purge,
+                        ENTITY_TYPE,
+                        *id,
+                        None,
+                        None,
+                    )
+                    .await?;
+                }
+                Ok(purged.len() as u64)
+            })
+            .await
+        })
+        .await
+    }
+}
+
+/// Stores `user` in `unit_of_work`, recording the fields that changed and
+/// announcing a new name.
+async fn update(
+    unit_of_work: &dyn UnitOfWork,
+    actor: &Actor,
+    user: User,
+    expected_versions: Option<Vec<i32>>,
+) -> Result<User, AppError> {
+    let before = unit_of_work.users().find_by_id(user.id).await?;
+    let expected_version = guard(before.as_ref(), expected_versions.as_deref())?;
+    let after = unit_of_work
+        .users()
+        .update_user(User::into(user), expected_version)
+        .await?;
+    audit::record(
+        unit_of_work,
+        Some(actor),
+        AuditAction::Update,
+        ENTITY_TYPE,
+        after.id,
+        before.as_ref(),
+        Some(&after),
+    )
+    .await?;
+    if let Some(before) = before.filter(|before| before.name != after.name) {
+        let event = DomainEvent::UserRenamed {
+            user_id: after.id,
+            old_name: before.name,
+            new_name: after.name.clone(),
+        };
+        event::emit(unit_of_work, event).await?;
+    }
+    Ok(User::from(after))
+}
+
+/// The version to guard a write with: the stored one, provided it is among
+/// `expected_versions`. The write still fails if it changes meanwhile.
+fn guard(
+    stored: Option<&UserEntity>,
+    expected_versions: Option<&[i32]>,
+) -> Result<Option<i32>, AppError> {
+    let Some(expected_versions) = expected_versions else {
+        return Ok(None);
+    };
+    let version = stored.ok_or(AppError::NotFound)?.version;
+    if expected_versions.contains(&version) {
+        Ok(Some(version))
+    } else {
+        Err(AppError::PreconditionFailed)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use repository::{entity::user::UserEntity, repository::user::MockUserRepository};
+    use repository::{
+        entity::user::UserEntity,
+        repository::audit::MockAuditRepository,
+        repository::outbox::MockOutboxRepository,
+        repository::refresh_token::MockRefreshTokenRepository,
+        repository::unit_of_work::{MockTransactionManager, MockUnitOfWork},
+        repository::user::{MockUserRepository, UserFilter},
+    };
 
     use super::*;
+    use crate::dto::auth::ADMIN_ROLE;
+    use crate::service::policy::OwnershipPolicy;
+    use crate::service::transaction::mock_transactions;
+    use serde_json::json;
+
+    fn user_service(user_repository: MockUserRepository) -> UserServiceImpl {
+        transactional_user_service(user_repository, MockTransactionManager::new())
+    }
+
+    fn transactional_user_service(
+        user_repository: MockUserRepository,
+        transactions: MockTransactionManager,
+    ) -> UserServiceImpl {
+        UserServiceImpl::new(
+            Arc::new(user_repository),
+            Arc::new(transactions),
+            Arc::new(OwnershipPolicy),
+        )
+    }
+
+    fn user_entity(id: i32, name: &str, version: i32) -> UserEntity {
+        let at = chrono::NaiveDateTime::parse_from_str("2021-01-01 00:00:00", "%Y-%m-%d %H:%M:%S")
+            .unwrap();
+        UserEntity {
+            id,
+            name: name.to_string(),
+            version,
+            deleted_at: None,
+            created_at: at,
+            updated_at: at,
+        }
+    }
+
+    /// An audit log expecting `times` events of `action` on user `id`.
+    fn audit_log(action: &'static str, id: i32, times: usize) -> Arc<MockAuditRepository> {
+        let mut audit = MockAuditRepository::new();
+        audit
+            .expect_record()
+            .withf(move |event| {
+                event.action == action && event.entity_type == "users" && event.entity_id == id
+            })
+            .times(times)
+            .returning(Ok);
+        Arc::new(audit)
+    }
+
+    /// An outbox expecting `times` events of `event_type`.
+    fn outbox(event_type: &'static str, times: usize) -> Arc<MockOutboxRepository> {
+        let mut outbox = MockOutboxRepository::new();
+        outbox
+            .expect_append()
+            .withf(move |event| event.event_type == event_type)
+            .times(times)
+            .returning(Ok);
+        Arc::new(outbox)
+    }
+
+    /// A unit of work on `users`, `audit` and `outbox` that is committed
+    /// once.
+    fn committed_unit_of_work(
+        users: MockUserRepository,
+        audit: Arc<MockAuditRepository>,
+        outbox: Arc<MockOutboxRepository>,
+    ) -> MockUnitOfWork {
+        let users = Arc::new(users);
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work.expect_users().returning(move || users.clone());
+        unit_of_work.expect_audit().returning(move || audit.clone());
+        unit_of_work
+            .expect_outbox()
+            .returning(move || outbox.clone());
+        unit_of_work.expect_commit().times(1).returning(|| Ok(()));
+        unit_of_work
+    }
+
+    /// A unit of work on `users` that is rolled back once.
+    fn rolled_back_unit_of_work(users: MockUserRepository) -> MockUnitOfWork {
+        let users = Arc::new(users);
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work.expect_users().returning(move || users.clone());
+        unit_of_work.expect_commit().never();
+        unit_of_work.expect_rollback().times(1).returning(|| Ok(()));
+        unit_of_work
+    }
+
+    /// A unit of work deleting user `id` and revoking its refresh tokens,
+    /// committed only when `revoked` succeeds.
+    fn deleting_unit_of_work(id: i32, revoked: Result<(), AppError>) -> MockUnitOfWork {
+        let audit = audit_log("delete", id, revoked.is_ok() as usize);
+        let outbox = outbox("UserDeleted", revoked.is_ok() as usize);
+        let mut users = MockUserRepository::new();
+        users
+            .expect_find_by_id()
+            .returning(|id| Ok(Some(user_entity(id, "Alice", 1))));
+        users
+            .expect_delete_user()
+            .withf(move |deleted, version| *deleted == id && version.is_none())
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let users = Arc::new(users);
+        let mut refresh_tokens = MockRefreshTokenRepository::new();
+        let mut revoked = Some(revoked);
+        refresh_tokens
+            .expect_revoke_all_for_user()
+            .withf(move |user_id| *user_id == id)
+            .times(1)
+            .returning(move |_| revoked.take().unwrap());
+        let refresh_tokens = Arc::new(refresh_tokens);
+        let mut unit_of_work = MockUnitOfWork::new();
+        unit_of_work.expect_users().returning(move || users.clone());
+        unit_of_work
+            .expect_refresh_tokens()
+            .returning(move || refresh_tokens.clone());
+        unit_of_work.expect_audit().returning(move || audit.clone());
+        unit_of_work
+            .expect_outbox()
+            .returning(move || outbox.clone());
+        unit_of_work
+    }
+
+    fn actor(user_id: i32) -> Actor {
+        Actor {
+            user_id,
+            roles: vec![],
+            permissions: vec![],
+        }
+    }
 
     #[tokio::test]
     async fn test_get_users() {
         // given
         let mut mock_user_repository = MockUserRepository::new();
-        mock_user_repository.expect_get_users().returning(|| {
-            Ok(vec![
+        mock_user_repository.expect_get_users().returning(|query| {
+            let rows = vec![
                 UserEntity {
                     id: 1,
                     name: "Alice".to_string(),
+                    version: 1,
+                    deleted_at: None,
                     created_at: chrono::NaiveDateTime::parse_from_str(
                         "2021-01-01 00:00:00",
                         "%Y-%m-%d %H:%M:%S",
@@ -88,6 +506,8 @@ mod tests {
                 UserEntity {
                     id: 2,
                     name: "Bob".to_string(),
+                    version: 1,
+                    deleted_at: None,
                     created_at: chrono::NaiveDateTime::parse_from_str(
                         "2021-01-01 00:00:00",
                         "%Y-%m-%d %H:%M:%S",
@@ -99,13 +519,40 @@ mod tests {
                     )
                     .unwrap(),
                 },
-            ])
+            ];
+            Ok(Page::from_rows(rows, query, 2))
         });
-        let user_service = UserServiceImpl::new(Arc::new(mock_user_repository));
+        let user_service = user_service(mock_user_repository);
+        // when
+        let page = user_service
+            .get_users(
+                None,
+                UserQuery::new(UserFilter::default(), None, None, None).unwrap(),
+            )
+            .await
+            .unwrap();
+        // then
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.total, 2);
+    }
+
+    #[tokio::test]
+    async fn test_get_users_including_deleted_requires_admin() {
+        // given
+        let user_service = user_service(MockUserRepository::new());
+        let query = || {
+            let filter = UserFilter {
+                include_deleted: true,
+                ..UserFilter::default()
+            };
+            UserQuery::new(filter, None, None, None).unwrap()
+        };
         // when
-        let users = user_service.get_users().await.unwrap();
+        let anonymous = user_service.get_users(None, query()).await;
+        let member = user_service.get_users(Some(actor(1)), query()).await;
         // then
-        assert_eq!(users.len(), 2);
+        assert!(matches!(anonymous, Err(AppError::Unauthorized)));
+        assert!(matches!(member, Err(AppError::Forbidden)));
     }
 
     #[tokio::test]
@@ -114,8 +561,10 @@ mod tests {
         let mut mock_user_repository = MockUserRepository::new();
         mock_user_repository.expect_find_by_id().returning(|id| {
             Ok(Option::Some(UserEntity {
-                id: id,
+                id,
                 name: "Alice".to_string(),
+                version: 1,
+                deleted_at: None,
                 created_at: chrono::NaiveDateTime::parse_from_str(
                     "2021-01-01 00:00:00",
                     "%Y-%m-%d %H:%M:%S",
@@ -128,7 +577,7 @@ mod tests {
                 .unwrap(),
             }))
         });
-        let user_service = UserServiceImpl::new(Arc::new(mock_user_repository));
+        let user_service = user_service(mock_user_repository);
         let id = 1;
         // when
         let user = user_service.find_by_id(id).await.unwrap().unwrap();
@@ -139,11 +588,13 @@ mod tests {
     #[tokio::test]
     async fn test_create_user() {
         // given
-        let mut mock_user_repository = MockUserRepository::new();
-        mock_user_repository.expect_create_user().returning(|user| {
+        let mut users = MockUserRepository::new();
+        users.expect_create_user().returning(|user| {
             Ok(UserEntity {
                 id: 3,
                 name: user.name.clone(),
+                version: 1,
+                deleted_at: None,
                 created_at: chrono::NaiveDateTime::parse_from_str(
                     "2021-01-01 00:00:00",
                     "%Y-%m-%d %H:%M:%S",
@@ -156,10 +607,15 @@ mod tests {
                 .unwrap(),
             })
         });
-        let user_service = UserServiceImpl::new(Arc::new(mock_user_repository));
+        let unit_of_work =
+            committed_unit_of_work(users, audit_log("create", 3, 1), outbox("UserCreated", 1));
+        let user_service =
+            transactional_user_service(MockUserRepository::new(), mock_transactions(unit_of_work));
         let user = User {
             id: 3,
             name: "Charlie".to_string(),
+            version: 1,
+            deleted_at: None,
             created_at: chrono::NaiveDateTime::parse_from_str(
                 "2021-01-01 00:00:00",
                 "%Y-%m-%d %H:%M:%S",
@@ -172,36 +628,21 @@ mod tests {
             .unwrap(),
         };
         // when
-        let user = user_service.create_user(user).await.unwrap();
+        let user = user_service.create_user(&actor(1), user).await.unwrap();
         // then
         assert_eq!(user.id, 3);
         assert_eq!(user.name, "Charlie");
     }
 
     #[tokio::test]
-    async fn test_update_user() {
+    async fn test_create_user_with_blank_name_is_rejected() {
         // given
-        let mut mock_user_repository = MockUserRepository::new();
-        mock_user_repository.expect_update_user().returning(|user| {
-            Ok(UserEntity {
-                id: user.id,
-                name: user.name.clone(),
-                created_at: chrono::NaiveDateTime::parse_from_str(
-                    "2021-01-01 00:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-                updated_at: chrono::NaiveDateTime::parse_from_str(
-                    "2021-01-01 00:00:00",
-                    "%Y-%m-%d %H:%M:%S",
-                )
-                .unwrap(),
-            })
-        });
-        let user_service = UserServiceImpl::new(Arc::new(mock_user_repository));
+        let user_service = user_service(MockUserRepository::new());
         let user = User {
-            id: 1,
-            name: "Alice".to_string(),
+            id: 0,
+            name: " ".to_string(),
+            version: 1,
+            deleted_at: None,
             created_at: chrono::NaiveDateTime::parse_from_str(
                 "2021-01-01 00:00:00",
                 "%Y-%m-%d %H:%M:%S",
@@ -214,23 +655,366 @@ mod tests {
             .unwrap(),
         };
         // when
-        let user = user_service.update_user(user).await.unwrap();
+        let result = user_service.create_user(&actor(1), user).await;
+        // then
+        let Err(AppError::Validation(violations)) = result else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(violations[0].field, "name");
+        assert_eq!(violations[0].code, "blank");
+    }
+
+    #[tokio::test]
+    async fn test_update_user() {
+        // given
+        let mut users = MockUserRepository::new();
+        users
+            .expect_find_by_id()
+            .withf(|id| *id == 1)
+            .returning(|id| Ok(Some(user_entity(id, "Alice", 1))));
+        users
+            .expect_update_user()
+            .returning(|user, _| Ok(user_entity(user.id, &user.name, 2)));
+        let mut audit = MockAuditRepository::new();
+        audit
+            .expect_record()
+            .withf(|event| {
+                event.actor_id == Some(1)
+                    && event.action == "update"
+                    && event.entity_id == 1
+                    && event.old_values == Some(json!({"name": "Alice", "version": 1}))
+                    && event.new_values == Some(json!({"name": "Alicia", "version": 2}))
+            })
+            .times(1)
+            .returning(Ok);
+        let mut outbox = MockOutboxRepository::new();
+        outbox
+            .expect_append()
+            .withf(|event| {
+                event.payload
+                    == json!({
+                        "type": "UserRenamed",
+                        "userId": 1,
+                        "oldName": "Alice",
+                        "newName": "Alicia",
+                    })
+            })
+            .times(1)
+            .returning(Ok);
+        let unit_of_work = committed_unit_of_work(users, Arc::new(audit), Arc::new(outbox));
+        let user_service =
+            transactional_user_service(MockUserRepository::new(), mock_transactions(unit_of_work));
+        let user = User::from(user_entity(1, "Alicia", 1));
+        // when
+        let user = user_service
+            .update_user(&actor(1), user, None)
+            .await
+            .unwrap();
         // then
         assert_eq!(user.id, 1);
-        assert_eq!(user.name, "Alice");
+        assert_eq!(user.name, "Alicia");
+        assert_eq!(user.version, 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_user_at_any_expected_version() {
+        // given
+        let mut users = MockUserRepository::new();
+        users
+            .expect_find_by_id()
+            .returning(|id| Ok(Some(user_entity(id, "Alice", 3))));
+        users
+            .expect_update_user()
+            .withf(|_, version| *version == Some(3))
+            .times(1)
+            .returning(|user, _| Ok(user_entity(user.id, &user.name, 4)));
+        let unit_of_work =
+            committed_unit_of_work(users, audit_log("update", 1, 1), outbox("UserRenamed", 1));
+        let user_service =
+            transactional_user_service(MockUserRepository::new(), mock_transactions(unit_of_work));
+        let user = User::from(user_entity(1, "Alicia", 3));
+        // when
+        let result = user_service
+            .update_user(&actor(1), user, Some(vec![2, 3]))
+            .await;
+        // then
+        assert_eq!(result.unwrap().version, 4);
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_at_none_of_the_expected_versions() {
+        // given
+        let mut users = MockUserRepository::new();
+        users
+            .expect_find_by_id()
+            .returning(|id| Ok(Some(user_entity(id, "Alice", 3))));
+        users.expect_delete_user().never();
+        let user_service = transactional_user_service(
+            MockUserRepository::new(),
+            mock_transactions(rolled_back_unit_of_work(users)),
+        );
+        // when
+        let result = user_service
+            .delete_user(&actor(1), 1, Some(vec![1, 2]))
+            .await;
+        // then
+        assert!(matches!(result, Err(AppError::PreconditionFailed)));
+    }
+
+    #[tokio::test]
+    async fn test_patch_user() {
+        // given
+        let mut users = MockUserRepository::new();
+        users
+            .expect_find_by_id()
+            .withf(|id| *id == 1)
+            .returning(|id| Ok(Some(user_entity(id, "Alice", 1))));
+        users
+            .expect_update_user()
+            .withf(|user, version| user.id == 1 && user.name == "Alicia" && *version == Some(1))
+            .returning(|user, _| Ok(user));
+        let unit_of_work =
+            committed_unit_of_work(users, audit_log("update", 1, 1), outbox("UserRenamed", 1));
+        let user_service =
+            transactional_user_service(MockUserRepository::new(), mock_transactions(unit_of_work));
+        // when
+        let user = user_service
+            .patch_user(&actor(1), 1, Patch::Merge(json!({"name": "Alicia"})), None)
+            .await
+            .unwrap();
+        // then
+        assert_eq!(user.name, "Alicia");
+    }
+
+    #[tokio::test]
+    async fn test_patch_user_ignores_a_lagging_replica() {
+        // given a replica or cache still holding version 1 as Alice
+        let mut lagging = MockUserRepository::new();
+        lagging
+            .expect_find_by_id()
+            .returning(|id| Ok(Some(user_entity(id, "Alice", 1))));
+        let mut users = MockUserRepository::new();
+        users
+            .expect_find_by_id()
+            .returning(|id| Ok(Some(user_entity(id, "Alicia", 2))));
+        users
+            .expect_update_user()
+            .withf(|user, version| user.name == "Alicia Smith" && *version == Some(2))
+            .times(1)
+            .returning(|user, _| Ok(user));
+        let unit_of_work =
+            committed_unit_of_work(users, audit_log("update", 1, 1), outbox("UserRenamed", 1));
+        let user_service = transactional_user_service(lagging, mock_transactions(unit_of_work));
+        // when
+        let result = user_service
+            .patch_user(
+                &actor(1),
+                1,
+                Patch::Merge(json!({"name": "Alicia Smith"})),
+                Some(vec![2]),
+            )
+            .await;
+        // then
+        assert_eq!(result.unwrap().name, "Alicia Smith");
+    }
+
+    #[tokio::test]
+    async fn test_patch_missing_user() {
+        // given
+        let mut users = MockUserRepository::new();
+        users.expect_find_by_id().returning(|_| Ok(None));
+        let user_service = transactional_user_service(
+            MockUserRepository::new(),
+            mock_transactions(rolled_back_unit_of_work(users)),
+        );
+        // when
+        let result = user_service
+            .patch_user(&actor(1), 1, Patch::Merge(json!({"name": "Alicia"})), None)
+            .await;
+        // then
+        assert!(matches!(result, Err(AppError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_patch_stale_version() {
+        // given
+        let mut users = MockUserRepository::new();
+        users
+            .expect_find_by_id()
+            .returning(|id| Ok(Some(user_entity(id, "Alice", 3))));
+        users.expect_update_user().never();
+        let user_service = transactional_user_service(
+            MockUserRepository::new(),
+            mock_transactions(rolled_back_unit_of_work(users)),
+        );
+        // when
+        let result = user_service
+            .patch_user(
+                &actor(1),
+                1,
+                Patch::Merge(json!({"name": "Alicia"})),
+                Some(vec![2]),
+            )
+            .await;
+        // then
+        assert!(matches!(result, Err(AppError::PreconditionFailed)));
+    }
+
+    #[tokio::test]
+    async fn test_patch_other_user_is_forbidden() {
+        // given
+        let user_service = user_service(MockUserRepository::new());
+        // when
+        let result = user_service
+            .patch_user(&actor(1), 2, Patch::Merge(json!({"name": "Bobby"})), None)
+            .await;
+        // then
+        assert!(matches!(result, Err(AppError::Forbidden)));
     }
 
     #[tokio::test]
     async fn test_delete_user() {
         // given
-        let mut mock_user_repository = MockUserRepository::new();
-        mock_user_repository
-            .expect_delete_user()
-            .returning(|_| Ok(()));
-        let user_service = UserServiceImpl::new(Arc::new(mock_user_repository));
+        let mut unit_of_work = deleting_unit_of_work(1, Ok(()));
+        unit_of_work.expect_commit().times(1).returning(|| Ok(()));
+        let user_service =
+            transactional_user_service(MockUserRepository::new(), mock_transactions(unit_of_work));
+        // when
+        let result = user_service.delete_user(&actor(1), 1, None).await;
+        // then
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_rolls_back_when_revoking_fails() {
+        // given
+        let mut unit_of_work = deleting_unit_of_work(1, Err(AppError::InternalServerError));
+        unit_of_work.expect_commit().never();
+        unit_of_work.expect_rollback().times(1).returning(|| Ok(()));
+        let user_service =
+            transactional_user_service(MockUserRepository::new(), mock_transactions(unit_of_work));
+        // when
+        let result = user_service.delete_user(&actor(1), 1, None).await;
+        // then
+        assert!(matches!(result, Err(AppError::InternalServerError)));
+    }
+
+    #[tokio::test]
+    async fn test_restore_user() {
+        // given
+        let mut users = MockUserRepository::new();
+        users
+            .expect_restore_user()
+            .withf(|id| *id == 1)
+            .returning(|id| {
+                Ok(UserEntity {
+                    id,
+                    name: "Alice".to_string(),
+                    version: 3,
+                    deleted_at: None,
+                    created_at: chrono::Utc::now().naive_utc(),
+                    updated_at: chrono::Utc::now().naive_utc(),
+                })
+            });
+        let unit_of_work =
+            committed_unit_of_work(users, audit_log("restore", 1, 1), outbox("UserRestored", 1));
+        let user_service =
+            transactional_user_service(MockUserRepository::new(), mock_transactions(unit_of_work));
+        // when
+        let user = user_service.restore_user(&actor(1), 1).await.unwrap();
+        // then
+        assert_eq!(user.id, 1);
+        assert!(user.deleted_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_purge_deleted() {
+        // given
+        let mut users = MockUserRepository::new();
+        users
+            .expect_purge_deleted()
+            .withf(|deleted_before| {
+                let expected = chrono::Utc::now().naive_utc() - chrono::Duration::days(30);
+                (*deleted_before - expected).num_seconds().abs() < 5
+            })
+            .returning(|_| Ok(vec![4, 5]));
+        let mut audit = MockAuditRepository::new();
+        audit
+            .expect_record()
+            .withf(|event| {
+                event.actor_id.is_none()
+                    && event.action == "purge"
+                    && [4, 5].contains(&event.entity_id)
+            })
+            .times(2)
+            .returning(Ok);
+        let unit_of_work = committed_unit_of_work(
+            users,
+            Arc::new(audit),
+            Arc::new(MockOutboxRepository::new()),
+        );
+        let user_service =
+            transactional_user_service(MockUserRepository::new(), mock_transactions(unit_of_work));
+        // when
+        let purged = user_service
+            .purge_deleted(Duration::from_secs(30 * 24 * 60 * 60))
+            .await
+            .unwrap();
+        // then
+        assert_eq!(purged, 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_other_user_is_forbidden() {
+        // given
+        let user_service = user_service(MockUserRepository::new());
+        let user = User {
+            id: 2,
+            name: "Bob".to_string(),
+            version: 1,
+            deleted_at: None,
+            created_at: chrono::NaiveDateTime::parse_from_str(
+                "2021-01-01 00:00:00",
+                "%Y-%m-%d %H:%M:%S",
+            )
+            .unwrap(),
+            updated_at: chrono::NaiveDateTime::parse_from_str(
+                "2021-01-01 00:00:00",
+                "%Y-%m-%d %H:%M:%S",
+            )
+            .unwrap(),
+        };
+        // when
+        let result = user_service.update_user(&actor(1), user, None).await;
+        // then
+        assert!(matches!(result, Err(AppError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_delete_other_user_is_forbidden() {
+        // given
+        let user_service = user_service(MockUserRepository::new());
+        // when
+        let result = user_service.delete_user(&actor(1), 2, None).await;
+        // then
+        assert!(matches!(result, Err(AppError::Forbidden)));
+    }
+
+    #[tokio::test]
+    async fn test_admin_can_delete_other_user() {
+        // given
+        let mut unit_of_work = deleting_unit_of_work(2, Ok(()));
+        unit_of_work.expect_commit().times(1).returning(|| Ok(()));
+        let user_service =
+            transactional_user_service(MockUserRepository::new(), mock_transactions(unit_of_work));
+        let admin = Actor {
+            user_id: 1,
+            roles: vec![ADMIN_ROLE.to_string()],
+            permissions: vec![],
+        };
         // when
-        let _ = user_service.delete_user(1).await;
+        let result = user_service.delete_user(&admin, 2, None).await;
         // then
-        // no panic
+        assert!(result.is_ok());
     }
 }
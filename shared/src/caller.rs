@@ -0,0 +1,14 @@
+tokio::task_local! {
+    static CALLER: String;
+}
+
+/// Runs `f` on behalf of `caller`, e.g. the id of the authenticated user,
+/// as visible to [`current`].
+pub async fn scope<F: std::future::Future>(caller: String, f: F) -> F::Output {
+    CALLER.scope(caller, f).await
+}
+
+/// Returns who the current request is served for, if anyone.
+pub fn current() -> Option<String> {
+    CALLER.try_with(|caller| caller.clone()).ok()
+}
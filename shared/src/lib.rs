@@ -1,19 +1,108 @@
-use sqlx;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
 use thiserror::Error;
+use utoipa::ToSchema;
+use validation::FieldViolation;
+
+pub mod caller;
+pub mod query;
+pub mod request_id;
+pub mod settings;
+pub mod validation;
 
 #[derive(Error, Debug)]
 pub enum AppError {
+    #[error("{0}")]
+    BadRequest(String),
     #[error("Resource not found")]
     NotFound,
     #[error("Resource already exists")]
     Conflict,
+    #[error("Authentication required")]
+    Unauthorized,
+    #[error("Permission denied")]
+    Forbidden,
+    #[error("Resource has been modified")]
+    PreconditionFailed,
+    #[error("Unsupported media type")]
+    UnsupportedMediaType,
+    #[error("Request validation failed")]
+    Validation(Vec<FieldViolation>),
     #[error("Internal server error")]
     InternalServerError,
 }
 
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::Conflict => StatusCode::CONFLICT,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden => StatusCode::FORBIDDEN,
+            AppError::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            AppError::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::BadRequest(_) => "BAD_REQUEST",
+            AppError::NotFound => "NOT_FOUND",
+            AppError::Conflict => "CONFLICT",
+            AppError::Unauthorized => "UNAUTHORIZED",
+            AppError::Forbidden => "FORBIDDEN",
+            AppError::PreconditionFailed => "PRECONDITION_FAILED",
+            AppError::UnsupportedMediaType => "UNSUPPORTED_MEDIA_TYPE",
+            AppError::Validation(_) => "VALIDATION_FAILED",
+            AppError::InternalServerError => "INTERNAL_SERVER_ERROR",
+        }
+    }
+}
+
 impl From<sqlx::Error> for AppError {
     fn from(err: sqlx::Error) -> Self {
-        print!("{:?}", err);
-        AppError::InternalServerError
+        match &err {
+            sqlx::Error::RowNotFound => AppError::NotFound,
+            sqlx::Error::Database(db_err) if db_err.is_unique_violation() => AppError::Conflict,
+            _ => {
+                tracing::error!(error = %err, source = ?std::error::Error::source(&err), "database query failed");
+                AppError::InternalServerError
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorResponse {
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Rules the request broke, for `VALIDATION_FAILED`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub violations: Option<Vec<FieldViolation>>,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let body = ErrorResponse {
+            code: self.code().to_string(),
+            message: self.to_string(),
+            request_id: request_id::current(),
+            violations: match self {
+                AppError::Validation(violations) => Some(violations),
+                _ => None,
+            },
+        };
+        (status, Json(body)).into_response()
     }
 }
@@ -0,0 +1,359 @@
+//! List query model shared by every paginated endpoint: keyset pagination,
+//! multi-key sorting and an endpoint-specific filter.
+
+use crate::AppError;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_LIMIT: u32 = 20;
+pub const MAX_LIMIT: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Asc,
+    Desc,
+}
+
+/// A field a list can be sorted by.
+pub trait SortField: Sized + Copy + PartialEq + Send + Sync + 'static {
+    /// Unique field appended to every sort so that keyset pages are stable.
+    const TIE_BREAKER: Self;
+
+    /// Parses the name used in `?sort=`.
+    fn parse(name: &str) -> Option<Self>;
+
+    /// The name used in `?sort=`.
+    fn name(&self) -> &'static str;
+
+    /// The SQL column the field sorts on.
+    fn column(&self) -> &'static str;
+
+    /// The kind of [`CursorValue`] the field holds.
+    fn kind(&self) -> CursorKind;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortKey<F> {
+    pub field: F,
+    pub direction: Direction,
+}
+
+/// A value of one sort key, as stored in a [`Cursor`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CursorValue {
+    Int(i64),
+    Text(String),
+    Timestamp(chrono::NaiveDateTime),
+}
+
+impl CursorValue {
+    pub fn kind(&self) -> CursorKind {
+        match self {
+            CursorValue::Int(_) => CursorKind::Int,
+            CursorValue::Text(_) => CursorKind::Text,
+            CursorValue::Timestamp(_) => CursorKind::Timestamp,
+        }
+    }
+}
+
+/// The variant of a [`CursorValue`], without the value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorKind {
+    Int,
+    Text,
+    Timestamp,
+}
+
+/// Rows that can be paginated by the sort fields `F`.
+pub trait Keyed<F> {
+    fn key(&self, field: F) -> CursorValue;
+}
+
+/// Opaque position after the last row of a page: the sort key values of that
+/// row together with the sort they belong to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    sort: String,
+    pub values: Vec<CursorValue>,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursor is always serializable");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, AppError> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| AppError::BadRequest("Invalid cursor".to_string()))
+    }
+}
+
+/// What to list: a filter, a sort ending in the tie breaker, a page size and
+/// where the previous page ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListQuery<F, Q> {
+    pub filter: Q,
+    pub sort: Vec<SortKey<F>>,
+    pub limit: u32,
+    pub cursor: Option<Cursor>,
+}
+
+impl<F: SortField, Q> ListQuery<F, Q> {
+    /// Builds a query from raw request parameters, e.g. `sort = "name,-created_at"`.
+    pub fn new(
+        filter: Q,
+        sort: Option<&str>,
+        limit: Option<u32>,
+        cursor: Option<&str>,
+    ) -> Result<Self, AppError> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+        if limit == 0 || limit > MAX_LIMIT {
+            return Err(AppError::BadRequest(format!(
+                "limit must be between 1 and {MAX_LIMIT}"
+            )));
+        }
+        let sort: Vec<SortKey<F>> = parse_sort(sort.unwrap_or_default())?;
+        let cursor = cursor.map(Cursor::decode).transpose()?;
+        if let Some(cursor) = &cursor {
+            if cursor.sort != sort_signature(&sort) || cursor.values.len() != sort.len() {
+                return Err(AppError::BadRequest(
+                    "cursor does not belong to this sort".to_string(),
+                ));
+            }
+            if sort
+                .iter()
+                .zip(&cursor.values)
+                .any(|(key, value)| value.kind() != key.field.kind())
+            {
+                return Err(AppError::BadRequest("Invalid cursor".to_string()));
+            }
+        }
+        Ok(Self {
+            filter,
+            sort,
+            limit,
+            cursor,
+        })
+    }
+}
+
+/// Parses `name,-created_at` into sort keys and appends the tie breaker.
+fn parse_sort<F: SortField>(sort: &str) -> Result<Vec<SortKey<F>>, AppError> {
+    let mut keys: Vec<SortKey<F>> = Vec::new();
+    for part in sort
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let (direction, name) = match part.strip_prefix('-') {
+            Some(name) => (Direction::Desc, name),
+            None => (Direction::Asc, part.strip_prefix('+').unwrap_or(part)),
+        };
+        let field = F::parse(name)
+            .ok_or_else(|| AppError::BadRequest(format!("cannot sort by '{name}'")))?;
+        if keys.iter().any(|key| key.field == field) {
+            return Err(AppError::BadRequest(format!("'{name}' is sorted twice")));
+        }
+        keys.push(SortKey { field, direction });
+    }
+    if !keys.iter().any(|key| key.field == F::TIE_BREAKER) {
+        keys.push(SortKey {
+            field: F::TIE_BREAKER,
+            direction: Direction::Asc,
+        });
+    }
+    Ok(keys)
+}
+
+fn sort_signature<F: SortField>(sort: &[SortKey<F>]) -> String {
+    sort.iter()
+        .map(|key| match key.direction {
+            Direction::Asc => key.field.name().to_string(),
+            Direction::Desc => format!("-{}", key.field.name()),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// One page of a list and the cursor of the next one, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<Cursor>,
+    /// Number of rows matching the filter across all pages.
+    pub total: i64,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from up to `limit + 1` rows fetched in sort order; the
+    /// extra row only signals that there is a next page.
+    pub fn from_rows<F: SortField, Q>(mut rows: Vec<T>, query: &ListQuery<F, Q>, total: i64) -> Self
+    where
+        T: Keyed<F>,
+    {
+        let next_cursor = if rows.len() > query.limit as usize {
+            rows.truncate(query.limit as usize);
+            rows.last().map(|last| Cursor {
+                sort: sort_signature(&query.sort),
+                values: query.sort.iter().map(|key| last.key(key.field)).collect(),
+            })
+        } else {
+            None
+        };
+        Self {
+            items: rows,
+            next_cursor,
+            total,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            total: self.total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Field {
+        Id,
+        Name,
+    }
+
+    impl SortField for Field {
+        const TIE_BREAKER: Self = Field::Id;
+
+        fn parse(name: &str) -> Option<Self> {
+            match name {
+                "id" => Some(Field::Id),
+                "name" => Some(Field::Name),
+                _ => None,
+            }
+        }
+
+        fn name(&self) -> &'static str {
+            match self {
+                Field::Id => "id",
+                Field::Name => "name",
+            }
+        }
+
+        fn column(&self) -> &'static str {
+            self.name()
+        }
+
+        fn kind(&self) -> CursorKind {
+            match self {
+                Field::Id => CursorKind::Int,
+                Field::Name => CursorKind::Text,
+            }
+        }
+    }
+
+    struct Row(i64, &'static str);
+
+    impl Keyed<Field> for Row {
+        fn key(&self, field: Field) -> CursorValue {
+            match field {
+                Field::Id => CursorValue::Int(self.0),
+                Field::Name => CursorValue::Text(self.1.to_string()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_sort() {
+        // when
+        let query = ListQuery::<Field, ()>::new((), Some("-name"), None, None).unwrap();
+        // then
+        assert_eq!(query.limit, DEFAULT_LIMIT);
+        assert_eq!(
+            query.sort,
+            vec![
+                SortKey {
+                    field: Field::Name,
+                    direction: Direction::Desc
+                },
+                SortKey {
+                    field: Field::Id,
+                    direction: Direction::Asc
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_parameters() {
+        for (sort, limit, cursor) in [
+            (Some("email"), None, None),
+            (Some("name,-name"), None, None),
+            (None, Some(0), None),
+            (None, Some(MAX_LIMIT + 1), None),
+            (None, None, Some("not-a-cursor")),
+        ] {
+            // when
+            let result = ListQuery::<Field, ()>::new((), sort, limit, cursor);
+            // then
+            assert!(matches!(result, Err(AppError::BadRequest(_))));
+        }
+    }
+
+    #[test]
+    fn test_page_cursor_round_trip() {
+        // given
+        let query = ListQuery::<Field, ()>::new((), Some("name"), Some(2), None).unwrap();
+        let rows = vec![Row(3, "a"), Row(1, "b"), Row(2, "c")];
+        // when
+        let page = Page::from_rows(rows, &query, 3);
+        // then
+        assert_eq!(page.items.len(), 2);
+        let cursor = page.next_cursor.unwrap();
+        assert_eq!(
+            cursor.values,
+            vec![CursorValue::Text("b".to_string()), CursorValue::Int(1)]
+        );
+        let next =
+            ListQuery::<Field, ()>::new((), Some("name"), Some(2), Some(&cursor.encode())).unwrap();
+        assert_eq!(next.cursor, Some(cursor.clone()));
+        let other_sort =
+            ListQuery::<Field, ()>::new((), Some("-name"), Some(2), Some(&cursor.encode()));
+        assert!(matches!(other_sort, Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_forged_cursor_value_types() {
+        // given
+        let forged = Cursor {
+            sort: "name,id".to_string(),
+            values: vec![
+                CursorValue::Text("b".to_string()),
+                CursorValue::Text("1 OR 1=1".to_string()),
+            ],
+        };
+        // when
+        let result = ListQuery::<Field, ()>::new((), Some("name"), None, Some(&forged.encode()));
+        // then
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[test]
+    fn test_last_page_has_no_cursor() {
+        // given
+        let query = ListQuery::<Field, ()>::new((), None, Some(2), None).unwrap();
+        // when
+        let page = Page::from_rows(vec![Row(1, "a"), Row(2, "b")], &query, 2);
+        // then
+        assert_eq!(page.items.len(), 2);
+        assert!(page.next_cursor.is_none());
+    }
+}
@@ -0,0 +1,160 @@
+use crate::AppError;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// One rule a request payload broke.
+#[derive(Serialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct FieldViolation {
+    /// Path of the offending field, e.g. `name` or `tags[0]`.
+    pub field: String,
+    /// Machine-readable rule name, e.g. `length` or `required`.
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldViolation {
+    pub fn new(
+        field: impl Into<String>,
+        code: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            field: field.into(),
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Describes why the JSON value at `path` did not fit the target type.
+    ///
+    /// `path` is as reported by `serde_path_to_error`, with `.` for the root.
+    pub fn from_json(path: &str, err: &serde_json::Error) -> Self {
+        // serde_json appends the position, which means nothing to API clients
+        let message = err.to_string();
+        let message = message
+            .rsplit_once(" at line ")
+            .map_or(message.as_str(), |(message, _)| message);
+        let child = |field: &str| match path {
+            "." => field.to_string(),
+            parent => format!("{parent}.{field}"),
+        };
+        if let Some(field) = quoted(message, "missing field `") {
+            return Self::new(child(field), "required", "is required");
+        }
+        if let Some(field) = quoted(message, "unknown field `") {
+            // unlike missing fields, the path already ends at the unknown one
+            let field = if path == "." { field } else { path };
+            return Self::new(field, "unknown", "is not a known field");
+        }
+        let field = match path {
+            "." => "body",
+            field => field,
+        };
+        Self::new(field, "type", message)
+    }
+}
+
+fn quoted<'a>(message: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = message.strip_prefix(prefix)?;
+    rest.split_once('`').map(|(field, _)| field)
+}
+
+/// Collects domain rule violations into a single `AppError::Validation`.
+#[derive(Debug, Default)]
+pub struct Violations(Vec<FieldViolation>);
+
+impl Violations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a violation of `field` unless `valid` holds.
+    pub fn check(&mut self, valid: bool, field: &str, code: &str, message: &str) -> &mut Self {
+        if !valid {
+            self.0.push(FieldViolation::new(field, code, message));
+        }
+        self
+    }
+
+    /// Checks that `value` is not blank and at most `max` characters long.
+    pub fn text(&mut self, field: &str, value: &str, max: u64) -> &mut Self {
+        self.check(
+            !value.trim().is_empty(),
+            field,
+            "blank",
+            "must not be blank",
+        )
+        .check(
+            value.chars().count() as u64 <= max,
+            field,
+            "length",
+            &format!("must be at most {max} characters"),
+        )
+    }
+
+    pub fn into_result(self) -> Result<(), AppError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(self.0))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_violations() {
+        // given
+        let mut violations = Violations::new();
+        // when
+        violations
+            .text("name", "  ", 255)
+            .text("title", &"x".repeat(256), 255)
+            .text("content", "fine", 255);
+        // then
+        let Err(AppError::Validation(violations)) = violations.into_result() else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(
+            violations,
+            vec![
+                FieldViolation::new("name", "blank", "must not be blank"),
+                FieldViolation::new("title", "length", "must be at most 255 characters"),
+            ]
+        );
+        assert!(Violations::new().into_result().is_ok());
+    }
+
+    #[test]
+    fn test_from_json() {
+        #[derive(Debug, serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        #[allow(dead_code)]
+        struct Target {
+            name: String,
+        }
+        let violation = |json: &str, path: &str| {
+            let err = serde_json::from_str::<Target>(json).unwrap_err();
+            FieldViolation::from_json(path, &err)
+        };
+        assert_eq!(
+            violation("{}", "."),
+            FieldViolation::new("name", "required", "is required")
+        );
+        assert_eq!(
+            violation(r#"{"name":"a","id":1}"#, "id"),
+            FieldViolation::new("id", "unknown", "is not a known field")
+        );
+        assert_eq!(
+            violation(r#"{"name":1}"#, "name"),
+            FieldViolation::new(
+                "name",
+                "type",
+                "invalid type: integer `1`, expected a string"
+            )
+        );
+    }
+}